@@ -0,0 +1,595 @@
+use axum::Router;
+use axum::extract::{Json, Path, Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+use crate::config::{Config, IssuerConfig};
+use crate::credit_note;
+use crate::db::{Database, InvoiceCursor};
+use crate::error::AppError;
+use crate::models::{Client, Invoice, InvoiceFilter, InvoiceSort, Item, Payment, items_total};
+use crate::pdf::{self, PdfConfig};
+use crate::utils::generate_unique_id;
+
+/// Overrides `token` so it needn't be written to the config file.
+const TOKEN_VAR: &str = "INVOICE_RS_API_TOKEN";
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+/// OpenAPI description of the routes below, served at `/openapi.json`.
+pub const OPENAPI: &str = include_str!("../assets/openapi.json");
+
+/// Settings for `invoice-rs serve`, from the `[api]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Address to listen on; keep it on localhost unless behind a proxy.
+    pub bind: String,
+    token: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            token: String::new(),
+        }
+    }
+}
+
+impl ApiConfig {
+    pub fn token(&self) -> String {
+        std::env::var(TOKEN_VAR).unwrap_or_else(|_| self.token.clone())
+    }
+}
+
+/// Shared by all requests. The server opens its own database connection,
+/// which the webhook worker shares.
+#[derive(Clone)]
+pub struct ApiState {
+    db: Arc<Mutex<Database>>,
+    token: Arc<str>,
+    issuer: Arc<IssuerConfig>,
+    pdf: PdfConfig,
+}
+
+impl ApiState {
+    pub fn new(db: Database, token: &str, config: &Config) -> Self {
+        Self {
+            db: Arc::new(Mutex::new(db)),
+            token: token.into(),
+            issuer: Arc::new(config.issuer.clone()),
+            pdf: config.pdf.clone(),
+        }
+    }
+
+    pub fn db(&self) -> Arc<Mutex<Database>> {
+        self.db.clone()
+    }
+
+    /// Run `f` on the database off the async threads.
+    async fn with_db<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&Database) -> Result<T, ApiError> + Send + 'static,
+    ) -> Result<T, ApiError> {
+        let db = self.db.clone();
+        blocking(move || f(&db.lock().unwrap())).await
+    }
+}
+
+/// Run `f` off the async threads.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, ApiError> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+/// An error response: `{"error": "..."}` with a matching status code.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(what: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("{} not found", what))
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(e: AppError) -> Self {
+        let status = match e {
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::from(e).into()
+    }
+}
+
+impl From<crate::models::ValidationErrors> for ApiError {
+    fn from(e: crate::models::ValidationErrors) -> Self {
+        AppError::from(e).into()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// One page of a list; pass `next` as `after` to get the following page.
+#[derive(Serialize)]
+struct Page<T> {
+    data: Vec<T>,
+    next: Option<u32>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from up to `limit + 1` rows, the extra one only telling
+    /// whether there is more.
+    fn new(mut data: Vec<T>, limit: usize, id: impl Fn(&T) -> u32) -> Self {
+        let next = if data.len() > limit {
+            data.truncate(limit);
+            data.last().map(id)
+        } else {
+            None
+        };
+        Self { data, next }
+    }
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    limit: Option<usize>,
+    after: Option<u32>,
+}
+
+impl PageQuery {
+    fn limit(&self) -> usize {
+        page_limit(self.limit)
+    }
+}
+
+fn page_limit(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+// Paging fields are repeated rather than flattened, which would turn the
+// numbers into strings for the query parser.
+#[derive(Deserialize)]
+struct InvoiceQuery {
+    limit: Option<usize>,
+    after: Option<u32>,
+    status: Option<String>,
+    client: Option<u32>,
+    search: Option<String>,
+}
+
+/// Body of `POST /invoices` and `PUT /invoices/{id}`. A missing code is
+/// generated, and a missing total is the sum of the items.
+#[derive(Deserialize)]
+struct InvoiceInput {
+    #[serde(default)]
+    code: String,
+    client_id: u32,
+    #[serde(default)]
+    project_id: Option<u32>,
+    currency: String,
+    total: Option<f64>,
+    #[serde(default)]
+    discount: f64,
+    #[serde(default)]
+    tax: f64,
+    #[serde(default = "draft")]
+    status: String,
+    date: Option<String>,
+    #[serde(default)]
+    due_date: String,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+fn draft() -> String {
+    "draft".to_string()
+}
+
+impl InvoiceInput {
+    /// Build the invoice the TUI form would, then validate it the same way.
+    fn into_invoice(self, db: &Database, existing: Option<&Invoice>) -> ApiResult<Invoice> {
+        let client = db
+            .get_client(self.client_id)?
+            .ok_or_else(|| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "client not found"))?;
+        let code = if self.code.trim().is_empty() {
+            match existing {
+                Some(invoice) => invoice.code.clone(),
+                None => generate_unique_id(&db.get_invoice_codes()?),
+            }
+        } else {
+            self.code
+        };
+        let total = self.total.unwrap_or_else(|| items_total(&self.items));
+        let date = self
+            .date
+            .unwrap_or_else(|| Local::now().date_naive().to_string());
+        let mut invoice = Invoice {
+            id: existing.map_or(0, |invoice| invoice.id),
+            code,
+            client,
+            currency: self.currency,
+            total,
+            discount: self.discount,
+            tax: self.tax,
+            status: self.status,
+            date,
+            due_date: self.due_date,
+            paid: existing.map_or(0.0, |invoice| invoice.paid),
+            items: self.items,
+            ..Invoice::default()
+        };
+        if let Some(existing) = existing {
+            invoice.void_reason = existing.void_reason.clone();
+            invoice.credit_note_for = existing.credit_note_for.clone();
+        }
+        if let Some(project_id) = self.project_id {
+            let project = db
+                .get_projects()?
+                .into_iter()
+                .find(|project| project.id == project_id)
+                .ok_or_else(|| {
+                    ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "project not found")
+                })?;
+            invoice.project = Some(project);
+        }
+        let id = existing.map(|invoice| invoice.id);
+        invoice.validate(|code| db.invoice_code_exists(code, id).unwrap_or(false))?;
+        Ok(invoice)
+    }
+}
+
+#[derive(Deserialize)]
+struct PaymentInput {
+    amount: f64,
+    date: Option<String>,
+}
+
+/// All routes, behind token authentication except the schema.
+pub fn router(state: ApiState) -> Router {
+    let api = Router::new()
+        .route("/clients", get(list_clients).post(create_client))
+        .route("/clients/{id}", get(get_client))
+        .route("/invoices", get(list_invoices).post(create_invoice))
+        .route(
+            "/invoices/{id}",
+            get(get_invoice).put(update_invoice).delete(delete_invoice),
+        )
+        .route("/invoices/{id}/items", get(list_items).post(add_item))
+        .route(
+            "/invoices/{id}/payments",
+            get(list_payments).post(record_payment),
+        )
+        .route("/invoices/{id}/pdf", get(invoice_pdf))
+        .route("/invoices/{id}/credit-note", post(create_credit_note))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate));
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .merge(api)
+        .with_state(state)
+}
+
+/// Listen on `bind` until the process is stopped.
+pub fn serve(state: ApiState, bind: &str) -> Result<(), AppError> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(bind).await?;
+        println!("Listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, router(state)).await
+    })?;
+    Ok(())
+}
+
+async fn authenticate(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            ApiError::new(StatusCode::UNAUTHORIZED, "missing or wrong bearer token").into_response()
+        }
+    }
+}
+
+/// Compare without stopping at the first difference, so response times
+/// don't give the token away.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI)
+}
+
+async fn list_clients(
+    State(state): State<ApiState>,
+    Query(query): Query<PageQuery>,
+) -> ApiResult<Json<Page<Client>>> {
+    state
+        .with_db(move |db| {
+            let limit = query.limit();
+            let data = db.list_clients(limit + 1, query.after)?;
+            Ok(Json(Page::new(data, limit, |client| client.id)))
+        })
+        .await
+}
+
+async fn create_client(
+    State(state): State<ApiState>,
+    Json(client): Json<Client>,
+) -> ApiResult<(StatusCode, Json<Client>)> {
+    state
+        .with_db(move |db| {
+            client.validate()?;
+            if db
+                .get_all_clients()?
+                .iter()
+                .any(|other| other.email.eq_ignore_ascii_case(&client.email))
+            {
+                return Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    "a client with this email already exists",
+                ));
+            }
+            let id = db.add_client(&client)?;
+            Ok((StatusCode::CREATED, Json(Client { id, ..client })))
+        })
+        .await
+}
+
+async fn get_client(State(state): State<ApiState>, Path(id): Path<u32>) -> ApiResult<Json<Client>> {
+    state
+        .with_db(move |db| {
+            db.get_client(id)?
+                .map(Json)
+                .ok_or_else(|| ApiError::not_found("client"))
+        })
+        .await
+}
+
+/// Invoices without their items, newest first.
+async fn list_invoices(
+    State(state): State<ApiState>,
+    Query(query): Query<InvoiceQuery>,
+) -> ApiResult<Json<Page<Invoice>>> {
+    state
+        .with_db(move |db| {
+            let sort = InvoiceSort::default();
+            let cursor = match query.after {
+                Some(after) => {
+                    let invoice = db.get_invoice(after)?.ok_or_else(|| {
+                        ApiError::new(StatusCode::BAD_REQUEST, "unknown 'after' invoice")
+                    })?;
+                    Some(InvoiceCursor::new(&sort, &invoice))
+                }
+                None => None,
+            };
+            let filter = InvoiceFilter {
+                status: query.status,
+                client_id: query.client,
+                search: query.search,
+            };
+            let limit = page_limit(query.limit);
+            let data = db.list_invoices(&filter, sort, limit + 1, cursor.as_ref())?;
+            Ok(Json(Page::new(data, limit, |invoice| invoice.id)))
+        })
+        .await
+}
+
+async fn create_invoice(
+    State(state): State<ApiState>,
+    Json(input): Json<InvoiceInput>,
+) -> ApiResult<(StatusCode, Json<Invoice>)> {
+    state
+        .with_db(move |db| {
+            let invoice = input.into_invoice(db, None)?;
+            let id = db.add_invoice(&invoice)?;
+            Ok((StatusCode::CREATED, Json(find_invoice(db, id)?)))
+        })
+        .await
+}
+
+async fn get_invoice(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<Json<Invoice>> {
+    state
+        .with_db(move |db| Ok(Json(find_invoice(db, id)?)))
+        .await
+}
+
+async fn update_invoice(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+    Json(input): Json<InvoiceInput>,
+) -> ApiResult<Json<Invoice>> {
+    state
+        .with_db(move |db| {
+            let existing = find_draft(db, id)?;
+            let invoice = input.into_invoice(db, Some(&existing))?;
+            db.update_invoice(&invoice)?;
+            Ok(Json(find_invoice(db, id)?))
+        })
+        .await
+}
+
+async fn delete_invoice(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<StatusCode> {
+    state
+        .with_db(move |db| {
+            find_draft(db, id)?;
+            db.delete_invoice(id)?;
+            Ok(StatusCode::NO_CONTENT)
+        })
+        .await
+}
+
+async fn list_items(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<Json<Vec<Item>>> {
+    state
+        .with_db(move |db| Ok(Json(find_invoice(db, id)?.items)))
+        .await
+}
+
+/// Append an item and raise the total by its amount.
+async fn add_item(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+    Json(item): Json<Item>,
+) -> ApiResult<(StatusCode, Json<Invoice>)> {
+    state
+        .with_db(move |db| {
+            let mut invoice = find_draft(db, id)?;
+            invoice.total += item.amount();
+            invoice.items.push(item);
+            invoice.validate(|code| db.invoice_code_exists(code, Some(id)).unwrap_or(false))?;
+            db.update_invoice(&invoice)?;
+            Ok((StatusCode::CREATED, Json(find_invoice(db, id)?)))
+        })
+        .await
+}
+
+/// Add a draft credit note cancelling the issued invoice in full.
+async fn create_credit_note(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<(StatusCode, Json<Invoice>)> {
+    state
+        .with_db(move |db| {
+            find_invoice(db, id)?;
+            let date = Local::now().date_naive().to_string();
+            let note = credit_note::create(db, id, &date)?;
+            Ok((StatusCode::CREATED, Json(find_invoice(db, note.id)?)))
+        })
+        .await
+}
+
+async fn list_payments(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+) -> ApiResult<Json<Vec<Payment>>> {
+    state
+        .with_db(move |db| {
+            find_invoice(db, id)?;
+            Ok(Json(db.get_invoice_payments(id)?))
+        })
+        .await
+}
+
+/// Record a payment the way the TUI does, returning the updated invoice.
+async fn record_payment(
+    State(state): State<ApiState>,
+    Path(id): Path<u32>,
+    Json(input): Json<PaymentInput>,
+) -> ApiResult<(StatusCode, Json<Invoice>)> {
+    state
+        .with_db(move |db| {
+            find_invoice(db, id)?;
+            db.check_payable(id)
+                .map_err(|e| ApiError::new(StatusCode::CONFLICT, e.to_string()))?;
+            if !input.amount.is_finite() || input.amount <= 0.0 {
+                return Err(ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "amount must be more than zero",
+                ));
+            }
+            let date = input
+                .date
+                .unwrap_or_else(|| Local::now().date_naive().to_string());
+            db.record_payment(id, input.amount, &date, "")?;
+            Ok((StatusCode::CREATED, Json(find_invoice(db, id)?)))
+        })
+        .await
+}
+
+async fn invoice_pdf(State(state): State<ApiState>, Path(id): Path<u32>) -> ApiResult<Response> {
+    let issuer = state.issuer.clone();
+    let config = state.pdf.clone();
+    let (invoice, issued) = state
+        .with_db(move |db| {
+            let invoice = find_invoice(db, id)?;
+            let issued = pdf::issued_pdf(db, &invoice)?;
+            Ok((invoice, issued))
+        })
+        .await?;
+    let code = invoice.code.clone();
+    let bytes = match issued {
+        Some(bytes) => bytes,
+        None => {
+            // Chrome takes its time, so the database isn't held while it prints.
+            let (invoice, bytes) = blocking(move || {
+                let bytes = pdf::render_invoice_pdf(&invoice, &issuer, &config)?;
+                Ok((invoice, bytes))
+            })
+            .await?;
+            state
+                .with_db(move |db| Ok(pdf::keep_issued(db, &invoice, bytes)?))
+                .await?
+        }
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"invoice_{}.pdf\"", code),
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+fn find_invoice(db: &Database, id: u32) -> ApiResult<Invoice> {
+    db.get_invoice(id)?
+        .ok_or_else(|| ApiError::not_found("invoice"))
+}
+
+/// An invoice that may still be changed; issued ones are a conflict.
+fn find_draft(db: &Database, id: u32) -> ApiResult<Invoice> {
+    let invoice = find_invoice(db, id)?;
+    credit_note::check_editable(&invoice)
+        .map_err(|e| ApiError::new(StatusCode::CONFLICT, e.to_string()))?;
+    Ok(invoice)
+}
@@ -1,168 +1,1656 @@
-use crate::db::Database;
+use crate::audit;
+use crate::backup::{self, BackupConfig};
+use crate::bank::{self, BankConfig, Suggestion};
+use crate::config::{Config, IssuerConfig};
+use crate::credit_note;
+use crate::db::{Database, InvoiceCursor};
+use crate::dunning::{self, DunningConfig, PendingReminder};
+use crate::error::AppError;
+use crate::expense::{self, ExpenseConfig};
+use crate::keymap::{KeyAction, KeyContext, Keymap};
+use crate::mail::{self, MailConfig};
+use crate::project::{self, ProjectBudget};
+use crate::timesheet::{self, TimeConfig};
+use crate::transfer::{self, ImportKind};
+use crate::ubl;
 use crate::ui::layout::SelectedTab;
-use crate::ui::{invoice_form::InvoiceForm, modal::Modal, modal::ModalType};
+use crate::ui::{
+    client_form::ClientForm,
+    entry_form::{EntryForm, EntryKind},
+    import_wizard::{ImportWizard, WizardStep},
+    input::InputField,
+    invoice_form::InvoiceForm,
+    modal::{Action, Modal, ModalType},
+};
+use crate::webhook;
 use crate::{
-    models::{Client, Invoice},
-    pdf::generate_invoice_pdf,
+    models::{
+        AuditEntry, Client, DashboardStats, Expense, Invoice, InvoiceFilter, InvoiceSort, Reminder,
+        SentEmail, TimeEntry, ValidationErrors,
+    },
+    pdf::{PdfConfig, generate_invoice_pdf, generate_report_pdf},
+    report::{DateRange, Report, ReportKind},
 };
+use chrono::{Local, Months};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SORT_SETTING: &str = "invoice_sort";
+const PAGE_SIZE: usize = 100;
+// Pages kept in memory around the selection.
+const WINDOW_PAGES: usize = 3;
+const HALF_PAGE: usize = 10;
+const DASHBOARD_MONTHS: u32 = 12;
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 pub enum Mode {
     Normal,
     Editing,
+    Wizard,
     Modal(ModalType),
 }
 
 pub struct App {
     pub db: Arc<Mutex<Database>>,
+    /// Window of invoice summaries (no items) around the selection.
     pub invoices: Vec<Invoice>,
+    pub invoice_count: usize,
+    /// Fully loaded copy of the selected invoice.
+    pub detail: Option<Invoice>,
+    /// Emails sent for the invoice in `detail`, oldest first.
+    pub detail_emails: Vec<SentEmail>,
+    /// Reminders sent or skipped for the invoice in `detail`.
+    pub detail_reminders: Vec<Reminder>,
+    /// Dunning levels due for review on the Reminders tab.
+    pub reminders: Vec<PendingReminder>,
+    pub reminder_selected: usize,
+    /// Open bank payments and their suggested invoices on the Bank tab.
+    pub matches: Vec<Suggestion>,
+    pub match_selected: usize,
+    /// Time entries on the Time tab, unbilled first.
+    pub time_entries: Vec<TimeEntry>,
+    pub time_selected: usize,
+    /// Expenses on the Expenses tab, those still to be billed first.
+    pub expenses: Vec<Expense>,
+    pub expense_selected: usize,
+    /// Projects and their budget use, shown on the Clients tab.
+    pub projects: Vec<ProjectBudget>,
+    pub dashboard: DashboardStats,
+    pub report_kind: ReportKind,
+    pub report_range: DateRange,
+    pub report: Option<Report>,
     pub clients: Vec<Client>,
+    /// The highlighted client on the Clients tab.
+    pub client_selected: usize,
     pub selected: usize,
+    pub sort: InvoiceSort,
+    pub filter: InvoiceFilter,
     pub mode: Mode,
     pub current_tab: SelectedTab,
     pub form: Option<InvoiceForm>,
+    pub client_form: Option<ClientForm>,
+    pub entry_form: Option<EntryForm>,
+    pub wizard: Option<ImportWizard>,
     pub modal: Option<Modal>,
+    /// Persistent warning shown above the footer.
+    pub banner: Option<String>,
+    /// Short-lived message shown in the footer.
+    pub status: Option<String>,
+    pub keymap: Keymap,
+    pub backup: BackupConfig,
+    pub issuer: IssuerConfig,
+    pub pdf: PdfConfig,
+    pub mail: MailConfig,
+    pub dunning: DunningConfig,
+    pub time: TimeConfig,
+    pub expense: ExpenseConfig,
+    pub bank: BankConfig,
+    last_backup_check: Option<Instant>,
+    pub show_help: bool,
+    /// Title and entries of the change history overlay, while it's open.
+    pub history: Option<(String, Vec<AuditEntry>)>,
+    pub should_quit: bool,
     pub empty_invoice: Invoice,
-    pub empty_client: Client,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
     pub fn new() -> Self {
-        let db = match Database::new() {
-            Ok(db) => Arc::new(Mutex::new(db)),
-            Err(_) => {
-                // fallback (empty)
-                return Self {
-                    db: Arc::new(Mutex::new(Database::empty())),
-                    invoices: Vec::new(),
-                    clients: Vec::new(),
-                    selected: 0,
-                    mode: Mode::Normal,
-                    current_tab: SelectedTab::default(),
-                    form: None,
-                    modal: None,
-                    empty_client: Client::default(),
-                    empty_invoice: Invoice::default(),
-                };
+        let (config, mut config_errors) = match Config::load() {
+            Ok(config) => (config, Vec::new()),
+            Err(e) => (Config::default(), vec![e.to_string()]),
+        };
+
+        let (mut db, banner) = match Database::new(&config.backup) {
+            Ok(db) => (db, None),
+            Err(e) => {
+                let db = Database::in_memory().expect("Failed to create in-memory DB");
+                let banner = format!(
+                    "Could not open invoice-rs.db ({}). Using a temporary database; changes will not be saved.",
+                    e
+                );
+                (db, Some(banner))
             }
         };
+        db.set_issuer(&config.issuer);
+        let db = Arc::new(Mutex::new(db));
+        webhook::spawn(db.clone(), config.webhooks.clone());
+
+        let (keymap, key_errors) = Keymap::new(&config.keys);
+        config_errors.extend(key_errors);
 
-        let (clients, invoices) = {
+        let (clients, sort) = {
             let conn = db.lock().unwrap();
             let clients = conn.get_all_clients().unwrap_or_else(|_| Vec::new());
-            let invoices = conn.get_all_invoices().unwrap_or_else(|_| Vec::new());
-            (clients, invoices)
+            let sort = conn
+                .get_setting(SORT_SETTING)
+                .ok()
+                .flatten()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default();
+            (clients, sort)
         };
 
-        Self {
+        let mut app = Self {
             db,
-            invoices,
+            invoices: Vec::new(),
+            invoice_count: 0,
+            detail: None,
+            detail_emails: Vec::new(),
+            detail_reminders: Vec::new(),
+            reminders: Vec::new(),
+            reminder_selected: 0,
+            matches: Vec::new(),
+            match_selected: 0,
+            time_entries: Vec::new(),
+            time_selected: 0,
+            expenses: Vec::new(),
+            expense_selected: 0,
+            projects: Vec::new(),
+            dashboard: DashboardStats::default(),
+            report_kind: ReportKind::default(),
+            report_range: DateRange::default(),
+            report: None,
             clients,
+            client_selected: 0,
             selected: 0,
+            sort,
+            filter: InvoiceFilter::default(),
             mode: Mode::Normal,
             current_tab: SelectedTab::default(),
             form: None,
+            client_form: None,
+            entry_form: None,
+            wizard: None,
             modal: None,
-            empty_client: Client::default(),
+            banner,
+            status: None,
+            keymap,
+            backup: config.backup,
+            issuer: config.issuer,
+            pdf: config.pdf,
+            mail: config.mail,
+            dunning: config.dunning,
+            time: config.time,
+            expense: config.expenses,
+            bank: config.bank,
+            last_backup_check: None,
+            show_help: false,
+            history: None,
+            should_quit: false,
             empty_invoice: Invoice::default(),
+        };
+        app.reload_invoices();
+        if !config_errors.is_empty() {
+            config_errors.insert(0, "Some key bindings were ignored:".to_string());
+            app.show_error(AppError::Config(config_errors.join("\n")));
+        }
+        app
+    }
+
+    /// Periodic work run between events: scheduled database backups.
+    pub fn tick(&mut self) {
+        if self
+            .last_backup_check
+            .is_some_and(|checked| checked.elapsed() < BACKUP_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_backup_check = Some(Instant::now());
+        let result = backup::run_schedule(&self.db.lock().unwrap(), &self.backup);
+        match result {
+            Ok(Some(path)) => self.status = Some(format!("Backed up to {}", path.display())),
+            Ok(None) => {}
+            Err(e) => self.show_error(e),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        if self.show_help {
+            self.show_help = false;
+            return;
+        }
+        if self.history.is_some() {
+            self.history = None;
+            return;
+        }
+        if let Mode::Normal = self.mode {
+            // Any key dismisses the last status message
+            self.status = None;
+        }
+
+        let context = KeyContext::from(&self.mode);
+        match self.keymap.action(context, self.current_tab, &key) {
+            Some(action) => self.perform(action),
+            None => match (context, key.code) {
+                (KeyContext::Alert, _) => self.close_modals(),
+                (KeyContext::Editing | KeyContext::Prompt, KeyCode::Char(c))
+                    if !key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    self.type_char(c)
+                }
+                _ => {}
+            },
+        }
+    }
+
+    pub fn perform(&mut self, action: KeyAction) {
+        if let Mode::Wizard = self.mode {
+            return self.perform_wizard(action);
+        }
+        match action {
+            KeyAction::Quit => self.should_quit = true,
+            KeyAction::Help => self.show_help = true,
+            KeyAction::Down if self.on_reports() => self.select_report(self.report_kind.next()),
+            KeyAction::Up if self.on_reports() => self.select_report(self.report_kind.previous()),
+            KeyAction::Down if self.on_reminders() => self.move_reminder(1),
+            KeyAction::Up if self.on_reminders() => self.move_reminder(-1),
+            KeyAction::Down if self.on_bank() => self.move_match(1),
+            KeyAction::Up if self.on_bank() => self.move_match(-1),
+            KeyAction::Down if self.on_time() => self.move_time(1),
+            KeyAction::Up if self.on_time() => self.move_time(-1),
+            KeyAction::Down if self.on_expenses() => self.move_expense(1),
+            KeyAction::Up if self.on_expenses() => self.move_expense(-1),
+            KeyAction::Down if self.on_clients() => self.move_client(1),
+            KeyAction::Up if self.on_clients() => self.move_client(-1),
+            KeyAction::Down => self.next(),
+            KeyAction::Up => self.previous(),
+            KeyAction::Top => self.reload_invoices(),
+            KeyAction::Bottom => self.select_last(),
+            KeyAction::PageDown => self.move_by(HALF_PAGE as isize),
+            KeyAction::PageUp => self.move_by(-(HALF_PAGE as isize)),
+            KeyAction::NextTab => self.next_tab(),
+            KeyAction::PreviousTab => self.previous_tab(),
+            KeyAction::NewTimeEntry => self.start_entry(EntryForm::time_entry()),
+            KeyAction::NewExpense => self.start_entry(EntryForm::expense()),
+            KeyAction::NewClient => self.start_new_client(),
+            KeyAction::EditClient => self.start_edit_client(),
+            KeyAction::NewProject => self.start_new_project(),
+            KeyAction::New => self.start_new(),
+            KeyAction::Edit => self.start_edit(),
+            KeyAction::ExportReportPdf => self.export_report_pdf(),
+            KeyAction::ExportPdf => self.export_pdf(),
+            KeyAction::ExportCsv => self.export_csv(),
+            KeyAction::ExportUbl => self.export_ubl(),
+            KeyAction::SendEmail => self.open_email_modal(),
+            KeyAction::ApproveReminder => self.open_reminder_modal(true),
+            KeyAction::SkipReminder => self.open_reminder_modal(false),
+            KeyAction::AcceptMatch => self.open_match_modal(true),
+            KeyAction::RejectMatch => self.open_match_modal(false),
+            KeyAction::ImportStatement => self.open_statement_modal(),
+            KeyAction::Import => self.start_import(),
+            KeyAction::ReportRange => self.open_report_range_modal(),
+            KeyAction::DeleteTimeEntry => self.open_delete_time_modal(),
+            KeyAction::DeleteExpense => self.open_delete_expense_modal(),
+            KeyAction::Delete => self.open_delete_modal(),
+            KeyAction::Void => self.open_void_modal(),
+            KeyAction::CreditNote => self.open_credit_note_modal(),
+            KeyAction::RecordPayment => self.open_payment_modal(),
+            KeyAction::ToggleTimer => self.toggle_timer(),
+            KeyAction::BillExpenses => self.open_bill_expenses_modal(),
+            KeyAction::BillTime => self.open_bill_time_modal(),
+            KeyAction::History => self.open_history(),
+            KeyAction::SortColumn => self.next_sort_column(),
+            KeyAction::ReverseSort => self.toggle_sort_direction(),
+            KeyAction::NextField => match (&self.mode, &mut self.form, &mut self.modal) {
+                (Mode::Editing, Some(form), _) => form.next_field(),
+                (Mode::Editing, ..) => {
+                    self.client_form.iter_mut().for_each(ClientForm::next_field);
+                    self.entry_form.iter_mut().for_each(EntryForm::next_field);
+                }
+                (Mode::Modal(_), _, Some(modal)) => modal.next_field(),
+                _ => {}
+            },
+            KeyAction::PreviousField => match (&self.mode, &mut self.form, &mut self.modal) {
+                (Mode::Editing, Some(form), _) => form.previous_field(),
+                (Mode::Editing, ..) => {
+                    self.client_form
+                        .iter_mut()
+                        .for_each(ClientForm::previous_field);
+                    self.entry_form
+                        .iter_mut()
+                        .for_each(EntryForm::previous_field);
+                }
+                (Mode::Modal(_), _, Some(modal)) => modal.previous_field(),
+                _ => {}
+            },
+            KeyAction::Backspace => self.edit_input(InputField::backspace),
+            KeyAction::DeleteForward => self.edit_input(InputField::delete),
+            KeyAction::DeleteWord => self.edit_input(InputField::delete_word),
+            KeyAction::CursorLeft => self.edit_input(InputField::move_left),
+            KeyAction::CursorRight => self.edit_input(InputField::move_right),
+            KeyAction::CursorHome => self.edit_input(InputField::move_home),
+            KeyAction::CursorEnd => self.edit_input(InputField::move_end),
+            KeyAction::Save => self.open_save_modal(),
+            KeyAction::Submit => self.submit_modal(),
+            KeyAction::Cancel => match self.mode {
+                Mode::Editing => self.cancel_form(),
+                _ => self.close_modals(),
+            },
+        }
+    }
+
+    /// The text input receiving keystrokes in the current mode.
+    fn active_input(&mut self) -> Option<&mut InputField> {
+        match (&self.mode, &mut self.form, &mut self.modal) {
+            (Mode::Editing, Some(form), _) => Some(form.focused_mut()),
+            (Mode::Editing, ..) => match &mut self.client_form {
+                Some(form) => Some(form.focused_mut()),
+                None => self.entry_form.as_mut().map(EntryForm::focused_mut),
+            },
+            (Mode::Wizard, _, _) => self
+                .wizard
+                .as_mut()
+                .filter(|wizard| wizard.step == WizardStep::File)
+                .map(|wizard| &mut wizard.path),
+            (Mode::Modal(ModalType::Prompt), _, Some(modal)) => Some(&mut modal.input),
+            _ => None,
+        }
+    }
+
+    fn edit_input(&mut self, edit: fn(&mut InputField)) {
+        if let Some(input) = self.active_input() {
+            edit(input);
+        }
+    }
+
+    fn type_char(&mut self, c: char) {
+        if let Some(input) = self.active_input() {
+            input.insert(c);
+        }
+    }
+
+    /// Insert bracketed-paste text into the active input.
+    pub fn paste(&mut self, text: &str) {
+        if let Some(input) = self.active_input() {
+            input.insert_str(text);
         }
     }
 
     pub fn next_tab(&mut self) {
         self.current_tab = self.current_tab.next();
+        self.load_tab();
     }
 
     pub fn previous_tab(&mut self) {
         self.current_tab = self.current_tab.previous();
+        self.load_tab();
+    }
+
+    /// Refresh the data shown by tabs that aren't kept up to date by edits.
+    fn load_tab(&mut self) {
+        match self.current_tab {
+            SelectedTab::Clients => self.load_projects(),
+            SelectedTab::Dashboard => self.load_dashboard(),
+            SelectedTab::Reports => self.load_report(),
+            SelectedTab::Reminders => self.load_reminders(),
+            SelectedTab::Bank => self.load_matches(),
+            SelectedTab::Time => self.load_time(),
+            SelectedTab::Expenses => self.load_expenses(),
+            _ => {}
+        }
+    }
+
+    fn load_dashboard(&mut self) {
+        let today = Local::now().date_naive();
+        let months: Vec<String> = (0..DASHBOARD_MONTHS)
+            .rev()
+            .filter_map(|back| today.checked_sub_months(Months::new(back)))
+            .map(|date| date.format("%Y-%m").to_string())
+            .collect();
+        let result = self
+            .db
+            .lock()
+            .unwrap()
+            .dashboard(&months, &today.to_string());
+        match result {
+            Ok(stats) => self.dashboard = stats,
+            Err(e) => self.show_error(e.into()),
+        }
+    }
+
+    fn on_reports(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Reports)
+    }
+
+    fn select_report(&mut self, kind: ReportKind) {
+        self.report_kind = kind;
+        self.load_report();
+    }
+
+    fn load_report(&mut self) {
+        let result = Report::generate(
+            &self.db.lock().unwrap(),
+            self.report_kind,
+            self.report_range,
+        );
+        match result {
+            Ok(report) => self.report = Some(report),
+            Err(e) => {
+                self.report = None;
+                self.show_error(e);
+            }
+        }
+    }
+
+    fn on_reminders(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Reminders)
+    }
+
+    fn load_reminders(&mut self) {
+        let today = Local::now().date_naive();
+        let result = dunning::pending(&self.db.lock().unwrap(), &self.dunning, today);
+        match result {
+            Ok(reminders) => self.reminders = reminders,
+            Err(e) => {
+                self.reminders.clear();
+                self.show_error(e);
+            }
+        }
+        self.reminder_selected = self
+            .reminder_selected
+            .min(self.reminders.len().saturating_sub(1));
+    }
+
+    fn move_reminder(&mut self, delta: isize) {
+        self.reminder_selected = self
+            .reminder_selected
+            .saturating_add_signed(delta)
+            .min(self.reminders.len().saturating_sub(1));
+    }
+
+    /// Ask before sending (`approve`) or skipping the selected reminder.
+    pub fn open_reminder_modal(&mut self, approve: bool) {
+        if !self.on_reminders() {
+            return;
+        }
+        let Some(reminder) = self.reminders.get(self.reminder_selected) else {
+            return;
+        };
+        let Some(level) = self.dunning.levels.get(reminder.level) else {
+            return;
+        };
+        let inv = &reminder.invoice;
+        let modal = if approve {
+            let mut content = format!(
+                "Send {} for invoice {} to {}?",
+                level.name, inv.code, inv.client.email
+            );
+            if reminder.fees() > 0.0 {
+                content.push_str(&format!(
+                    " {:.2} {} in fees will be added.",
+                    reminder.fees(),
+                    inv.currency
+                ));
+            }
+            if self.mail.dry_run {
+                content.push_str(" (dry run)");
+            }
+            Modal::confirm(content, Action::SendReminder(inv.id))
+        } else {
+            let content = format!("Skip {} for invoice {}?", level.name, inv.code);
+            Modal::confirm(content, Action::SkipReminder(inv.id))
+        };
+        self.open_modal(modal);
+    }
+
+    fn send_reminder(&self, id: u32, approve: bool) -> Result<String, AppError> {
+        let reminder = self
+            .reminders
+            .iter()
+            .find(|reminder| reminder.invoice.id == id)
+            .ok_or_else(|| AppError::Validation("Reminder not found.".to_string()))?;
+        let db = self.db.lock().unwrap();
+        if approve {
+            dunning::approve(&db, reminder, &self.dunning, &self.mail, &self.issuer)
+        } else {
+            dunning::skip(&db, reminder, &self.dunning)
+        }
+    }
+
+    fn on_bank(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Bank)
+    }
+
+    fn load_matches(&mut self) {
+        let result = bank::suggest(&self.db.lock().unwrap());
+        match result {
+            Ok(matches) => self.matches = matches,
+            Err(e) => {
+                self.matches.clear();
+                self.show_error(e);
+            }
+        }
+        self.match_selected = self
+            .match_selected
+            .min(self.matches.len().saturating_sub(1));
+    }
+
+    fn move_match(&mut self, delta: isize) {
+        self.match_selected = self
+            .match_selected
+            .saturating_add_signed(delta)
+            .min(self.matches.len().saturating_sub(1));
+    }
+
+    /// Ask before recording (`accept`) or setting aside the selected payment.
+    pub fn open_match_modal(&mut self, accept: bool) {
+        let Some(suggestion) = self.matches.get(self.match_selected) else {
+            return;
+        };
+        let transaction = &suggestion.transaction;
+        let modal = if accept {
+            let Some(inv) = &suggestion.invoice else {
+                self.show_error(AppError::Validation(
+                    "No invoice matches this payment.".to_string(),
+                ));
+                return;
+            };
+            let content = format!(
+                "Record {:.2} {} from {} as payment for invoice {} ({})?",
+                transaction.amount,
+                transaction.currency,
+                transaction.name,
+                inv.code,
+                inv.client.name
+            );
+            Modal::confirm(content, Action::AcceptMatch(transaction.id))
+        } else {
+            let content = format!(
+                "Set aside {:.2} {} from {}? It won't be matched again.",
+                transaction.amount, transaction.currency, transaction.name
+            );
+            Modal::confirm(content, Action::RejectMatch(transaction.id))
+        };
+        self.open_modal(modal);
+    }
+
+    fn resolve_match(&self, id: u32, accept: bool) -> Result<String, AppError> {
+        let suggestion = self
+            .matches
+            .iter()
+            .find(|suggestion| suggestion.transaction.id == id)
+            .ok_or_else(|| AppError::Validation("Bank payment not found.".to_string()))?;
+        let db = self.db.lock().unwrap();
+        if accept {
+            bank::accept(&db, suggestion)
+        } else {
+            bank::reject(&db, suggestion)
+        }
+    }
+
+    pub fn open_statement_modal(&mut self) {
+        self.open_modal(Modal::prompt(
+            "Statement file (CAMT.053, OFX or CSV):".to_string(),
+            Action::ImportStatement,
+        ));
+    }
+
+    fn on_time(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Time)
+    }
+
+    fn load_time(&mut self) {
+        let result = self.db.lock().unwrap().get_time_entries();
+        match result {
+            Ok(entries) => self.time_entries = entries,
+            Err(e) => {
+                self.time_entries.clear();
+                self.show_error(e.into());
+            }
+        }
+        self.time_selected = self
+            .time_selected
+            .min(self.time_entries.len().saturating_sub(1));
+    }
+
+    fn move_time(&mut self, delta: isize) {
+        self.time_selected = self
+            .time_selected
+            .saturating_add_signed(delta)
+            .min(self.time_entries.len().saturating_sub(1));
+    }
+
+    /// Stop the running timer, or ask what to start one for.
+    pub fn toggle_timer(&mut self) {
+        let result = self.db.lock().unwrap().running_timer();
+        match result {
+            Ok(Some(_)) => {
+                let result = timesheet::stop(&self.db.lock().unwrap(), Local::now());
+                if self.on_time() {
+                    self.load_time();
+                }
+                match result {
+                    Ok(message) => self.status = Some(message),
+                    Err(e) => self.show_error(e),
+                }
+            }
+            Ok(None) => self.start_entry(EntryForm::timer()),
+            Err(e) => self.show_error(e.into()),
+        }
+    }
+
+    /// Add the time entry or start the timer `form` describes, or show
+    /// its problems on the form and return `None`.
+    fn add_time_entry(
+        &self,
+        form: &mut EntryForm,
+        timer: bool,
+    ) -> Result<Option<String>, AppError> {
+        let db = self.db.lock().unwrap();
+        let values = timesheet::ENTRY_FIELDS.map(|key| form.value(key));
+        let entry = match timesheet::validate_entry(
+            values,
+            &self.clients,
+            &db.get_projects()?,
+            &self.time,
+            timer,
+        ) {
+            Ok(entry) => entry,
+            Err(errors) => {
+                form.show_errors(&errors);
+                return Ok(None);
+            }
+        };
+        if timer {
+            return timesheet::start(&db, &entry).map(Some);
+        }
+        db.add_time_entry(&entry)?;
+        Ok(Some(format!(
+            "Added {} h for {}",
+            timesheet::format_minutes(entry.minutes),
+            entry.client.name
+        )))
+    }
+
+    /// Ask before billing the selected entry's client for all their unbilled time.
+    pub fn open_bill_time_modal(&mut self) {
+        if !self.on_time() {
+            return;
+        }
+        let Some(selected) = self.time_entries.get(self.time_selected) else {
+            return;
+        };
+        let client = &selected.client;
+        let unbilled: Vec<&TimeEntry> = self
+            .time_entries
+            .iter()
+            .filter(|entry| {
+                entry.client.id == client.id && entry.invoice_id.is_none() && !entry.is_running()
+            })
+            .collect();
+        if unbilled.is_empty() {
+            self.show_error(AppError::Validation(format!(
+                "{} has no unbilled time.",
+                client.name
+            )));
+            return;
+        }
+        let minutes: u32 = unbilled.iter().map(|entry| entry.minutes).sum();
+        let amount: f64 = unbilled.iter().map(|entry| entry.amount()).sum();
+        let content = format!(
+            "Bill {} h ({:.2} {}) from {} entries for {} on new draft invoices, one per project?",
+            timesheet::format_minutes(minutes),
+            amount,
+            self.time.currency,
+            unbilled.len(),
+            client.name
+        );
+        self.open_modal(Modal::confirm(content, Action::BillTime(client.id)));
+    }
+
+    pub fn open_delete_time_modal(&mut self) {
+        let Some(entry) = self.time_entries.get(self.time_selected) else {
+            return;
+        };
+        if entry.invoice_id.is_some() {
+            self.show_error(AppError::Validation(
+                "Billed time can't be deleted. Delete its draft invoice first.".to_string(),
+            ));
+            return;
+        }
+        let content = format!(
+            "Delete {} h for {} ({})?",
+            timesheet::format_minutes(entry.minutes),
+            entry.client.name,
+            entry.description
+        );
+        self.open_modal(Modal::confirm(content, Action::DeleteTimeEntry(entry.id)));
+    }
+
+    fn on_clients(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Clients)
+    }
+
+    fn load_projects(&mut self) {
+        let result = project::budgets(&self.db.lock().unwrap());
+        match result {
+            Ok(projects) => self.projects = projects,
+            Err(e) => {
+                self.projects.clear();
+                self.show_error(e);
+            }
+        }
+    }
+
+    pub fn start_new_client(&mut self) {
+        self.mode = Mode::Editing;
+        self.client_form = Some(ClientForm::new());
+    }
+
+    fn move_client(&mut self, delta: isize) {
+        self.client_selected = self
+            .client_selected
+            .saturating_add_signed(delta)
+            .min(self.clients.len().saturating_sub(1));
+    }
+
+    pub fn start_edit_client(&mut self) {
+        let Some(client) = self.clients.get(self.client_selected) else {
+            return;
+        };
+        self.mode = Mode::Editing;
+        self.client_form = Some(ClientForm::from_client(client));
+    }
+
+    fn reload_clients(&mut self) {
+        self.clients = self
+            .db
+            .lock()
+            .unwrap()
+            .get_all_clients()
+            .unwrap_or_default();
+        self.client_selected = self
+            .client_selected
+            .min(self.clients.len().saturating_sub(1));
+    }
+
+    /// Open the form for a new project of the highlighted client.
+    pub fn start_new_project(&mut self) {
+        let Some(client) = self.clients.get(self.client_selected) else {
+            return;
+        };
+        self.start_entry(EntryForm::project(client));
+    }
+
+    /// Add the project `form` describes, or show its problems on the form
+    /// and return `None`.
+    fn add_project(
+        &self,
+        form: &mut EntryForm,
+        client_id: u32,
+    ) -> Result<Option<String>, AppError> {
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.id == client_id)
+            .ok_or_else(|| AppError::Validation("Client not found.".to_string()))?;
+        let db = self.db.lock().unwrap();
+        let values = project::PROJECT_FIELDS.map(|key| form.value(key));
+        let projects = db.get_projects()?;
+        let result = project::validate_project(values, client).and_then(|new| {
+            let taken = projects
+                .iter()
+                .any(|project| project.client_id == client_id && project.name == new.name);
+            if taken {
+                let mut errors = ValidationErrors::default();
+                errors.add("name", "Already used by another of the client's projects");
+                return Err(errors);
+            }
+            Ok(new)
+        });
+        let new = match result {
+            Ok(new) => new,
+            Err(errors) => {
+                form.show_errors(&errors);
+                return Ok(None);
+            }
+        };
+        project::add(&db, &new)?;
+        Ok(Some(format!(
+            "Added project {} for {}",
+            new.name, client.name
+        )))
+    }
+
+    fn on_expenses(&self) -> bool {
+        matches!(self.current_tab, SelectedTab::Expenses)
+    }
+
+    fn load_expenses(&mut self) {
+        let result = self.db.lock().unwrap().get_expenses();
+        match result {
+            Ok(expenses) => self.expenses = expenses,
+            Err(e) => {
+                self.expenses.clear();
+                self.show_error(e.into());
+            }
+        }
+        self.expense_selected = self
+            .expense_selected
+            .min(self.expenses.len().saturating_sub(1));
+    }
+
+    fn move_expense(&mut self, delta: isize) {
+        self.expense_selected = self
+            .expense_selected
+            .saturating_add_signed(delta)
+            .min(self.expenses.len().saturating_sub(1));
+    }
+
+    /// Add the expense `form` describes, or show its problems on the form
+    /// and return `None`.
+    fn add_expense(&self, form: &mut EntryForm) -> Result<Option<String>, AppError> {
+        let db = self.db.lock().unwrap();
+        let values = expense::EXPENSE_FIELDS.map(|key| form.value(key));
+        let expense = match expense::validate_expense(
+            values,
+            &self.clients,
+            &db.get_projects()?,
+            &self.expense,
+        ) {
+            Ok(expense) => expense,
+            Err(errors) => {
+                form.show_errors(&errors);
+                return Ok(None);
+            }
+        };
+        db.add_expense(&expense)?;
+        Ok(Some(format!(
+            "Added {:.2} {} from {}",
+            expense.amount, expense.currency, expense.vendor
+        )))
+    }
+
+    /// Ask for the markup before billing the selected expense's client for
+    /// all their billable expenses.
+    pub fn open_bill_expenses_modal(&mut self) {
+        let Some(selected) = self.expenses.get(self.expense_selected) else {
+            return;
+        };
+        let Some(client) = &selected.client else {
+            self.show_error(AppError::Validation(
+                "This expense has no client to bill.".to_string(),
+            ));
+            return;
+        };
+        let count = self
+            .expenses
+            .iter()
+            .filter(|expense| {
+                expense.billable
+                    && !expense.is_billed()
+                    && expense.client.as_ref().is_some_and(|c| c.id == client.id)
+            })
+            .count();
+        if count == 0 {
+            self.show_error(AppError::Validation(format!(
+                "{} has no billable expenses.",
+                client.name
+            )));
+            return;
+        }
+        let mut modal = Modal::prompt(
+            format!(
+                "Markup % on {} expense(s) for {}, added to their draft invoice:",
+                count, client.name
+            ),
+            Action::BillExpenses(client.id),
+        );
+        modal.input.set_value(&self.expense.markup.to_string());
+        self.open_modal(modal);
+    }
+
+    fn bill_expenses(&self, client_id: u32, input: &str) -> Result<String, AppError> {
+        let markup = expense::parse_markup(input, &self.expense)?;
+        expense::bill(
+            &self.db.lock().unwrap(),
+            client_id,
+            markup,
+            &self.expense,
+            Local::now().date_naive(),
+        )
+    }
+
+    pub fn open_delete_expense_modal(&mut self) {
+        let Some(expense) = self.expenses.get(self.expense_selected) else {
+            return;
+        };
+        if expense.is_billed() {
+            self.show_error(AppError::Validation(
+                "Billed expenses can't be deleted.".to_string(),
+            ));
+            return;
+        }
+        let content = format!(
+            "Delete {:.2} {} from {}?",
+            expense.amount, expense.currency, expense.vendor
+        );
+        self.open_modal(Modal::confirm(content, Action::DeleteExpense(expense.id)));
+    }
+
+    pub fn open_report_range_modal(&mut self) {
+        if !self.on_reports() {
+            return;
+        }
+        let mut modal = Modal::prompt(
+            "Report dates (from..to):".to_string(),
+            Action::SetReportRange,
+        );
+        modal.input.set_value(&self.report_range.to_string());
+        self.open_modal(modal);
+    }
+
+    fn set_report_range(&mut self, input: &str) -> Result<String, AppError> {
+        self.report_range = input.parse()?;
+        self.load_report();
+        Ok(format!("Showing {}", self.report_range))
+    }
+
+    pub fn export_report_pdf(&mut self) {
+        let Some(report) = &self.report else {
+            return;
+        };
+        match generate_report_pdf(report) {
+            Ok(path) => self.status = Some(format!("Saved {}", path)),
+            Err(e) => self.show_error(e),
+        }
+    }
+
+    /// Export the current report, the filtered invoices or the clients,
+    /// depending on the tab.
+    pub fn export_csv(&mut self) {
+        let today = Local::now().date_naive();
+        let result = match self.current_tab {
+            SelectedTab::Reports => match &self.report {
+                Some(report) => report.export_csv(),
+                None => return,
+            },
+            SelectedTab::Clients => {
+                let path = format!("clients_{}.csv", today);
+                let db = self.db.lock().unwrap();
+                transfer::export_to(&db, ImportKind::Clients, &self.filter, self.sort, &path)
+                    .map(|_| path)
+            }
+            _ => {
+                let path = format!("invoices_{}.csv", today);
+                let db = self.db.lock().unwrap();
+                transfer::export_to(&db, ImportKind::Invoices, &self.filter, self.sort, &path)
+                    .map(|_| path)
+            }
+        };
+        match result {
+            Ok(path) => self.status = Some(format!("Saved {}", path)),
+            Err(e) => self.show_error(e),
+        }
+    }
+
+    pub fn start_import(&mut self) {
+        let kind = match self.current_tab {
+            SelectedTab::Clients => ImportKind::Clients,
+            _ => ImportKind::Invoices,
+        };
+        self.wizard = Some(ImportWizard::new(kind));
+        self.mode = Mode::Wizard;
+    }
+
+    fn perform_wizard(&mut self, action: KeyAction) {
+        let Some(wizard) = self.wizard.as_mut() else {
+            self.mode = Mode::Normal;
+            return;
+        };
+        let on_file = wizard.step == WizardStep::File;
+        match action {
+            KeyAction::Help => self.show_help = true,
+            KeyAction::NextField => wizard.next_row(),
+            KeyAction::PreviousField => wizard.previous_row(),
+            KeyAction::CursorLeft if !on_file => wizard.cycle_field(false),
+            KeyAction::CursorRight if !on_file => wizard.cycle_field(true),
+            KeyAction::Submit => self.advance_wizard(),
+            KeyAction::Cancel => {
+                let still_open = wizard.back();
+                if !still_open {
+                    self.wizard = None;
+                    self.mode = Mode::Normal;
+                }
+            }
+            KeyAction::Backspace => self.edit_input(InputField::backspace),
+            KeyAction::DeleteForward => self.edit_input(InputField::delete),
+            KeyAction::DeleteWord => self.edit_input(InputField::delete_word),
+            KeyAction::CursorLeft => self.edit_input(InputField::move_left),
+            KeyAction::CursorRight => self.edit_input(InputField::move_right),
+            KeyAction::CursorHome => self.edit_input(InputField::move_home),
+            KeyAction::CursorEnd => self.edit_input(InputField::move_end),
+            _ => {}
+        }
+    }
+
+    /// Move the import wizard on: read the file, run the dry run, then import.
+    fn advance_wizard(&mut self) {
+        let Some(wizard) = self.wizard.as_mut() else {
+            return;
+        };
+        let db = self.db.lock().unwrap();
+        let result = match (wizard.step, &wizard.file, &wizard.mapping, &wizard.preview) {
+            (WizardStep::File, ..) => wizard.load_file().map(|_| None),
+            (WizardStep::Mapping, Some(file), Some(mapping), _) => {
+                transfer::preview(&db, file, mapping).map(|preview| {
+                    wizard.show_preview(preview);
+                    None
+                })
+            }
+            (WizardStep::Preview, .., Some(preview)) => {
+                transfer::commit(&db, preview).map(|count| Some((count, wizard.kind)))
+            }
+            _ => Ok(None),
+        };
+        drop(db);
+
+        match result {
+            Ok(Some((count, kind))) => {
+                self.wizard = None;
+                self.mode = Mode::Normal;
+                self.reload_clients();
+                self.reload_invoices();
+                self.status = Some(format!("Imported {} {}", count, kind));
+            }
+            Ok(None) => {}
+            Err(e) => self.show_error(e),
+        }
     }
 
     pub fn next(&mut self) {
+        self.move_by(1);
+    }
+
+    pub fn previous(&mut self) {
+        self.move_by(-1);
+    }
+
+    /// Move the selection by `delta` rows, paging in invoices as needed.
+    pub fn move_by(&mut self, delta: isize) {
+        let start = self.selected_id();
+        for _ in 0..delta.unsigned_abs() {
+            let moved = if delta > 0 {
+                self.step_down()
+            } else {
+                self.step_up()
+            };
+            if !moved {
+                break;
+            }
+        }
+        if self.selected_id() != start {
+            self.load_detail();
+        }
+    }
+
+    fn selected_id(&self) -> Option<u32> {
+        self.invoices.get(self.selected).map(|inv| inv.id)
+    }
+
+    fn step_down(&mut self) -> bool {
+        if self.selected + 1 >= self.invoices.len() {
+            self.load_next_page();
+        }
         if self.selected + 1 < self.invoices.len() {
             self.selected += 1;
+            true
+        } else {
+            false
         }
     }
 
-    pub fn previous(&mut self) {
+    fn step_up(&mut self) -> bool {
+        if self.selected == 0 {
+            self.load_previous_page();
+        }
         if self.selected > 0 {
             self.selected -= 1;
+            true
+        } else {
+            false
         }
     }
 
-    pub fn selected_invoice(&self) -> &Invoice {
-        &self
+    /// Jump to the last invoice by fetching the final page in reverse.
+    pub fn select_last(&mut self) {
+        let mut page = self
+            .db
+            .lock()
+            .unwrap()
+            .list_invoices(&self.filter, self.sort.reversed(), PAGE_SIZE, None)
+            .unwrap_or_default();
+        page.reverse();
+        self.invoices = page;
+        self.selected = self.invoices.len().saturating_sub(1);
+        self.load_detail();
+    }
+
+    pub fn next_sort_column(&mut self) {
+        self.sort.column = self.sort.column.next();
+        self.apply_sort();
+    }
+
+    pub fn toggle_sort_direction(&mut self) {
+        self.sort.direction = self.sort.direction.toggle();
+        self.apply_sort();
+    }
+
+    fn apply_sort(&mut self) {
+        let _ = self
+            .db
+            .lock()
+            .unwrap()
+            .set_setting(SORT_SETTING, &self.sort.to_string());
+        self.reload_invoices();
+    }
+
+    /// Drop the loaded window and fetch the first page again.
+    pub fn reload_invoices(&mut self) {
+        let (invoices, count) = {
+            let db = self.db.lock().unwrap();
+            let invoices = db
+                .list_invoices(&self.filter, self.sort, PAGE_SIZE, None)
+                .unwrap_or_default();
+            let count = db.count_invoices(&self.filter).unwrap_or(invoices.len());
+            (invoices, count)
+        };
+        self.invoices = invoices;
+        self.invoice_count = count;
+        self.selected = 0;
+        self.load_detail();
+    }
+
+    fn load_next_page(&mut self) {
+        let Some(last) = self.invoices.last() else {
+            return;
+        };
+        let cursor = InvoiceCursor::new(&self.sort, last);
+        let page = self
+            .db
+            .lock()
+            .unwrap()
+            .list_invoices(&self.filter, self.sort, PAGE_SIZE, Some(&cursor))
+            .unwrap_or_default();
+        self.invoices.extend(page);
+
+        let overflow = self.invoices.len().saturating_sub(PAGE_SIZE * WINDOW_PAGES);
+        self.invoices.drain(..overflow);
+        self.selected -= overflow;
+    }
+
+    fn load_previous_page(&mut self) {
+        let Some(first) = self.invoices.first() else {
+            return;
+        };
+        let cursor = InvoiceCursor::new(&self.sort, first);
+        let mut page = self
+            .db
+            .lock()
+            .unwrap()
+            .list_invoices(&self.filter, self.sort.reversed(), PAGE_SIZE, Some(&cursor))
+            .unwrap_or_default();
+        page.reverse();
+        self.selected += page.len();
+        self.invoices.splice(..0, page);
+        self.invoices.truncate(PAGE_SIZE * WINDOW_PAGES);
+    }
+
+    fn load_detail(&mut self) {
+        let db = self.db.lock().unwrap();
+        self.detail = self
             .invoices
             .get(self.selected)
+            .and_then(|inv| db.get_invoice(inv.id).ok().flatten());
+        self.detail_emails = self
+            .detail
+            .as_ref()
+            .and_then(|inv| db.get_emails(inv.id).ok())
+            .unwrap_or_default();
+        self.detail_reminders = self
+            .detail
+            .as_ref()
+            .and_then(|inv| db.get_reminders(inv.id).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn selected_invoice(&self) -> &Invoice {
+        self.detail
+            .as_ref()
+            .or_else(|| self.invoices.get(self.selected))
             .unwrap_or(&self.empty_invoice)
     }
 
     pub fn start_new(&mut self) {
+        let projects = self.db.lock().unwrap().get_projects().unwrap_or_default();
         self.mode = Mode::Editing;
-        self.form = Some(InvoiceForm::new(self.clients.clone()));
+        self.form = Some(InvoiceForm::new(self.clients.clone(), projects));
     }
 
     pub fn start_edit(&mut self) {
+        if let Err(e) = credit_note::check_editable(self.selected_invoice()) {
+            self.show_error(e);
+            return;
+        }
+        let projects = self.db.lock().unwrap().get_projects().unwrap_or_default();
         self.mode = Mode::Editing;
         self.form = Some(InvoiceForm::from_invoice(
             self.selected_invoice(),
             self.clients.clone(),
+            projects,
         ));
     }
 
     pub fn open_save_modal(&mut self) {
-        let invoice_id = self.selected_invoice().id;
-        let content = "Are you sure you want to save this invoice?".to_string();
-
-        // take a mutable pointer to self to pass into closure
-        let app_ptr: *mut App = self;
-
-        self.mode = Mode::Modal(ModalType::Confirm);
-        self.modal = Some(Modal::new(
-            Some(invoice_id),
-            content,
-            ModalType::Confirm,
-            Some(Box::new(move |_: &mut App| {
-                // cast pointer back to mutable reference
-                let app: &mut App = unsafe { &mut *app_ptr };
-                app.save_form();
-            })),
+        if self.entry_form.is_some() {
+            return self.save_entry_form();
+        }
+        if let Some(form) = self.client_form.as_mut() {
+            let (clients, id) = (&self.clients, form.id);
+            let email_taken = |email: &str| {
+                clients
+                    .iter()
+                    .any(|c| Some(c.id) != id && c.email.eq_ignore_ascii_case(email))
+            };
+            if form.validate(email_taken) {
+                self.open_modal(Modal::confirm(
+                    "Are you sure you want to save this client?".to_string(),
+                    Action::SaveClient,
+                ));
+            }
+            return;
+        }
+        let db = self.db.lock().unwrap();
+        if let Some(form) = self.form.as_mut() {
+            let id = form.id;
+            if !form.validate(|code| db.invoice_code_exists(code, id).unwrap_or(false)) {
+                return;
+            }
+        }
+        drop(db);
+        self.open_modal(Modal::confirm(
+            "Are you sure you want to save this invoice?".to_string(),
+            Action::SaveInvoice,
         ));
     }
 
-    pub fn close_modals(&mut self) {
-        self.mode = Mode::Normal;
-        self.modal = None;
+    /// Show the audit trail of the selected invoice, checking the whole
+    /// log's hash chain on the way.
+    pub fn open_history(&mut self) {
+        let Some(inv) = self.invoices.get(self.selected) else {
+            return;
+        };
+        let (id, code) = (inv.id, inv.code.clone());
+        let result = {
+            let db = self.db.lock().unwrap();
+            db.get_audit_log(None)
+                .and_then(|log| Ok((audit::verify(&log), db.get_audit_log(Some(id))?)))
+        };
+        match result {
+            Ok((check, entries)) => {
+                let title = match check {
+                    Ok(()) => format!("History of invoice {}", code),
+                    Err(entry) => format!(
+                        "History of invoice {} (audit log tampered with at entry {})",
+                        code, entry
+                    ),
+                };
+                self.history = Some((title, entries));
+            }
+            Err(e) => self.show_error(e.into()),
+        }
     }
 
-    pub fn save_form(&mut self) {
-        if let Some(form) = &self.form {
-            let id = if self.selected < self.invoices.len() {
-                self.selected_invoice().id
+    pub fn open_delete_modal(&mut self) {
+        if let Some(inv) = self.invoices.get(self.selected) {
+            if let Err(e) = credit_note::check_editable(inv) {
+                self.show_error(e);
+                return;
+            }
+            let content = format!("Delete invoice {}? This cannot be undone.", inv.code);
+            self.open_modal(Modal::confirm(content, Action::DeleteInvoice(inv.id)));
+        }
+    }
+
+    pub fn open_void_modal(&mut self) {
+        if let Some(inv) = self.invoices.get(self.selected) {
+            if let Err(e) = credit_note::check_editable(inv) {
+                self.show_error(e);
+                return;
+            }
+            let content = format!("Reason for voiding invoice {}:", inv.code);
+            self.open_modal(Modal::prompt(content, Action::VoidInvoice(inv.id)));
+        }
+    }
+
+    pub fn open_credit_note_modal(&mut self) {
+        if let Some(inv) = self.invoices.get(self.selected) {
+            let content = format!(
+                "Create a draft credit note cancelling invoice {}?",
+                inv.code
+            );
+            self.open_modal(Modal::confirm(content, Action::CreditNote(inv.id)));
+        }
+    }
+
+    pub fn open_payment_modal(&mut self) {
+        if let Some(inv) = self.invoices.get(self.selected) {
+            let content = format!(
+                "Payment amount for invoice {} (balance {:.2}):",
+                inv.code,
+                inv.balance()
+            );
+            self.open_modal(Modal::prompt(content, Action::RecordPayment(inv.id)));
+        }
+    }
+
+    pub fn open_email_modal(&mut self) {
+        if let Some(inv) = self.invoices.get(self.selected) {
+            let content = if self.mail.dry_run {
+                format!(
+                    "Write invoice {} for {} to {}? (dry run)",
+                    inv.code, inv.client.email, self.mail.outbox
+                )
+            } else {
+                format!("Email invoice {} to {}?", inv.code, inv.client.email)
+            };
+            self.open_modal(Modal::confirm(content, Action::EmailInvoice(inv.id)));
+        }
+    }
+
+    fn open_modal(&mut self, modal: Modal) {
+        self.mode = Mode::Modal(modal.kind);
+        self.modal = Some(modal);
+    }
+
+    /// Close the current modal, returning to the form or wizard if one is open.
+    pub fn close_modals(&mut self) {
+        self.mode =
+            if self.form.is_some() || self.client_form.is_some() || self.entry_form.is_some() {
+                Mode::Editing
+            } else if self.wizard.is_some() {
+                Mode::Wizard
             } else {
-                self.invoices.len() as u32 + 1
+                Mode::Normal
             };
-            if let Some(inv) = form.to_invoice(id) {
-                if self.selected < self.invoices.len() {
-                    self.invoices[self.selected] = inv;
-                } else {
-                    let result = self.db.lock().unwrap().add_invoice(&inv);
-                    match result {
-                        Ok(_) => (),
-                        Err(e) => println!("Error adding invoice: {}", e),
-                    }
-                    self.invoices.push(inv);
+        self.modal = None;
+    }
+
+    /// Close the current modal and run its action, if it was confirmed.
+    pub fn submit_modal(&mut self) {
+        let Some(modal) = self.modal.take() else {
+            return;
+        };
+        self.close_modals();
+        if let Some(action) = modal.submitted_action() {
+            self.dispatch(action, modal.input.value().to_string());
+        }
+    }
+
+    pub fn dispatch(&mut self, action: Action, input: String) {
+        let result = match action {
+            Action::SaveInvoice => return self.save_form(),
+            Action::SaveClient => return self.save_client_form(),
+            Action::DeleteInvoice(id) => self
+                .db
+                .lock()
+                .unwrap()
+                .delete_invoice(id)
+                .map(|_| "Invoice deleted".to_string())
+                .map_err(AppError::from),
+            Action::VoidInvoice(id) => self
+                .db
+                .lock()
+                .unwrap()
+                .void_invoice(id, input.trim())
+                .map(|_| "Invoice voided".to_string())
+                .map_err(AppError::from),
+            Action::CreditNote(id) => credit_note::create(
+                &self.db.lock().unwrap(),
+                id,
+                &Local::now().date_naive().to_string(),
+            )
+            .map(|note| format!("Created draft credit note {}", note.code)),
+            Action::RecordPayment(id) => self.record_payment(id, &input),
+            Action::EmailInvoice(id) => self.email_invoice(id),
+            Action::SendReminder(id) => self.send_reminder(id, true),
+            Action::SkipReminder(id) => self.send_reminder(id, false),
+            Action::AcceptMatch(id) => self.resolve_match(id, true),
+            Action::RejectMatch(id) => self.resolve_match(id, false),
+            Action::ImportStatement => {
+                bank::import(&self.db.lock().unwrap(), input.trim(), &self.bank)
+            }
+            Action::BillTime(client_id) => timesheet::bill(
+                &self.db.lock().unwrap(),
+                client_id,
+                &self.time,
+                Local::now().date_naive(),
+            ),
+            Action::DeleteTimeEntry(id) => self
+                .db
+                .lock()
+                .unwrap()
+                .delete_time_entry(id)
+                .map(|_| "Time entry deleted".to_string())
+                .map_err(AppError::from),
+            Action::BillExpenses(client_id) => self.bill_expenses(client_id, &input),
+            Action::DeleteExpense(id) => self
+                .db
+                .lock()
+                .unwrap()
+                .delete_expense(id)
+                .map(|_| "Expense deleted".to_string())
+                .map_err(AppError::from),
+            Action::SetReportRange => {
+                match self.set_report_range(&input) {
+                    Ok(message) => self.status = Some(message),
+                    Err(e) => self.show_error(e),
                 }
+                return;
             }
+        };
+        self.reload_invoices();
+        if self.on_reminders() {
+            self.load_reminders();
+        }
+        if self.on_bank() {
+            self.load_matches();
+        }
+        if self.on_time() {
+            self.load_time();
+        }
+        if self.on_expenses() {
+            self.load_expenses();
+        }
+        if self.on_clients() {
+            self.load_projects();
+        }
+        match result {
+            Ok(message) => self.status = Some(message),
+            Err(e) => self.show_error(e),
+        }
+    }
+
+    fn record_payment(&self, id: u32, input: &str) -> Result<String, AppError> {
+        let amount = input
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| amount.is_finite() && *amount > 0.0)
+            .ok_or_else(|| {
+                AppError::Validation(format!("'{}' is not a valid payment amount.", input))
+            })?;
+        let today = Local::now().date_naive().to_string();
+        self.db
+            .lock()
+            .unwrap()
+            .record_payment(id, amount, &today, "")?;
+        Ok(format!("Recorded payment of {:.2}", amount))
+    }
+
+    fn email_invoice(&self, id: u32) -> Result<String, AppError> {
+        let db = self.db.lock().unwrap();
+        let inv = db
+            .get_invoice(id)?
+            .ok_or_else(|| AppError::Validation("Invoice not found.".to_string()))?;
+        mail::send_invoice(&db, &inv, &self.mail, &self.issuer, &self.pdf)
+    }
+
+    pub fn save_form(&mut self) {
+        let result = match self.form.take() {
+            Some(form) => self.save_invoice(&form),
+            None => Ok(()),
+        };
+        self.mode = Mode::Normal;
+        self.reload_invoices();
+        match result {
+            Ok(()) => self.status = Some("Invoice saved".to_string()),
+            Err(e) => self.show_error(e),
         }
+    }
+
+    pub fn save_client_form(&mut self) {
+        let Some(form) = self.client_form.take() else {
+            return;
+        };
+        let client = form.to_client(form.id.unwrap_or(0));
+        let result = {
+            let db = self.db.lock().unwrap();
+            match form.id {
+                Some(_) => db.update_client(&client),
+                None => db.add_client(&client).map(|_| ()),
+            }
+        };
         self.mode = Mode::Normal;
-        self.form = None;
+        self.reload_clients();
+        match result {
+            Ok(()) => self.status = Some(format!("Client {} saved", client.name)),
+            Err(e) => self.show_error(e.into()),
+        }
+    }
+
+    fn start_entry(&mut self, form: EntryForm) {
+        self.mode = Mode::Editing;
+        self.entry_form = Some(form);
+    }
+
+    /// Add what the entry form describes, keeping the form open while it
+    /// has problems.
+    pub fn save_entry_form(&mut self) {
+        let Some(mut form) = self.entry_form.take() else {
+            return;
+        };
+        let result = match form.kind {
+            EntryKind::TimeEntry => self.add_time_entry(&mut form, false),
+            EntryKind::Timer => self.add_time_entry(&mut form, true),
+            EntryKind::Expense => self.add_expense(&mut form),
+            EntryKind::Project(client_id) => self.add_project(&mut form, client_id),
+        };
+        match result {
+            Ok(Some(message)) => {
+                self.mode = Mode::Normal;
+                self.status = Some(message);
+            }
+            Ok(None) => self.entry_form = Some(form),
+            Err(e) => {
+                self.entry_form = Some(form);
+                self.show_error(e);
+            }
+        }
+        if self.on_time() {
+            self.load_time();
+        }
+        if self.on_expenses() {
+            self.load_expenses();
+        }
+        if self.on_clients() {
+            self.load_projects();
+        }
+    }
+
+    fn save_invoice(&self, form: &InvoiceForm) -> Result<(), AppError> {
+        let inv = form.to_invoice(form.id.unwrap_or(0)).ok_or_else(|| {
+            AppError::Validation(
+                "Invoice not saved: check the amounts and the client email.".to_string(),
+            )
+        })?;
+        let db = self.db.lock().unwrap();
+        inv.validate(|code| db.invoice_code_exists(code, form.id).unwrap_or(false))?;
+        match form.id {
+            Some(_) => db.update_invoice(&inv)?,
+            None => {
+                db.add_invoice(&inv)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn show_error(&mut self, error: AppError) {
+        self.open_modal(Modal::alert(error.to_string()));
     }
 
     pub fn cancel_form(&mut self) {
         self.mode = Mode::Normal;
         self.form = None;
+        self.client_form = None;
+        self.entry_form = None;
     }
 
     pub fn export_pdf(&mut self) {
-        generate_invoice_pdf(&self.selected_invoice()).unwrap();
+        if self.invoices.is_empty() {
+            self.show_error(AppError::Validation("No invoice selected.".to_string()));
+            return;
+        }
+        let result = generate_invoice_pdf(
+            &self.db.lock().unwrap(),
+            self.selected_invoice(),
+            &self.issuer,
+            &self.pdf,
+        );
+        match result {
+            Ok(path) => {
+                self.status = Some(format!("Saved {}", path));
+                self.reload_invoices();
+            }
+            Err(e) => self.show_error(e),
+        }
+    }
+
+    pub fn export_ubl(&mut self) {
+        if self.invoices.is_empty() {
+            self.show_error(AppError::Validation("No invoice selected.".to_string()));
+            return;
+        }
+        match ubl::export_ubl(self.selected_invoice(), &self.issuer) {
+            Ok(path) => self.status = Some(format!("Saved {}", path)),
+            Err(e) => self.show_error(e),
+        }
     }
 }
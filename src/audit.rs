@@ -0,0 +1,132 @@
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::models::AuditEntry;
+
+/// Who changes are logged as by default: the login running the app.
+pub fn user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// The top-level fields that differ between two JSON snapshots, as
+/// `{field: [before, after]}`. A missing snapshot counts as all nulls.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new && !changes.contains_key(key) {
+            changes.insert(key.clone(), Value::Array(vec![old.clone(), new.clone()]));
+        }
+    }
+    changes
+}
+
+/// Hex SHA-256 over the entry's fields and the previous entry's hash, so
+/// changing, removing or reordering entries breaks every later hash.
+pub fn hash(entry: &AuditEntry) -> String {
+    let invoice_id = entry
+        .invoice_id
+        .map(|id| id.to_string())
+        .unwrap_or_default();
+    digest(&[
+        &entry.prev_hash,
+        &entry.id.to_string(),
+        &entry.at,
+        &entry.actor,
+        &entry.entity,
+        &entry.entity_id.to_string(),
+        &invoice_id,
+        &entry.action,
+        &entry.changes,
+    ])
+}
+
+/// The hash of entries logged before it covered the invoice.
+fn legacy_hash(entry: &AuditEntry) -> String {
+    digest(&[
+        &entry.prev_hash,
+        &entry.id.to_string(),
+        &entry.at,
+        &entry.actor,
+        &entry.entity,
+        &entry.entity_id.to_string(),
+        &entry.action,
+        &entry.changes,
+    ])
+}
+
+fn digest(fields: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Check the whole log, oldest first. Returns the id of the first entry
+/// whose hash or link to the previous entry doesn't match. The log can't be
+/// rewritten, so entries with the legacy hash pass until the first entry
+/// with the current one.
+pub fn verify(entries: &[AuditEntry]) -> Result<(), u32> {
+    let mut prev_hash = "";
+    let mut legacy = true;
+    for entry in entries {
+        if entry.prev_hash != prev_hash {
+            return Err(entry.id);
+        }
+        if entry.hash == hash(entry) {
+            legacy = false;
+        } else if !legacy || entry.hash != legacy_hash(entry) {
+            return Err(entry.id);
+        }
+        prev_hash = &entry.hash;
+    }
+    Ok(())
+}
+
+/// One `field: before → after` line per change, with long values shortened.
+pub fn describe(entry: &AuditEntry) -> Vec<String> {
+    let changes: Map<String, Value> = serde_json::from_str(&entry.changes).unwrap_or_default();
+    changes
+        .iter()
+        .map(|(field, change)| {
+            let show = |index: usize| short(change.get(index).unwrap_or(&Value::Null));
+            match entry.action.as_str() {
+                "create" => format!("{}: {}", field, show(1)),
+                "delete" => format!("{}: {}", field, show(0)),
+                _ => format!("{}: {} → {}", field, show(0), show(1)),
+            }
+        })
+        .collect()
+}
+
+fn short(value: &Value) -> String {
+    const MAX: usize = 60;
+    let text = match value {
+        Value::String(text) => text.clone(),
+        Value::Object(fields) if fields.get("name").is_some_and(Value::is_string) => {
+            fields["name"].as_str().unwrap_or_default().to_string()
+        }
+        Value::Array(values) if values.iter().all(Value::is_object) => {
+            format!("{} entries", values.len())
+        }
+        value => value.to_string(),
+    };
+    if text.chars().count() > MAX {
+        format!("{}…", text.chars().take(MAX).collect::<String>())
+    } else {
+        text
+    }
+}
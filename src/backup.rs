@@ -0,0 +1,223 @@
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Client, Invoice, Payment};
+
+/// Version of the JSON backup layout. Bump it when the layout changes and
+/// teach `Backup::read` to upgrade older files.
+///
+/// 2 added `tables`; version 1 files read as having none of them.
+pub const FORMAT_VERSION: u32 = 2;
+
+const LAST_BACKUP_SETTING: &str = "last_backup";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Where and how often copies of `invoice-rs.db` are kept, from the
+/// `[backup]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub dir: String,
+    /// Number of database copies to keep; older ones are deleted.
+    pub keep: usize,
+    /// Hours between scheduled copies, or 0 to only copy before migrations.
+    pub interval_hours: u32,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: "backups".to_string(),
+            keep: 10,
+            interval_hours: 24,
+        }
+    }
+}
+
+/// A table row as written to a backup, column name to value.
+pub type Row = serde_json::Map<String, serde_json::Value>;
+
+/// The whole dataset as written to a JSON backup file.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created: String,
+    #[serde(default)]
+    pub clients: Vec<Client>,
+    #[serde(default)]
+    pub invoices: Vec<Invoice>,
+    #[serde(default)]
+    pub payments: Vec<Payment>,
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    /// Rows of the remaining tables, such as reminders, emails, bank
+    /// transactions, events and the audit log, by table name.
+    #[serde(default)]
+    pub tables: BTreeMap<String, Vec<Row>>,
+    /// Highest id in each table when the backup was taken.
+    #[serde(default)]
+    pub sequences: BTreeMap<String, i64>,
+}
+
+impl Backup {
+    pub fn export(db: &Database) -> Result<Self, AppError> {
+        Ok(Self {
+            version: FORMAT_VERSION,
+            created: Local::now().to_rfc3339(),
+            clients: db.get_all_clients()?,
+            invoices: db.get_all_invoices()?,
+            payments: db.get_payments()?,
+            settings: db.get_settings()?,
+            tables: db.get_backup_tables()?,
+            sequences: db.get_sequences()?,
+        })
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), AppError> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| AppError::Validation(e.to_string()))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn read(path: &str) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path)?;
+        let backup: Self = serde_json::from_str(&text)
+            .map_err(|e| AppError::Validation(format!("{} is not a backup: {}", path, e)))?;
+        if backup.version > FORMAT_VERSION {
+            return Err(AppError::Validation(format!(
+                "{} was written by a newer version (format {}, this build reads up to {})",
+                path, backup.version, FORMAT_VERSION
+            )));
+        }
+        Ok(backup)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RestoreMode {
+    /// Delete everything first and load the backup as is.
+    Replace,
+    /// Add what's missing, matching clients by id and invoices by code.
+    Merge,
+}
+
+/// What a restore added, and the records it left alone because they
+/// differ from what's already stored.
+#[derive(Default)]
+pub struct RestoreReport {
+    pub clients: usize,
+    pub invoices: usize,
+    pub payments: usize,
+    /// Rows of the other tables.
+    pub rows: usize,
+    pub settings: usize,
+    pub conflicts: Vec<String>,
+}
+
+impl RestoreReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "Restored {} clients, {} invoices, {} payments, {} other rows and {} settings; {} conflicts",
+            self.clients,
+            self.invoices,
+            self.payments,
+            self.rows,
+            self.settings,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// Restore `backup`. A copy of the database is taken first so a restore can
+/// be undone.
+pub fn restore(
+    db: &Database,
+    backup: &Backup,
+    mode: RestoreMode,
+    config: &BackupConfig,
+) -> Result<RestoreReport, AppError> {
+    if mode == RestoreMode::Replace {
+        let unsaved = db.unsaved_tables(backup)?;
+        if !unsaved.is_empty() {
+            return Err(AppError::Validation(format!(
+                "Replacing the data would lose the rows in {}, which the backup doesn't \
+                 contain; use --merge instead.",
+                unsaved.join(", ")
+            )));
+        }
+    }
+    snapshot(db, config, "pre-restore")?;
+    let report = match mode {
+        RestoreMode::Replace => db.restore_replace(backup)?,
+        RestoreMode::Merge => db.restore_merge(backup)?,
+    };
+    Ok(report)
+}
+
+/// Path for a new database copy, named so copies sort by age.
+pub fn snapshot_path(config: &BackupConfig, reason: &str) -> Result<PathBuf, AppError> {
+    fs::create_dir_all(&config.dir)?;
+    let stamp = Local::now().format(TIMESTAMP_FORMAT);
+    let mut path = Path::new(&config.dir).join(format!("invoice-rs-{}-{}.db", stamp, reason));
+    // Several copies can be taken within the same second.
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = Path::new(&config.dir).join(format!("invoice-rs-{}-{}-{}.db", stamp, reason, n));
+    }
+    Ok(path)
+}
+
+/// Copy the database into the backup directory and delete the oldest copies
+/// beyond `keep`.
+pub fn snapshot(db: &Database, config: &BackupConfig, reason: &str) -> Result<PathBuf, AppError> {
+    let path = snapshot_path(config, reason)?;
+    db.copy_to(&path)?;
+    prune(config)?;
+    Ok(path)
+}
+
+pub fn prune(config: &BackupConfig) -> Result<(), AppError> {
+    let mut copies: Vec<PathBuf> = fs::read_dir(&config.dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("invoice-rs-") && name.ends_with(".db"))
+        })
+        .collect();
+    copies.sort();
+    let excess = copies.len().saturating_sub(config.keep.max(1));
+    for path in &copies[..excess] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Take a scheduled copy if `interval_hours` have passed since the last one.
+pub fn run_schedule(db: &Database, config: &BackupConfig) -> Result<Option<PathBuf>, AppError> {
+    if config.interval_hours == 0 {
+        return Ok(None);
+    }
+    let now = Local::now().naive_local();
+    let last = db
+        .get_setting(LAST_BACKUP_SETTING)?
+        .and_then(|value| NaiveDateTime::parse_from_str(&value, TIMESTAMP_FORMAT).ok());
+    if let Some(last) = last
+        && now - last < TimeDelta::hours(config.interval_hours.into())
+    {
+        return Ok(None);
+    }
+    let path = snapshot(db, config, "scheduled")?;
+    db.set_setting(
+        LAST_BACKUP_SETTING,
+        &now.format(TIMESTAMP_FORMAT).to_string(),
+    )?;
+    Ok(Some(path))
+}
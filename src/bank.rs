@@ -0,0 +1,604 @@
+use chrono::{Datelike, NaiveDate};
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use strum::Display;
+
+use crate::csv;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{BankTransaction, Invoice};
+
+// Accepted CSV headers, lowercased, most specific first.
+const DATE_COLUMNS: &[&str] = &[
+    "booking date",
+    "date",
+    "value date",
+    "buchungstag",
+    "valuta",
+];
+const AMOUNT_COLUMNS: &[&str] = &["amount", "betrag", "credit"];
+const NAME_COLUMNS: &[&str] = &["name", "payer", "counterparty", "auftraggeber"];
+const REFERENCE_COLUMNS: &[&str] = &[
+    "reference",
+    "purpose",
+    "memo",
+    "verwendungszweck",
+    "description",
+];
+const CURRENCY_COLUMNS: &[&str] = &["currency", "ccy", "währung"];
+const ID_COLUMNS: &[&str] = &["id", "transaction id"];
+
+/// Bank statement settings, from the `[bank]` table of the config file.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BankConfig {
+    /// How to read CSV exports, from `[[bank.csv]]`. The first profile
+    /// whose headers a file has is used.
+    pub csv: Vec<CsvProfile>,
+}
+
+impl BankConfig {
+    fn profile(&self, headers: &[String]) -> CsvProfile {
+        self.csv
+            .iter()
+            .find(|profile| {
+                profile
+                    .headers
+                    .iter()
+                    .all(|name| headers.contains(&name.trim().to_lowercase()))
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Number and date notation of one bank's CSV export.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CsvProfile {
+    /// Column headers that tell this bank's files apart; none matches any
+    /// file.
+    pub headers: Vec<String>,
+    pub decimal: Decimal,
+    pub date_order: DateOrder,
+}
+
+/// Decimal separator of amounts.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decimal {
+    /// The last of `.` and `,` when both appear. Alone, either one is the
+    /// decimal separator unless it is repeated or followed by exactly three
+    /// digits, as in `1,234`.
+    #[default]
+    Auto,
+    Dot,
+    Comma,
+}
+
+/// Order of day, month and year in dates that aren't ISO `2026-01-31`.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// `31.01.2026` or `31/01/2026`.
+    #[default]
+    Dmy,
+    /// `01/31/2026`.
+    Mdy,
+    /// `2026/01/31`.
+    Ymd,
+}
+
+impl DateOrder {
+    /// Formats with a four-digit year first, as `%Y` also takes two.
+    fn formats(self) -> &'static [&'static str] {
+        match self {
+            Self::Dmy => &["%d.%m.%Y", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%y", "%d/%m/%y"],
+            Self::Mdy => &["%m/%d/%Y", "%m-%d-%Y", "%m.%d.%Y", "%m/%d/%y"],
+            Self::Ymd => &["%Y/%m/%d", "%Y.%m.%d"],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Display)]
+pub enum StatementFormat {
+    #[strum(to_string = "CAMT.053")]
+    Camt053,
+    #[strum(to_string = "OFX")]
+    Ofx,
+    #[strum(to_string = "CSV")]
+    Csv,
+}
+
+impl StatementFormat {
+    /// Tell the format from the file's content, falling back to CSV.
+    pub fn detect(text: &str) -> Self {
+        let start = text.trim_start_matches('\u{feff}').trim_start();
+        if start.contains("OFXHEADER") || start.contains("<OFX>") {
+            Self::Ofx
+        } else if start.starts_with('<') {
+            Self::Camt053
+        } else {
+            Self::Csv
+        }
+    }
+}
+
+/// An open transaction with the invoice it most likely pays, if any.
+#[derive(Clone)]
+pub struct Suggestion {
+    pub transaction: BankTransaction,
+    pub invoice: Option<Invoice>,
+    /// What matched: `code`, `amount` and/or `client`.
+    pub reasons: Vec<&'static str>,
+}
+
+/// Read the incoming payments from a statement file. Debits are left out
+/// as they can't settle an invoice.
+pub fn read_statement(
+    path: &str,
+    config: &BankConfig,
+) -> Result<(StatementFormat, Vec<BankTransaction>), AppError> {
+    let text = fs::read_to_string(path)?;
+    let format = StatementFormat::detect(&text);
+    let transactions = match format {
+        StatementFormat::Camt053 => parse_camt053(&text),
+        StatementFormat::Ofx => parse_ofx(&text),
+        StatementFormat::Csv => parse_csv(&text, config),
+    }
+    .map_err(|e| AppError::Validation(format!("{}: {}", path, e)))?;
+    Ok((
+        format,
+        transactions
+            .into_iter()
+            .filter(|transaction| transaction.amount > 0.0)
+            .collect(),
+    ))
+}
+
+/// Store the payments from a statement file, returning a summary.
+pub fn import(db: &Database, path: &str, config: &BankConfig) -> Result<String, AppError> {
+    let (format, transactions) = read_statement(path, config)?;
+    let added = db.import_transactions(&transactions)?;
+    Ok(format!(
+        "Imported {} new of {} payments from {} statement",
+        added,
+        transactions.len(),
+        format
+    ))
+}
+
+/// Pair each open transaction with the open invoice it matches best. An
+/// invoice is suggested for one transaction at most.
+pub fn suggest(db: &Database) -> Result<Vec<Suggestion>, AppError> {
+    let transactions = db.get_open_transactions()?;
+    let invoices = db.get_unpaid_invoices()?;
+
+    let mut candidates = Vec::new();
+    for (t, transaction) in transactions.iter().enumerate() {
+        for (i, invoice) in invoices.iter().enumerate() {
+            let (score, reasons) = score(transaction, invoice);
+            if score >= 2 {
+                candidates.push((score, t, i, reasons));
+            }
+        }
+    }
+    // Strongest matches first; ties go to the older transaction and invoice.
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut chosen: HashMap<usize, (usize, Vec<&'static str>)> = HashMap::new();
+    let mut taken = vec![false; invoices.len()];
+    for (_, t, i, reasons) in candidates {
+        if chosen.contains_key(&t) || taken[i] {
+            continue;
+        }
+        taken[i] = true;
+        chosen.insert(t, (i, reasons));
+    }
+
+    Ok(transactions
+        .into_iter()
+        .enumerate()
+        .map(|(t, transaction)| match chosen.remove(&t) {
+            Some((i, reasons)) => Suggestion {
+                transaction,
+                invoice: Some(invoices[i].clone()),
+                reasons,
+            },
+            None => Suggestion {
+                transaction,
+                invoice: None,
+                reasons: Vec::new(),
+            },
+        })
+        .collect())
+}
+
+/// Record the transaction as a payment on the suggested invoice.
+pub fn accept(db: &Database, suggestion: &Suggestion) -> Result<String, AppError> {
+    let invoice = suggestion
+        .invoice
+        .as_ref()
+        .ok_or_else(|| AppError::Validation("No invoice matches this payment.".to_string()))?;
+    let transaction = &suggestion.transaction;
+    db.accept_transaction(transaction, invoice.id)?;
+    Ok(format!(
+        "Recorded {:.2} {} from {} on invoice {}",
+        transaction.amount, transaction.currency, transaction.name, invoice.code
+    ))
+}
+
+/// Set the transaction aside; it won't be suggested again.
+pub fn reject(db: &Database, suggestion: &Suggestion) -> Result<String, AppError> {
+    db.reject_transaction(suggestion.transaction.id)?;
+    Ok(format!(
+        "Set aside {:.2} {} from {}",
+        suggestion.transaction.amount, suggestion.transaction.currency, suggestion.transaction.name
+    ))
+}
+
+/// How well `transaction` matches `invoice`: 4 for its code in the
+/// reference, 2 for the exact balance, 1 for the client's name.
+fn score(transaction: &BankTransaction, invoice: &Invoice) -> (u32, Vec<&'static str>) {
+    if !transaction.currency.is_empty()
+        && !transaction.currency.eq_ignore_ascii_case(&invoice.currency)
+    {
+        return (0, Vec::new());
+    }
+    let mut score = 0;
+    let mut reasons = Vec::new();
+    let code = normalize(&invoice.code);
+    if code.len() >= 3 && normalize(&transaction.reference).contains(&code) {
+        score += 4;
+        reasons.push("code");
+    }
+    if (transaction.amount - invoice.balance()).abs() < 0.005 {
+        score += 2;
+        reasons.push("amount");
+    }
+    let payer = normalize(&transaction.name);
+    let client = normalize(&invoice.client.name);
+    if payer.len() >= 3 && client.len() >= 3 && (payer.contains(&client) || client.contains(&payer))
+    {
+        score += 1;
+        reasons.push("client");
+    }
+    (score, reasons)
+}
+
+/// Uppercase letters and digits only, so `INV-0042` matches `inv 0042`.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// ISO 20022 `camt.053` statements, any version: one transaction per entry,
+/// or per transaction detail for batched entries.
+pub fn parse_camt053(text: &str) -> Result<Vec<BankTransaction>, String> {
+    let doc = Document::parse(text).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if find(root, "Stmt").is_none() {
+        return Err("not a CAMT.053 statement".to_string());
+    }
+
+    let mut transactions = Vec::new();
+    for stmt in root.descendants().filter(|node| is(*node, "Stmt")) {
+        let account = child(stmt, &["Acct", "Id", "IBAN"])
+            .or_else(|| child(stmt, &["Acct", "Id", "Othr", "Id"]))
+            .map(text_of)
+            .unwrap_or_default();
+        let mut statement = Vec::new();
+        for entry in stmt.children().filter(|node| is(*node, "Ntry")) {
+            if text_of_path(entry, &["RvslInd"]) == "true" {
+                continue;
+            }
+            let sign = match text_of_path(entry, &["CdtDbtInd"]).as_str() {
+                "DBIT" => -1.0,
+                _ => 1.0,
+            };
+            let date = [
+                &["BookgDt", "Dt"][..],
+                &["BookgDt", "DtTm"],
+                &["ValDt", "Dt"],
+            ]
+            .iter()
+            .map(|path| text_of_path(entry, path))
+            .find(|date| !date.is_empty())
+            .map(|date| date.chars().take(10).collect())
+            .unwrap_or_default();
+            let details: Vec<Node> = entry
+                .descendants()
+                .filter(|node| is(*node, "TxDtls"))
+                .collect();
+
+            if details.len() > 1 {
+                for (n, detail) in details.iter().enumerate() {
+                    let amount = child(*detail, &["AmtDtls", "TxAmt", "Amt"])
+                        .or_else(|| child(*detail, &["Amt"]));
+                    let Some(amount) = amount else {
+                        continue;
+                    };
+                    let mut transaction = camt_details(*detail);
+                    transaction.amount = sign * parse_amount(&text_of(amount), Decimal::Dot)?;
+                    transaction.currency = amount.attribute("Ccy").unwrap_or("").to_string();
+                    transaction.date.clone_from(&date);
+                    if transaction.bank_id.is_empty() {
+                        let entry_id = camt_id(entry);
+                        if !entry_id.is_empty() {
+                            transaction.bank_id = format!("{}/{}", entry_id, n + 1);
+                        }
+                    }
+                    statement.push(transaction);
+                }
+                continue;
+            }
+
+            let amount = child(entry, &["Amt"]).ok_or("entry without an amount")?;
+            let mut transaction = details
+                .first()
+                .map(|detail| camt_details(*detail))
+                .unwrap_or_default();
+            transaction.amount = sign * parse_amount(&text_of(amount), Decimal::Dot)?;
+            transaction.currency = amount.attribute("Ccy").unwrap_or("").to_string();
+            transaction.date = date;
+            let entry_id = camt_id(entry);
+            if !entry_id.is_empty() {
+                transaction.bank_id = entry_id;
+            }
+            if transaction.reference.is_empty() {
+                transaction.reference = text_of_path(entry, &["AddtlNtryInf"]);
+            }
+            statement.push(transaction);
+        }
+        fill_ids(&mut statement, &account);
+        transactions.extend(statement);
+    }
+    Ok(transactions)
+}
+
+/// Payer, remittance information and reference of a `TxDtls` element.
+fn camt_details(detail: Node) -> BankTransaction {
+    let name = find(detail, "Dbtr")
+        .and_then(|debtor| find(debtor, "Nm"))
+        .map(text_of)
+        .unwrap_or_default();
+    let mut reference: Vec<String> = detail
+        .descendants()
+        .filter(|node| is(*node, "Ustrd"))
+        .map(text_of)
+        .collect();
+    if let Some(creditor_ref) = find(detail, "CdtrRefInf").and_then(|node| find(node, "Ref")) {
+        reference.push(text_of(creditor_ref));
+    }
+    let bank_id = [&["Refs", "AcctSvcrRef"][..], &["Refs", "EndToEndId"]]
+        .iter()
+        .map(|path| text_of_path(detail, path))
+        .find(|id| !id.is_empty() && id != "NOTPROVIDED")
+        .unwrap_or_default();
+    BankTransaction {
+        bank_id,
+        name,
+        reference: reference.join(" "),
+        ..BankTransaction::default()
+    }
+}
+
+fn camt_id(entry: Node) -> String {
+    let id = text_of_path(entry, &["AcctSvcrRef"]);
+    if id.is_empty() {
+        text_of_path(entry, &["NtryRef"])
+    } else {
+        id
+    }
+}
+
+fn is(node: Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+/// The first descendant element called `name`, whatever its namespace.
+fn find<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.descendants().find(|node| is(*node, name))
+}
+
+/// Follow `path` through child elements.
+fn child<'a, 'i>(node: Node<'a, 'i>, path: &[&str]) -> Option<Node<'a, 'i>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children().find(|child| is(*child, name))
+    })
+}
+
+fn text_of(node: Node) -> String {
+    node.text().unwrap_or("").trim().to_string()
+}
+
+fn text_of_path(node: Node, path: &[&str]) -> String {
+    child(node, path).map(text_of).unwrap_or_default()
+}
+
+/// OFX 1.x (SGML) and 2.x (XML) statements. Amounts may use a decimal
+/// point or comma, but never group thousands.
+pub fn parse_ofx(text: &str) -> Result<Vec<BankTransaction>, String> {
+    let currency = ofx_value(text, "CURDEF");
+    let account = ofx_value(text, "ACCTID");
+    let mut transactions = Vec::new();
+    for block in text.split("<STMTTRN>").skip(1) {
+        let block = block.split("</STMTTRN>").next().unwrap_or(block);
+        let posted = ofx_value(block, "DTPOSTED");
+        let date = posted
+            .get(..8)
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+            .map(|date| date.to_string())
+            .ok_or_else(|| format!("bad DTPOSTED '{}'", posted))?;
+        let name = match ofx_value(block, "NAME") {
+            name if name.is_empty() => ofx_value(block, "PAYEE"),
+            name => name,
+        };
+        transactions.push(BankTransaction {
+            bank_id: ofx_value(block, "FITID"),
+            date,
+            amount: parse_amount(&ofx_value(block, "TRNAMT").replace(',', "."), Decimal::Dot)?,
+            currency: currency.clone(),
+            name,
+            reference: ofx_value(block, "MEMO"),
+            ..BankTransaction::default()
+        });
+    }
+    if transactions.is_empty() && !text.contains("<OFX>") {
+        return Err("not an OFX statement".to_string());
+    }
+    fill_ids(&mut transactions, &account);
+    Ok(transactions)
+}
+
+/// The value after `<tag>`, up to the next tag; OFX 1.x leaves elements
+/// unclosed.
+fn ofx_value(block: &str, tag: &str) -> String {
+    let open = format!("<{}>", tag);
+    let Some(start) = block.find(&open) else {
+        return String::new();
+    };
+    let rest = &block[start + open.len()..];
+    rest[..rest.find('<').unwrap_or(rest.len())]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// A CSV export with a header row. Columns are found by name: a date and
+/// an amount are required, payer, reference, currency and id are used when
+/// present. Both `,` and `;` separated files are read, with amounts and
+/// dates as the matching profile in `config` says.
+pub fn parse_csv(text: &str, config: &BankConfig) -> Result<Vec<BankTransaction>, String> {
+    let first_line = text.lines().next().unwrap_or("");
+    let separator = if first_line.matches(';').count() > first_line.matches(',').count() {
+        ';'
+    } else {
+        ','
+    };
+    let mut records = csv::parse_with(text, separator)?.into_iter();
+    let headers: Vec<String> = records
+        .next()
+        .ok_or("the file is empty")?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| headers.iter().position(|header| header == name))
+    };
+    let (Some(date_col), Some(amount_col)) = (column(DATE_COLUMNS), column(AMOUNT_COLUMNS)) else {
+        return Err(format!(
+            "need a date and an amount column, found: {}",
+            headers.join(", ")
+        ));
+    };
+    let profile = config.profile(&headers);
+    let name_col = column(NAME_COLUMNS);
+    let reference_col = column(REFERENCE_COLUMNS);
+    let currency_col = column(CURRENCY_COLUMNS);
+    let id_col = column(ID_COLUMNS);
+
+    let mut transactions = Vec::new();
+    for (n, record) in records.enumerate() {
+        let field = |col: Option<usize>| {
+            col.and_then(|col| record.get(col))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let line = n + 2;
+        let raw_date = field(Some(date_col));
+        let date = parse_date(&raw_date, profile.date_order)
+            .ok_or_else(|| format!("line {}: bad date '{}'", line, raw_date))?;
+        let amount = parse_amount(&field(Some(amount_col)), profile.decimal)
+            .map_err(|e| format!("line {}: {}", line, e))?;
+        transactions.push(BankTransaction {
+            bank_id: field(id_col),
+            date,
+            amount,
+            currency: field(currency_col).to_uppercase(),
+            name: field(name_col),
+            reference: field(reference_col),
+            ..BankTransaction::default()
+        });
+    }
+    fill_ids(&mut transactions, "");
+    Ok(transactions)
+}
+
+/// Give transactions without a bank reference one made of their contents,
+/// so importing the same statement twice adds nothing. Ids are prefixed
+/// with the account, as banks only keep them unique per account.
+fn fill_ids(transactions: &mut [BankTransaction], account: &str) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for transaction in transactions {
+        if transaction.bank_id.is_empty() {
+            let key = format!(
+                "{}|{:.2}|{}|{}",
+                transaction.date, transaction.amount, transaction.name, transaction.reference
+            );
+            let count = seen.entry(key.clone()).or_default();
+            *count += 1;
+            transaction.bank_id = format!("{}|{}", key, count);
+        }
+        if !account.is_empty() {
+            transaction.bank_id = format!("{}:{}", account, transaction.bank_id);
+        }
+    }
+}
+
+/// Amounts like `1234.50`, `1,234.50`, `1.234,50` or `-12,5`; any other
+/// separator than the decimal one groups thousands.
+fn parse_amount(text: &str, decimal: Decimal) -> Result<f64, String> {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+'))
+        .collect();
+    let decimal = match decimal {
+        Decimal::Dot => Some('.'),
+        Decimal::Comma => Some(','),
+        Decimal::Auto => match (cleaned.rfind('.'), cleaned.rfind(',')) {
+            (Some(dot), Some(comma)) => Some(if dot > comma { '.' } else { ',' }),
+            (Some(at), None) | (None, Some(at)) => {
+                let separator = cleaned[at..].chars().next();
+                let repeated = cleaned.matches(|c| Some(c) == separator).count() > 1;
+                let digits = cleaned.len() - at - 1;
+                if repeated || digits == 3 {
+                    None
+                } else {
+                    separator
+                }
+            }
+            (None, None) => None,
+        },
+    };
+    let normalized: String = cleaned
+        .chars()
+        .filter_map(|c| match c {
+            '.' | ',' if Some(c) == decimal => Some('.'),
+            '.' | ',' => None,
+            _ => Some(c),
+        })
+        .collect();
+    normalized
+        .parse()
+        .map_err(|_| format!("bad amount '{}'", text))
+}
+
+/// Dates as `YYYY-MM-DD`, from ISO or compact `YYYYMMDD` notation or with
+/// day, month and year in `order`.
+fn parse_date(text: &str, order: DateOrder) -> Option<String> {
+    ["%Y-%m-%d", "%Y%m%d"]
+        .iter()
+        .chain(order.formats())
+        .filter_map(|format| NaiveDate::parse_from_str(text.trim(), format).ok())
+        // `%Y` also takes two digits; those are meant for `%y`.
+        .find(|date| date.year() >= 1970)
+        .map(|date| date.to_string())
+}
@@ -0,0 +1,675 @@
+use crate::api::{self, ApiState};
+use crate::audit;
+use crate::backup::{self, Backup, RestoreMode};
+use crate::bank;
+use crate::config::Config;
+use crate::credit_note;
+use crate::db::Database;
+use crate::dunning;
+use crate::error::AppError;
+use crate::expense;
+use crate::ledger::{self, LedgerFormat};
+use crate::mail;
+use crate::models::{Invoice, InvoiceFilter, InvoiceSort};
+use crate::pdf::{self, PdfConfig};
+use crate::project;
+use crate::report::DateRange;
+use crate::timesheet;
+use crate::transfer::{self, CsvFile, ImportKind, Mapping};
+use crate::ubl;
+use crate::webhook;
+use chrono::{Local, Utc};
+use std::sync::Mutex;
+
+const USAGE: &str = "Usage:
+  invoice-rs                      Start the terminal UI
+  invoice-rs import <clients|invoices> <file.csv> [--dry-run] [--map column=field]...
+  invoice-rs export <clients|invoices> <file.csv> [--status STATUS] [--client ID] [--search TEXT]
+  invoice-rs pdf <invoice code> [--facturx minimum|basic|en16931]
+  invoice-rs ubl <invoice code>     Write invoice_<code>.xml (UBL 2.1, Peppol BIS Billing 3.0)
+  invoice-rs email <invoice code> [--dry-run]   Email the invoice pdf to the client
+  invoice-rs credit-note <invoice code>        Create a draft credit note cancelling an issued invoice
+  invoice-rs reminders                         List payment reminders that are due
+  invoice-rs remind <invoice code> [--dry-run]  Send the reminder due for an invoice
+  invoice-rs bank [statement]                  Import a CAMT.053, OFX or CSV statement and list matches
+  invoice-rs ledger <ledger|hledger|beancount> [FROM..TO]   Write the journal, this quarter by default
+  invoice-rs time                              List time entries, unbilled first
+  invoice-rs time add <client> <project> <description> <duration> [rate]
+  invoice-rs time start <client> <project> <description> [rate]
+  invoice-rs time stop                         Stop the running timer
+  invoice-rs time bill <client>                Put the client's unbilled time on draft invoices
+  invoice-rs expenses                          List expenses, those still to be billed first
+  invoice-rs expenses add <vendor> <amount> [--category C] [--client C] [--project P] [--receipt FILE] [--date D]
+  invoice-rs expenses bill <client> [--markup PERCENT]   Add billable expenses to a draft invoice
+  invoice-rs projects                          List projects with their budget use
+  invoice-rs projects add <client> <name> [--po PO] [--budget AMOUNT] [--rate RATE]
+  invoice-rs projects close <id>               Close a project to new time and expenses
+  invoice-rs serve [--bind ADDR]                Serve the JSON API (see invoice-rs openapi)
+  invoice-rs openapi                           Print the API's OpenAPI schema
+  invoice-rs audit [invoice code]             Show the audit log, or one invoice's history, and check it
+  invoice-rs webhooks [--deliver]              Show recent webhook deliveries, or send due ones first
+  invoice-rs backup <file.json>
+  invoice-rs restore <file.json> [--merge | --replace]";
+
+/// Run a command given on the command line. Returns `None` when there is
+/// none and the TUI should start instead.
+pub fn run(args: &[String]) -> Option<Result<(), AppError>> {
+    let (command, rest) = args.split_first()?;
+    Some(match command.as_str() {
+        "import" => import(rest),
+        "export" => export(rest),
+        "pdf" => export_pdf(rest),
+        "ubl" => export_ubl(rest),
+        "email" => email(rest),
+        "credit-note" => create_credit_note(rest),
+        "reminders" => reminders(rest),
+        "serve" => serve(rest),
+        "openapi" => {
+            println!("{}", api::OPENAPI);
+            Ok(())
+        }
+        "remind" => remind(rest),
+        "bank" => bank(rest),
+        "ledger" => export_ledger(rest),
+        "time" => time(rest),
+        "expenses" => expenses(rest),
+        "projects" => projects(rest),
+        "audit" => show_audit(rest),
+        "webhooks" => webhooks(rest),
+        "backup" => backup(rest),
+        "restore" => restore(rest),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(usage(&format!("unknown command '{}'", other))),
+    })
+}
+
+fn open_db() -> Result<(Database, Config), AppError> {
+    let config = Config::load()?;
+    let mut db = Database::new(&config.backup)?;
+    db.set_actor(&format!("{} (cli)", audit::user()));
+    db.set_issuer(&config.issuer);
+    Ok((db, config))
+}
+
+fn usage(problem: &str) -> AppError {
+    AppError::Validation(format!("{}\n\n{}", problem, USAGE))
+}
+
+/// Split `<kind> <file>` from the options that follow them.
+fn target(args: &[String]) -> Result<(ImportKind, &str, &[String]), AppError> {
+    match args {
+        [kind, path, options @ ..] => {
+            let kind = kind
+                .parse()
+                .map_err(|_| usage(&format!("unknown kind '{}'", kind)))?;
+            Ok((kind, path.as_str(), options))
+        }
+        _ => Err(usage("missing kind or file")),
+    }
+}
+
+fn import(args: &[String]) -> Result<(), AppError> {
+    let (kind, path, mut options) = target(args)?;
+    let mut dry_run = false;
+    let mut overrides = Vec::new();
+    while let Some((option, rest)) = options.split_first() {
+        options = rest;
+        match option.as_str() {
+            "--dry-run" => dry_run = true,
+            "--map" => {
+                let (value, rest) = options
+                    .split_first()
+                    .ok_or_else(|| usage("--map needs a column=field value"))?;
+                overrides.push(value.clone());
+                options = rest;
+            }
+            other => return Err(usage(&format!("unknown option '{}'", other))),
+        }
+    }
+
+    let file = CsvFile::read(path)?;
+    let mut mapping = Mapping::guess(kind, &file.headers);
+    mapping.apply(&file.headers, &overrides)?;
+    for (header, field) in file.headers.iter().zip(&mapping.columns) {
+        println!("{:>20} -> {}", header, field.unwrap_or("(ignored)"));
+    }
+
+    let (db, _) = open_db()?;
+    let preview = transfer::preview(&db, &file, &mapping)?;
+    for issue in &preview.duplicates {
+        println!("skipped {}", issue);
+    }
+    for issue in &preview.errors {
+        println!("error {}", issue);
+    }
+    println!("{}", preview.summary());
+    if dry_run {
+        return Ok(());
+    }
+    let count = transfer::commit(&db, &preview)?;
+    println!("Imported {} {}", count, kind);
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), AppError> {
+    let (kind, path, mut options) = target(args)?;
+    let mut filter = InvoiceFilter::default();
+    while let [option, value, rest @ ..] = options {
+        options = rest;
+        match option.as_str() {
+            "--status" => filter.status = Some(value.clone()),
+            "--search" => filter.search = Some(value.clone()),
+            "--client" => {
+                filter.client_id = Some(
+                    value
+                        .parse()
+                        .map_err(|_| usage(&format!("'{}' is not a client id", value)))?,
+                )
+            }
+            other => return Err(usage(&format!("unknown option '{}'", other))),
+        }
+    }
+    if let [option] = options {
+        return Err(usage(&format!("{} needs a value", option)));
+    }
+
+    let (db, _) = open_db()?;
+    transfer::export_to(&db, kind, &filter, InvoiceSort::default(), path)?;
+    println!("Saved {}", path);
+    Ok(())
+}
+
+fn export_pdf(args: &[String]) -> Result<(), AppError> {
+    let (db, config) = open_db()?;
+    let (code, facturx) = match args {
+        [code] => (code, config.pdf.facturx),
+        [code, option, profile] if option == "--facturx" => {
+            let profile = profile
+                .parse()
+                .map_err(|_| usage(&format!("unknown Factur-X profile '{}'", profile)))?;
+            (code, Some(profile))
+        }
+        _ => return Err(usage("pdf needs an invoice code")),
+    };
+    let invoice = find_invoice(&db, code)?;
+    let pdf_config = PdfConfig {
+        facturx,
+        ..config.pdf
+    };
+    let path = pdf::generate_invoice_pdf(&db, &invoice, &config.issuer, &pdf_config)?;
+    println!("Saved {}", path);
+    if let Some(hash) = db
+        .get_invoice(invoice.id)?
+        .map(|invoice| invoice.pdf_hash)
+        .filter(|hash| !hash.is_empty())
+    {
+        println!("SHA-256 {}", hash);
+    }
+    Ok(())
+}
+
+fn find_invoice(db: &Database, code: &str) -> Result<Invoice, AppError> {
+    db.get_invoice_by_code(code)?
+        .ok_or_else(|| AppError::Validation(format!("No invoice with code '{}'", code)))
+}
+
+fn export_ubl(args: &[String]) -> Result<(), AppError> {
+    let [code] = args else {
+        return Err(usage("ubl needs an invoice code"));
+    };
+    let (db, config) = open_db()?;
+    let invoice = find_invoice(&db, code)?;
+    let path = ubl::export_ubl(&invoice, &config.issuer)?;
+    println!("Saved {}", path);
+    Ok(())
+}
+
+fn create_credit_note(args: &[String]) -> Result<(), AppError> {
+    let [code] = args else {
+        return Err(usage("credit-note needs an invoice code"));
+    };
+    let (db, _) = open_db()?;
+    let invoice = find_invoice(&db, code)?;
+    let note = credit_note::create(&db, invoice.id, &Local::now().date_naive().to_string())?;
+    println!(
+        "Created draft credit note {} for {}",
+        note.code, invoice.code
+    );
+    Ok(())
+}
+
+fn email(args: &[String]) -> Result<(), AppError> {
+    let (code, dry_run) = match args {
+        [code] => (code, false),
+        [code, option] if option == "--dry-run" => (code, true),
+        _ => return Err(usage("email needs an invoice code")),
+    };
+    let (db, mut config) = open_db()?;
+    config.mail.dry_run |= dry_run;
+    let invoice = find_invoice(&db, code)?;
+    let message = mail::send_invoice(&db, &invoice, &config.mail, &config.issuer, &config.pdf)?;
+    println!("{}", message);
+    Ok(())
+}
+
+fn reminders(args: &[String]) -> Result<(), AppError> {
+    if !args.is_empty() {
+        return Err(usage("reminders takes no arguments"));
+    }
+    let (db, config) = open_db()?;
+    let today = chrono::Local::now().date_naive();
+    let pending = dunning::pending(&db, &config.dunning, today)?;
+    if pending.is_empty() {
+        println!("No reminders due");
+    }
+    for reminder in &pending {
+        let inv = &reminder.invoice;
+        println!(
+            "{}  {}  {} days overdue  {:.2} {}  {}. {}",
+            inv.code,
+            inv.client.name,
+            reminder.days_overdue,
+            inv.balance(),
+            inv.currency,
+            reminder.level + 1,
+            config.dunning.levels[reminder.level].name
+        );
+    }
+    Ok(())
+}
+
+fn bank(args: &[String]) -> Result<(), AppError> {
+    let (db, config) = open_db()?;
+    match args {
+        [] => {}
+        [path] => println!("{}", bank::import(&db, path, &config.bank)?),
+        _ => return Err(usage("bank takes one statement file")),
+    }
+    let suggestions = bank::suggest(&db)?;
+    if suggestions.is_empty() {
+        println!("No open bank payments");
+    }
+    for suggestion in &suggestions {
+        let transaction = &suggestion.transaction;
+        let matched = match &suggestion.invoice {
+            Some(inv) => format!("-> {} ({})", inv.code, suggestion.reasons.join(", ")),
+            None => "no match".to_string(),
+        };
+        println!(
+            "{}  {:.2} {}  {}  {}  {}",
+            transaction.date,
+            transaction.amount,
+            transaction.currency,
+            transaction.name,
+            transaction.reference,
+            matched
+        );
+    }
+    Ok(())
+}
+
+fn time(args: &[String]) -> Result<(), AppError> {
+    let (db, config) = open_db()?;
+    let fields: Vec<&str> = args.iter().skip(1).map(String::as_str).collect();
+    let message = match args.first().map(String::as_str) {
+        None => {
+            for entry in db.get_time_entries()? {
+                let state = if entry.is_running() {
+                    "running"
+                } else if entry.invoice_id.is_some() {
+                    "billed"
+                } else {
+                    "unbilled"
+                };
+                println!(
+                    "{}  {:>6} h  {:>8.2}  {}  {}  {}  {}",
+                    entry.date,
+                    timesheet::format_minutes(entry.minutes),
+                    entry.amount(),
+                    entry.client.name,
+                    entry.project,
+                    entry.description,
+                    state
+                );
+            }
+            return Ok(());
+        }
+        Some(command @ ("add" | "start")) => {
+            let timer = command == "start";
+            let entry = timesheet::entry(
+                &fields,
+                &db.get_all_clients()?,
+                &db.get_projects()?,
+                &config.time,
+                timer,
+            )?;
+            if timer {
+                timesheet::start(&db, &entry)?
+            } else {
+                db.add_time_entry(&entry)?;
+                format!(
+                    "Added {} h for {}",
+                    timesheet::format_minutes(entry.minutes),
+                    entry.client.name
+                )
+            }
+        }
+        Some("stop") => timesheet::stop(&db, Local::now())?,
+        Some("bill") => {
+            let [client] = fields.as_slice() else {
+                return Err(usage("time bill needs a client"));
+            };
+            let clients = db.get_all_clients()?;
+            let client = timesheet::find_client(&clients, client)?;
+            timesheet::bill(&db, client.id, &config.time, Local::now().date_naive())?
+        }
+        Some(other) => return Err(usage(&format!("unknown time command '{}'", other))),
+    };
+    println!("{}", message);
+    Ok(())
+}
+
+fn expenses(args: &[String]) -> Result<(), AppError> {
+    let (db, config) = open_db()?;
+    let message = match args {
+        [] => {
+            for expense in db.get_expenses()? {
+                let billing = if expense.is_billed() {
+                    "billed"
+                } else if expense.billable {
+                    "billable"
+                } else {
+                    ""
+                };
+                println!(
+                    "{}  {:>10.2} {}  {}  {}  {}  {}",
+                    expense.date,
+                    expense.amount,
+                    expense.currency,
+                    expense.vendor,
+                    expense.category,
+                    expense.client.map(|client| client.name).unwrap_or_default(),
+                    billing
+                );
+            }
+            return Ok(());
+        }
+        [command, vendor, amount, options @ ..] if command == "add" => {
+            let mut options = options;
+            // Fields in the order `expense::entry` takes them.
+            let mut fields = [vendor.as_str(), amount.as_str(), "", "", "", "", ""];
+            while let [option, value, rest @ ..] = options {
+                options = rest;
+                let index = match option.as_str() {
+                    "--category" => 2,
+                    "--client" => 3,
+                    "--project" => 4,
+                    "--receipt" => 5,
+                    "--date" => 6,
+                    other => return Err(usage(&format!("unknown option '{}'", other))),
+                };
+                fields[index] = value.as_str();
+            }
+            if !options.is_empty() {
+                return Err(usage("options need a value"));
+            }
+            let expense = expense::entry(
+                &fields,
+                &db.get_all_clients()?,
+                &db.get_projects()?,
+                &config.expenses,
+            )?;
+            db.add_expense(&expense)?;
+            format!(
+                "Added {:.2} {} from {}",
+                expense.amount, expense.currency, expense.vendor
+            )
+        }
+        [command, client, options @ ..] if command == "bill" => {
+            let markup = match options {
+                [] => config.expenses.markup,
+                [option, value] if option == "--markup" => {
+                    expense::parse_markup(value, &config.expenses)?
+                }
+                _ => return Err(usage("expenses bill takes a client and --markup PERCENT")),
+            };
+            let clients = db.get_all_clients()?;
+            let client = timesheet::find_client(&clients, client)?;
+            expense::bill(
+                &db,
+                client.id,
+                markup,
+                &config.expenses,
+                Local::now().date_naive(),
+            )?
+        }
+        _ => return Err(usage("unknown expenses command")),
+    };
+    println!("{}", message);
+    Ok(())
+}
+
+fn projects(args: &[String]) -> Result<(), AppError> {
+    let (db, _) = open_db()?;
+    let message = match args {
+        [] => {
+            let clients = db.get_all_clients()?;
+            for budget in project::budgets(&db)? {
+                let project = &budget.project;
+                let client = clients
+                    .iter()
+                    .find(|client| client.id == project.client_id)
+                    .map_or("", |client| client.name.as_str());
+                println!(
+                    "{:>4}  {}  {}  {}  {:>10.2} of {:>10.2}  {}",
+                    project.id,
+                    client,
+                    project.name,
+                    project.po_number,
+                    budget.used,
+                    project.budget,
+                    project.status
+                );
+            }
+            return Ok(());
+        }
+        [command, client, name, options @ ..] if command == "add" => {
+            let mut options = options;
+            // Fields in the order `project::entry` takes them.
+            let mut fields = [name.as_str(), "", "", ""];
+            while let [option, value, rest @ ..] = options {
+                options = rest;
+                let index = match option.as_str() {
+                    "--po" => 1,
+                    "--budget" => 2,
+                    "--rate" => 3,
+                    other => return Err(usage(&format!("unknown option '{}'", other))),
+                };
+                fields[index] = value.as_str();
+            }
+            if !options.is_empty() {
+                return Err(usage("options need a value"));
+            }
+            let clients = db.get_all_clients()?;
+            let client = timesheet::find_client(&clients, client)?;
+            let new = project::entry(&fields, client)?;
+            let id = project::add(&db, &new)?;
+            format!("Added project {} ({}) for {}", new.name, id, client.name)
+        }
+        [command, id] if command == "close" => {
+            let id = id
+                .parse()
+                .map_err(|_| usage(&format!("'{}' is not a project id", id)))?;
+            db.set_project_status(id, "closed")?;
+            format!("Closed project {}", id)
+        }
+        _ => return Err(usage("unknown projects command")),
+    };
+    println!("{}", message);
+    Ok(())
+}
+
+fn export_ledger(args: &[String]) -> Result<(), AppError> {
+    let (format, range) = match args {
+        [format] => (format, DateRange::default()),
+        [format, range] => (format, range.parse()?),
+        _ => return Err(usage("ledger needs a format")),
+    };
+    let format: LedgerFormat = format.parse().map_err(|_| {
+        usage(&format!(
+            "unknown ledger format '{}' (ledger, hledger or beancount)",
+            format
+        ))
+    })?;
+    let (db, config) = open_db()?;
+    let path = ledger::export(&db, &config.ledger, format, range)?;
+    println!("Saved {}", path);
+    Ok(())
+}
+
+fn remind(args: &[String]) -> Result<(), AppError> {
+    let (code, dry_run) = match args {
+        [code] => (code, false),
+        [code, option] if option == "--dry-run" => (code, true),
+        _ => return Err(usage("remind needs an invoice code")),
+    };
+    let (db, mut config) = open_db()?;
+    config.mail.dry_run |= dry_run;
+    let today = chrono::Local::now().date_naive();
+    let reminder = dunning::pending(&db, &config.dunning, today)?
+        .into_iter()
+        .find(|reminder| reminder.invoice.code == *code)
+        .ok_or_else(|| AppError::Validation(format!("No reminder due for '{}'", code)))?;
+    let message = dunning::approve(
+        &db,
+        &reminder,
+        &config.dunning,
+        &config.mail,
+        &config.issuer,
+    )?;
+    println!("{}", message);
+    Ok(())
+}
+
+fn serve(args: &[String]) -> Result<(), AppError> {
+    let (mut db, config) = open_db()?;
+    db.set_actor("api");
+    let bind = match args {
+        [] => config.api.bind.clone(),
+        [option, bind] if option == "--bind" => bind.clone(),
+        _ => return Err(usage("serve takes only --bind")),
+    };
+    let token = config.api.token();
+    if token.is_empty() {
+        return Err(AppError::Config(
+            "set 'token' in the [api] table or INVOICE_RS_API_TOKEN".to_string(),
+        ));
+    }
+    let state = ApiState::new(db, &token, &config);
+    webhook::spawn(state.db(), config.webhooks.clone());
+    api::serve(state, &bind)
+}
+
+fn show_audit(args: &[String]) -> Result<(), AppError> {
+    let (db, _) = open_db()?;
+    let invoice_id = match args {
+        [] => None,
+        [code] => Some(find_invoice(&db, code)?.id),
+        _ => return Err(usage("audit takes at most an invoice code")),
+    };
+    for entry in db.get_audit_log(invoice_id)? {
+        println!(
+            "{}  {}  {} {} {}  {}",
+            entry.id, entry.at, entry.action, entry.entity, entry.entity_id, entry.actor
+        );
+        for line in audit::describe(&entry) {
+            println!("    {}", line);
+        }
+    }
+    match audit::verify(&db.get_audit_log(None)?) {
+        Ok(()) => println!("Audit log intact"),
+        Err(id) => {
+            return Err(AppError::Validation(format!(
+                "The audit log was tampered with at entry {}",
+                id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn webhooks(args: &[String]) -> Result<(), AppError> {
+    let (db, config) = open_db()?;
+    let db = Mutex::new(db);
+    match args {
+        [] => {}
+        [option] if option == "--deliver" => {
+            let delivered = webhook::run_once(&db, &config.webhooks, Utc::now().timestamp())?;
+            println!("Delivered {} webhook(s)", delivered);
+        }
+        _ => return Err(usage("webhooks takes only --deliver")),
+    }
+    let deliveries = db.lock().unwrap().get_deliveries(20)?;
+    if deliveries.is_empty() {
+        println!("No webhook deliveries yet");
+    }
+    for delivery in deliveries.iter().rev() {
+        let code = delivery
+            .response_code
+            .map(|code| code.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<6} {}  {:<18} invoice {:<5} {:<10} {:>2} tries  {:>3}  {}  {}",
+            delivery.id,
+            delivery.updated_at,
+            delivery.event.kind,
+            delivery.event.invoice_id,
+            delivery.status,
+            delivery.attempts,
+            code,
+            delivery.url,
+            delivery.error
+        );
+    }
+    Ok(())
+}
+
+fn backup(args: &[String]) -> Result<(), AppError> {
+    let [path] = args else {
+        return Err(usage("backup needs a file"));
+    };
+    let (db, _) = open_db()?;
+    let backup = Backup::export(&db)?;
+    backup.write(path)?;
+    println!(
+        "Saved {} clients, {} invoices and {} payments to {}",
+        backup.clients.len(),
+        backup.invoices.len(),
+        backup.payments.len(),
+        path
+    );
+    Ok(())
+}
+
+fn restore(args: &[String]) -> Result<(), AppError> {
+    let (path, mode) = match args {
+        [path] => (path, RestoreMode::Merge),
+        [path, option] => match option.as_str() {
+            "--merge" => (path, RestoreMode::Merge),
+            "--replace" => (path, RestoreMode::Replace),
+            other => return Err(usage(&format!("unknown option '{}'", other))),
+        },
+        _ => return Err(usage("restore needs a file")),
+    };
+    let backup = Backup::read(path)?;
+    let (db, config) = open_db()?;
+    let report = backup::restore(&db, &backup, mode, &config.backup)?;
+    for conflict in &report.conflicts {
+        println!("conflict {}", conflict);
+    }
+    println!("{}", report.summary());
+    Ok(())
+}
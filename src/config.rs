@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::api::ApiConfig;
+use crate::backup::BackupConfig;
+use crate::bank::BankConfig;
+use crate::dunning::DunningConfig;
+use crate::error::AppError;
+use crate::expense::ExpenseConfig;
+use crate::keymap::KeymapConfig;
+use crate::ledger::LedgerConfig;
+use crate::mail::MailConfig;
+use crate::pdf::PdfConfig;
+use crate::timesheet::TimeConfig;
+use crate::webhook::WebhookConfig;
+
+const CONFIG_PATH: &str = "invoice-rs.toml";
+
+/// User settings read from `invoice-rs.toml` in the working directory.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub keys: KeymapConfig,
+    pub backup: BackupConfig,
+    pub issuer: IssuerConfig,
+    pub pdf: PdfConfig,
+    pub mail: MailConfig,
+    pub dunning: DunningConfig,
+    pub api: ApiConfig,
+    pub webhooks: WebhookConfig,
+    pub ledger: LedgerConfig,
+    pub time: TimeConfig,
+    pub expenses: ExpenseConfig,
+    pub bank: BankConfig,
+}
+
+/// The business issuing invoices, from the `[issuer]` table. E-invoice
+/// exports need the address, VAT id, electronic address and bank account.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IssuerConfig {
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub postal_code: String,
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`.
+    pub country: String,
+    pub vat_id: String,
+    /// Electronic address as `scheme:id`, e.g. `0088:5790000435951`.
+    pub endpoint: String,
+    pub email: String,
+    pub iban: String,
+    pub bic: String,
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults when it doesn't exist.
+    pub fn load() -> Result<Self, AppError> {
+        if !Path::new(CONFIG_PATH).exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(CONFIG_PATH)?;
+        toml::from_str(&text).map_err(|e| AppError::Config(e.to_string()))
+    }
+}
@@ -0,0 +1,59 @@
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::Invoice;
+
+/// Add a draft credit note dated `date` that cancels issued invoice `id` in
+/// full, and return it. Edit the draft to credit less. The first note for an
+/// invoice is `CN-<code>`, later ones `CN-<code>-2`, `CN-<code>-3` and so on.
+pub fn create(db: &Database, id: u32, date: &str) -> Result<Invoice, AppError> {
+    let invoice = db
+        .get_invoice(id)?
+        .ok_or_else(|| AppError::Validation("Invoice not found.".to_string()))?;
+    if !invoice.is_issued() {
+        return Err(AppError::Validation(format!(
+            "Invoice {} hasn't been issued; edit or delete it instead.",
+            invoice.code
+        )));
+    }
+    if invoice.is_credit_note() || matches!(invoice.status.as_str(), "void" | "cancelled") {
+        return Err(AppError::Validation(format!(
+            "Invoice {} can't be credited.",
+            invoice.code
+        )));
+    }
+
+    if invoice.credited >= invoice.calculate_net_total() - 0.005 {
+        return Err(AppError::Validation(format!(
+            "Invoice {} has already been credited in full.",
+            invoice.code
+        )));
+    }
+
+    let mut note = invoice.credit_note(&next_code(db, &invoice.code)?, date);
+    note.id = db.add_invoice(&note)?;
+    Ok(note)
+}
+
+/// The first of `CN-<code>`, `CN-<code>-2`, ... that isn't taken yet.
+fn next_code(db: &Database, code: &str) -> Result<String, AppError> {
+    let base = format!("CN-{}", code);
+    let mut candidate = base.clone();
+    for n in 2.. {
+        if !db.invoice_code_exists(&candidate, None)? {
+            break;
+        }
+        candidate = format!("{}-{}", base, n);
+    }
+    Ok(candidate)
+}
+
+/// Refuse to change or delete an issued invoice.
+pub fn check_editable(invoice: &Invoice) -> Result<(), AppError> {
+    if invoice.is_issued() {
+        return Err(AppError::Validation(format!(
+            "Invoice {} has been issued and can't be changed; correct it with a credit note.",
+            invoice.code
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+/// Split CSV text into records. Handles quoted fields with embedded commas,
+/// quotes and line breaks, and both `\n` and `\r\n` line endings.
+pub fn parse(text: &str) -> Result<Vec<Vec<String>>, String> {
+    parse_with(text, ',')
+}
+
+/// Like `parse`, with fields separated by `separator`, e.g. `;` in bank
+/// exports from countries using a decimal comma.
+pub fn parse_with(text: &str, separator: char) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            c if c == separator => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("unterminated quoted field on line {}", line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    // Blank lines carry no data.
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+    for field in records.iter_mut().flatten() {
+        if field.starts_with('\'') && is_formula(&field[1..]) {
+            field.remove(0);
+        }
+    }
+    Ok(records)
+}
+
+/// Whether a spreadsheet would run `value` as a formula, or it is one with
+/// the apostrophe `field` adds, which must be kept when read back.
+fn is_formula(value: &str) -> bool {
+    match value.chars().next() {
+        Some('=' | '+' | '-' | '@' | '\t' | '\r') => true,
+        Some('\'') => is_formula(&value[1..]),
+        _ => false,
+    }
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break.
+/// Fields a spreadsheet would run as a formula get an apostrophe in front,
+/// which `parse` removes again.
+pub fn field(value: &str) -> String {
+    let value = if is_formula(value) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Append one CSV record to `out`.
+pub fn write_record<S: AsRef<str>>(out: &mut String, values: &[S]) {
+    let fields: Vec<String> = values.iter().map(|value| field(value.as_ref())).collect();
+    out.push_str(&fields.join(","));
+    out.push_str("\r\n");
+}
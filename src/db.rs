@@ -1,20 +1,143 @@
-use crate::models::{Client, Invoice, Item};
-use rusqlite::{Connection, Result, params};
+use crate::audit;
+use crate::backup::{self, Backup, BackupConfig, RestoreReport};
+use crate::config::IssuerConfig;
+use crate::error::AppError;
+use crate::models::{
+    AuditAction, AuditEntity, AuditEntry, BankTransaction, Client, ClientRevenue, DashboardStats,
+    EventKind, Expense, Invoice, InvoiceFilter, InvoiceSort, Item, MonthlyTotals, Payment, Project,
+    Reminder, SentEmail, SortColumn, SortDirection, TimeEntry, WebhookDelivery, WebhookEvent,
+};
+use crate::report::ReportKind;
+use rusqlite::{
+    Connection, OptionalExtension, Result, Row, params, params_from_iter, types::Value,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
 
+const DB_PATH: &str = "invoice-rs.db";
+const SCHEMA_VERSION: i32 = 11;
+/// The day `emit_overdue` last looked for overdue invoices.
+const OVERDUE_SETTING: &str = "last_overdue_check";
+
+// Invoice rows are identified by their rowid; the `id` column mirrors it.
+const INVOICE_SELECT: &str = "
+    SELECT invoices.rowid, invoices.code, invoices.total, invoices.currency, invoices.discount,
+           invoices.tax, invoices.paid, invoices.status, invoices.date, invoices.due_date,
+           CAST(c.id AS INTEGER), c.name, c.phone, c.email, c.address, invoices.void_reason,
+           c.country, c.vat_id, c.endpoint, c.reference,
+           pr.id, CAST(pr.client_id AS INTEGER), pr.name, pr.po_number, pr.budget, pr.rate,
+           pr.status, invoices.issued_at, invoices.issued_client, invoices.issued_issuer,
+           invoices.pdf_hash, invoices.credit_note_for, COALESCE(cr.amount, 0),
+           invoices.issued_project
+    FROM invoices
+    JOIN clients c ON invoices.client_id = c.id
+    LEFT JOIN projects pr ON invoices.project_id = pr.id
+    LEFT JOIN credits cr ON cr.code = invoices.code";
+
+// Tables a backup carries row by row besides clients, projects, invoices and
+// payments, parents first, with the columns that refer to rows of other tables.
+const BACKUP_TABLES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "time_entries",
+        &[
+            ("client_id", "clients"),
+            ("project_id", "projects"),
+            ("invoice_id", "invoices"),
+        ],
+    ),
+    (
+        "expenses",
+        &[
+            ("client_id", "clients"),
+            ("project_id", "projects"),
+            ("invoice_id", "invoices"),
+        ],
+    ),
+    (
+        "reminders",
+        &[("invoice_id", "invoices"), ("fee_invoice_id", "invoices")],
+    ),
+    ("emails", &[("invoice_id", "invoices")]),
+    ("bank_transactions", &[("invoice_id", "invoices")]),
+    ("events", &[("invoice_id", "invoices")]),
+    ("webhook_deliveries", &[("event_id", "events")]),
+    ("issued_pdfs", &[("invoice_id", "invoices")]),
+];
+
+// Invoices left out of revenue and receivables figures.
+const EXCLUDED_STATUSES: &str = "'draft', 'void', 'cancelled'";
+const NET_TOTAL: &str = "(invoices.total - invoices.discount + invoices.tax)";
+// What is left to pay once payments and issued credit notes are taken off.
+const BALANCE: &str = "(invoices.total - invoices.discount + invoices.tax - invoices.paid
+    - COALESCE((SELECT amount FROM credits WHERE credits.code = invoices.code), 0))";
+// The client name an invoice shows: its snapshot once issued, as read by
+// `invoice_from_row`, so sorting, searching and paging agree with the list.
+const CLIENT_NAME: &str = "COALESCE(json_extract(invoices.issued_client, '$.name'), c.name)";
+
+/// Position after which the next page of invoices starts.
+#[derive(Clone)]
+pub struct InvoiceCursor {
+    key: Value,
+    id: u32,
+}
+
+impl InvoiceCursor {
+    pub fn new(sort: &InvoiceSort, invoice: &Invoice) -> Self {
+        let key = match sort.column {
+            SortColumn::Code => Value::Text(invoice.code.clone()),
+            SortColumn::Date => Value::Text(invoice.date.clone()),
+            SortColumn::DueDate => Value::Text(invoice.due_date.clone()),
+            // The snapshot name for issued invoices, matching `CLIENT_NAME`.
+            SortColumn::Client => Value::Text(invoice.client.name.clone()),
+            SortColumn::NetTotal => Value::Real(invoice.calculate_net_total()),
+            SortColumn::Balance => Value::Real(invoice.balance()),
+            SortColumn::Status => Value::Text(invoice.status.clone()),
+        };
+        Self {
+            key,
+            id: invoice.id,
+        }
+    }
+}
+
+/// The connection, who changes made through it are logged as, and the
+/// issuer snapshotted onto invoices when they are issued.
 #[derive(Debug)]
-pub struct Database(Connection);
+pub struct Database {
+    conn: Connection,
+    actor: String,
+    issuer: IssuerConfig,
+}
 
 impl Database {
-    pub fn new() -> Result<Self> {
-        let conn = Connection::open("invoice-rs.db")?;
+    /// Open `invoice-rs.db`, copying it into the backup directory first when
+    /// it is about to be migrated.
+    pub fn new(backup: &BackupConfig) -> Result<Self, AppError> {
+        let conn = Connection::open(DB_PATH)?;
+        if needs_migration(&conn)? {
+            let path = backup::snapshot_path(backup, "pre-migration")?;
+            copy_to(&conn, &path)?;
+            backup::prune(backup)?;
+        }
+        Ok(Self::init(conn)?)
+    }
+
+    /// Create any missing tables on `conn` and migrate it to the current schema.
+    fn init(conn: Connection) -> Result<Self> {
         conn.execute_batch(
             "
+            PRAGMA foreign_keys = ON;
+
             CREATE TABLE IF NOT EXISTS clients (
                 id TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
                 phone TEXT NOT NULL,
                 email TEXT NOT NULL,
                 address TEXT NOT NULL,
+                country TEXT NOT NULL DEFAULT '',
+                vat_id TEXT NOT NULL DEFAULT '',
+                endpoint TEXT NOT NULL DEFAULT '',
+                reference TEXT NOT NULL DEFAULT '',
                 UNIQUE (id)
             );
 
@@ -24,106 +147,2854 @@ impl Database {
                 client_id TEXT NOT NULL,
                 total REAL NOT NULL,
                 currency TEXT NOT NULL DEFAULT 'USD',
+                discount REAL NOT NULL DEFAULT 0,
+                tax REAL NOT NULL DEFAULT 0,
+                paid REAL NOT NULL DEFAULT 0,
                 date TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                due_date TEXT NOT NULL DEFAULT '',
                 status TEXT NOT NULL DEFAULT 'draft',
-                items JSON NOT NULL DEFAULT '[]',
+                void_reason TEXT NOT NULL DEFAULT '',
+                project_id INTEGER,
+                issued_at TEXT NOT NULL DEFAULT '',
+                issued_client TEXT,
+                issued_issuer TEXT,
+                issued_project TEXT,
+                pdf_hash TEXT NOT NULL DEFAULT '',
+                credit_note_for TEXT NOT NULL DEFAULT '',
                 FOREIGN KEY (client_id) REFERENCES clients(id),
                 UNIQUE (code),
                 UNIQUE (id)
             );
+
+            CREATE TABLE IF NOT EXISTS projects (
+                id INTEGER PRIMARY KEY,
+                client_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                po_number TEXT NOT NULL DEFAULT '',
+                budget REAL NOT NULL DEFAULT 0,
+                rate REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'active',
+                FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE CASCADE,
+                UNIQUE (client_id, name)
+            );
+
+            CREATE TABLE IF NOT EXISTS invoice_items (
+                id INTEGER PRIMARY KEY,
+                invoice_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                quantity INTEGER,
+                price REAL,
+                tax_rate REAL,
+                remark TEXT,
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+                UNIQUE (invoice_id, position)
+            );
+
+            CREATE TABLE IF NOT EXISTS payments (
+                id INTEGER PRIMARY KEY,
+                invoice_id TEXT NOT NULL,
+                amount REAL NOT NULL,
+                date TEXT NOT NULL,
+                reference TEXT NOT NULL DEFAULT '',
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS emails (
+                id INTEGER PRIMARY KEY,
+                invoice_id TEXT NOT NULL,
+                sent_at TEXT NOT NULL,
+                recipient TEXT NOT NULL,
+                subject TEXT NOT NULL,
+                status TEXT NOT NULL,
+                detail TEXT NOT NULL DEFAULT '',
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS reminders (
+                id INTEGER PRIMARY KEY,
+                invoice_id TEXT NOT NULL,
+                level INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                date TEXT NOT NULL,
+                fees REAL NOT NULL DEFAULT 0,
+                status TEXT NOT NULL,
+                fee_invoice_id INTEGER,
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY,
+                kind TEXT NOT NULL,
+                invoice_id TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                dispatched INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS webhook_deliveries (
+                id INTEGER PRIMARY KEY,
+                event_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                status TEXT NOT NULL DEFAULT 'pending',
+                response_code INTEGER,
+                error TEXT NOT NULL DEFAULT '',
+                next_attempt_at INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                claimed_by TEXT,
+                FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS bank_transactions (
+                id INTEGER PRIMARY KEY,
+                bank_id TEXT NOT NULL UNIQUE,
+                date TEXT NOT NULL,
+                amount REAL NOT NULL,
+                currency TEXT NOT NULL DEFAULT '',
+                name TEXT NOT NULL DEFAULT '',
+                reference TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL DEFAULT 'open',
+                invoice_id TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS time_entries (
+                id INTEGER PRIMARY KEY,
+                client_id TEXT NOT NULL,
+                project TEXT NOT NULL DEFAULT '',
+                project_id INTEGER,
+                description TEXT NOT NULL DEFAULT '',
+                date TEXT NOT NULL,
+                minutes INTEGER NOT NULL DEFAULT 0,
+                rate REAL NOT NULL DEFAULT 0,
+                started_at TEXT,
+                invoice_id TEXT,
+                FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE CASCADE,
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS expenses (
+                id INTEGER PRIMARY KEY,
+                date TEXT NOT NULL,
+                vendor TEXT NOT NULL,
+                amount REAL NOT NULL,
+                currency TEXT NOT NULL DEFAULT 'USD',
+                category TEXT NOT NULL DEFAULT '',
+                receipt TEXT NOT NULL DEFAULT '',
+                client_id TEXT,
+                project_id INTEGER,
+                billable INTEGER NOT NULL DEFAULT 0,
+                invoice_id TEXT,
+                FOREIGN KEY (client_id) REFERENCES clients(id) ON DELETE SET NULL,
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS issued_pdfs (
+                id INTEGER PRIMARY KEY,
+                invoice_id TEXT NOT NULL UNIQUE,
+                pdf BLOB NOT NULL,
+                FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY,
+                at TEXT NOT NULL,
+                actor TEXT NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id INTEGER NOT NULL,
+                invoice_id INTEGER,
+                action TEXT NOT NULL,
+                changes TEXT NOT NULL,
+                prev_hash TEXT NOT NULL,
+                hash TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS audit_log_invoice ON audit_log (invoice_id);
+
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'the audit log is append-only');
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+                SELECT RAISE(ABORT, 'the audit log is append-only');
+            END;
+
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
             ",
         )?;
-        Ok(Self(conn))
+        migrate(&conn)?;
+        Ok(Self {
+            conn,
+            actor: audit::user(),
+            issuer: IssuerConfig::default(),
+        })
+    }
+
+    /// Log later changes as made by `actor`.
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_string();
+    }
+
+    /// Snapshot `issuer` onto invoices issued from now on.
+    pub fn set_issuer(&mut self, issuer: &IssuerConfig) {
+        self.issuer = issuer.clone();
+    }
+
+    /// In-memory fallback with the full schema, used when the file can't be opened.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    /// Insert a new invoice and return its id.
+    pub fn add_invoice(&self, invoice: &Invoice) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        let id = insert_invoice(&tx, invoice, None)?;
+        self.issue(id)?;
+        self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+        self.emit(EventKind::InvoiceCreated, id, None)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Insert all of `invoices` in one transaction, or none if any fails.
+    pub fn import_invoices(&self, invoices: &[Invoice]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        for invoice in invoices {
+            let id = insert_invoice(&tx, invoice, None)?;
+            self.issue(id)?;
+            self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+            self.emit(EventKind::InvoiceCreated, id, None)?;
+        }
+        tx.commit()?;
+        Ok(invoices.len())
+    }
+
+    /// Store changes to a draft; issued invoices are refused.
+    pub fn update_invoice(&self, invoice: &Invoice) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.check_unissued(invoice.id)?;
+        let before = self.snapshot(AuditEntity::Invoice, invoice.id)?;
+        update_invoice_row(&tx, invoice)?;
+        self.issue(invoice.id)?;
+        self.audit(
+            AuditEntity::Invoice,
+            invoice.id,
+            AuditAction::Update,
+            before,
+        )?;
+        self.emit(EventKind::InvoiceUpdated, invoice.id, None)?;
+        tx.commit()
+    }
+
+    /// Whether an invoice other than `exclude_id` already uses `code`.
+    pub fn invoice_code_exists(&self, code: &str, exclude_id: Option<u32>) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM invoices WHERE code = ?1 AND rowid IS NOT ?2)",
+            params![code, exclude_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Delete a draft; issued invoices are refused.
+    pub fn delete_invoice(&self, id: u32) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.check_unissued(id)?;
+        let before = self.snapshot(AuditEntity::Invoice, id)?;
+        tx.execute("DELETE FROM invoices WHERE rowid = ?1", [id])?;
+        self.audit(AuditEntity::Invoice, id, AuditAction::Delete, before)?;
+        tx.commit()
+    }
+
+    /// Void a draft. Issued invoices are cancelled with a credit note
+    /// instead, so that both documents stay on record.
+    pub fn void_invoice(&self, id: u32, reason: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.check_unissued(id)?;
+        let before = self.snapshot(AuditEntity::Invoice, id)?;
+        tx.execute(
+            "UPDATE invoices SET status = 'void', void_reason = ?1 WHERE rowid = ?2",
+            params![reason, id],
+        )?;
+        self.audit(AuditEntity::Invoice, id, AuditAction::Status, before)?;
+        self.emit(EventKind::InvoiceUpdated, id, None)?;
+        tx.commit()
+    }
+
+    /// Record a payment and mark the invoice paid once its balance is settled.
+    pub fn record_payment(
+        &self,
+        invoice_id: u32,
+        amount: f64,
+        date: &str,
+        reference: &str,
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.apply_payment(invoice_id, amount, date, reference)?;
+        tx.commit()
+    }
+
+    /// The body of `record_payment`, for use inside a transaction.
+    fn apply_payment(
+        &self,
+        invoice_id: u32,
+        amount: f64,
+        date: &str,
+        reference: &str,
+    ) -> Result<()> {
+        self.check_payable(invoice_id)?;
+        let before = self.snapshot(AuditEntity::Invoice, invoice_id)?;
+        self.conn.execute(
+            "INSERT INTO payments (invoice_id, amount, date, reference) VALUES (?1, ?2, ?3, ?4)",
+            params![invoice_id, amount, date, reference],
+        )?;
+        let payment_id = self.conn.last_insert_rowid() as u32;
+        self.audit(AuditEntity::Payment, payment_id, AuditAction::Create, None)?;
+        let payment = serde_json::json!({
+            "id": payment_id,
+            "amount": amount,
+            "date": date,
+            "reference": reference,
+        });
+        self.conn.execute(
+            &format!(
+                "UPDATE invoices
+                 SET paid = paid + ?1,
+                     status = CASE WHEN {BALANCE} - ?1 <= 0 THEN 'paid' ELSE status END
+                 WHERE rowid = ?2"
+            ),
+            params![amount, invoice_id],
+        )?;
+        self.issue(invoice_id)?;
+        self.audit(
+            AuditEntity::Invoice,
+            invoice_id,
+            AuditAction::Update,
+            before,
+        )?;
+        self.emit(
+            EventKind::PaymentRecorded,
+            invoice_id,
+            Some(("payment", payment)),
+        )
+    }
+
+    /// Move a draft invoice to sent; later statuses are left alone.
+    pub fn mark_sent(&self, id: u32) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let before = self.snapshot(AuditEntity::Invoice, id)?;
+        let changed = tx.execute(
+            "UPDATE invoices SET status = 'sent' WHERE rowid = ?1 AND status = 'draft'",
+            [id],
+        )?;
+        if changed > 0 {
+            self.issue(id)?;
+            self.audit(AuditEntity::Invoice, id, AuditAction::Status, before)?;
+            self.emit(EventKind::InvoiceSent, id, None)?;
+        }
+        tx.commit()
+    }
+
+    /// Keep the PDF first rendered for an issued invoice, and its hash.
+    /// An invoice that already has a PDF keeps it.
+    pub fn set_issued_pdf(&self, id: u32, hash: &str, pdf: &[u8]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let before = self.snapshot(AuditEntity::Invoice, id)?;
+        tx.execute(
+            "UPDATE invoices SET pdf_hash = ?1 WHERE rowid = ?2 AND issued_at != '' AND pdf_hash = ''",
+            params![hash, id],
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO issued_pdfs (invoice_id, pdf)
+             SELECT ?1, ?2 WHERE EXISTS (
+                 SELECT 1 FROM invoices WHERE rowid = ?1 AND issued_at != '' AND pdf_hash = ?3
+             )",
+            params![id, pdf, hash],
+        )?;
+        self.audit(AuditEntity::Invoice, id, AuditAction::Update, before)?;
+        tx.commit()
+    }
+
+    /// The PDF kept for an issued invoice, if it has been rendered yet.
+    pub fn get_issued_pdf(&self, id: u32) -> Result<Option<Vec<u8>>> {
+        self.conn
+            .query_row(
+                "SELECT pdf FROM issued_pdfs WHERE invoice_id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    /// Freeze invoice `id` once it has left draft: record when, and
+    /// snapshot its client, its project and the issuer. Called inside the transaction
+    /// that changed its status; drafts, void or cancelled invoices and
+    /// invoices already issued are left alone.
+    fn issue(&self, id: u32) -> Result<()> {
+        let Some(invoice) = self.get_invoice(id)? else {
+            return Ok(());
+        };
+        self.conn.execute(
+            &format!(
+                "UPDATE invoices
+                 SET issued_at = ?1, issued_client = ?2, issued_issuer = ?3, issued_project = ?5
+                 WHERE rowid = ?4 AND issued_at = '' AND status NOT IN ({EXCLUDED_STATUSES})"
+            ),
+            params![
+                chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                to_json(&invoice.client)?,
+                to_json(&self.issuer)?,
+                id,
+                invoice.project.as_ref().map(to_json).transpose()?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Fail when invoice `id` has been issued, whose content must not change.
+    fn check_unissued(&self, id: u32) -> Result<()> {
+        match self.get_invoice(id)? {
+            Some(invoice) if invoice.is_issued() => Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+                Some(format!(
+                    "invoice {} has been issued; correct it with a credit note",
+                    invoice.code
+                )),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail unless invoice `id` can take payments: drafts, void and
+    /// cancelled invoices are owed nothing, and a credit note's balance is
+    /// owed to the client instead.
+    pub fn check_payable(&self, id: u32) -> Result<()> {
+        let payable: Option<(String, bool)> = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT code, status NOT IN ({EXCLUDED_STATUSES}) AND credit_note_for = ''
+                     FROM invoices WHERE rowid = ?1"
+                ),
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match payable {
+            Some((code, false)) => Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CONSTRAINT),
+                Some(format!(
+                    "invoice {} is a draft, void, cancelled or a credit note and takes no payments",
+                    code
+                )),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn log_email(&self, email: &SentEmail) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO emails (invoice_id, sent_at, recipient, subject, status, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                email.invoice_id,
+                email.sent_at,
+                email.recipient,
+                email.subject,
+                email.status,
+                email.detail,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Emails sent for an invoice, oldest first.
+    pub fn get_emails(&self, invoice_id: u32) -> Result<Vec<SentEmail>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(invoice_id AS INTEGER), sent_at, recipient, subject, status, detail
+             FROM emails
+             WHERE invoice_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([invoice_id], |row| {
+            Ok(SentEmail {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                sent_at: row.get(2)?,
+                recipient: row.get(3)?,
+                subject: row.get(4)?,
+                status: row.get(5)?,
+                detail: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Unpaid invoices whose due date is before `today`, with the highest
+    /// dunning level each has reached so far.
+    pub fn get_overdue(&self, today: &str) -> Result<Vec<(Invoice, u32)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{INVOICE_SELECT}
+             WHERE invoices.status NOT IN ({EXCLUDED_STATUSES})
+               AND invoices.due_date != '' AND invoices.due_date < ?1
+               AND {BALANCE} > 0
+               AND invoices.rowid NOT IN (
+                   SELECT fee_invoice_id FROM reminders WHERE fee_invoice_id IS NOT NULL
+               )
+             ORDER BY invoices.due_date, invoices.rowid"
+        ))?;
+        let invoices = stmt
+            .query_map([today], invoice_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT COALESCE(MAX(level), 0) FROM reminders WHERE invoice_id = ?1")?;
+        invoices
+            .into_iter()
+            .map(|invoice| {
+                let level = stmt.query_row([invoice.id], |row| row.get(0))?;
+                Ok((invoice, level))
+            })
+            .collect()
+    }
+
+    /// Record a reminder and bill its `fees` on a new invoice
+    /// `<code>-F<level>`, as the reminded invoice is already issued. Fee
+    /// invoices aren't dunned themselves. Returns the reminder's id.
+    pub fn add_reminder(&self, reminder: &Reminder, fees: &[Item]) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO reminders (invoice_id, level, name, date, fees, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                reminder.invoice_id,
+                reminder.level,
+                reminder.name,
+                reminder.date,
+                reminder.fees,
+                reminder.status,
+            ],
+        )?;
+        let reminder_id = tx.last_insert_rowid() as u32;
+        if let Some(invoice) = self.get_invoice(reminder.invoice_id)?
+            && !fees.is_empty()
+        {
+            let fee_invoice = Invoice {
+                code: format!("{}-F{}", invoice.code, reminder.level),
+                client: invoice.client,
+                currency: invoice.currency,
+                total: reminder.fees,
+                status: "sent".to_string(),
+                date: reminder.date.clone(),
+                due_date: reminder.date.clone(),
+                project: invoice.project,
+                items: fees.to_vec(),
+                ..Invoice::default()
+            };
+            let id = insert_invoice(&tx, &fee_invoice, None)?;
+            self.issue(id)?;
+            self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+            self.emit(EventKind::InvoiceCreated, id, None)?;
+            tx.execute(
+                "UPDATE reminders SET fee_invoice_id = ?1 WHERE id = ?2",
+                params![id, reminder_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(reminder_id)
     }
 
-    pub fn empty() -> Self {
-        // In-memory fallback
-        let conn = Connection::open_in_memory().expect("Failed to create in-memory DB");
-        Self(conn)
+    pub fn set_reminder_status(&self, id: u32, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE reminders SET status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+
+    /// Reminders sent or skipped for an invoice, oldest first.
+    pub fn get_reminders(&self, invoice_id: u32) -> Result<Vec<Reminder>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(invoice_id AS INTEGER), level, name, date, fees, status
+             FROM reminders
+             WHERE invoice_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([invoice_id], |row| {
+            Ok(Reminder {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                level: row.get(2)?,
+                name: row.get(3)?,
+                date: row.get(4)?,
+                fees: row.get(5)?,
+                status: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Store statement transactions, skipping those whose `bank_id` is
+    /// already known. Returns how many were new.
+    pub fn import_transactions(&self, transactions: &[BankTransaction]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut added = 0;
+        for transaction in transactions {
+            added += tx.execute(
+                "INSERT OR IGNORE INTO bank_transactions
+                     (bank_id, date, amount, currency, name, reference)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    transaction.bank_id,
+                    transaction.date,
+                    transaction.amount,
+                    transaction.currency,
+                    transaction.name,
+                    transaction.reference,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(added)
+    }
+
+    /// Transactions not yet matched or set aside, oldest first.
+    pub fn get_open_transactions(&self) -> Result<Vec<BankTransaction>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, bank_id, date, amount, currency, name, reference
+             FROM bank_transactions
+             WHERE status = 'open'
+             ORDER BY date, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(BankTransaction {
+                id: row.get(0)?,
+                bank_id: row.get(1)?,
+                date: row.get(2)?,
+                amount: row.get(3)?,
+                currency: row.get(4)?,
+                name: row.get(5)?,
+                reference: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Record an open transaction as a payment on `invoice_id`.
+    pub fn accept_transaction(&self, transaction: &BankTransaction, invoice_id: u32) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let reference = if transaction.reference.is_empty() {
+            &transaction.bank_id
+        } else {
+            &transaction.reference
+        };
+        self.apply_payment(invoice_id, transaction.amount, &transaction.date, reference)?;
+        tx.execute(
+            "UPDATE bank_transactions SET status = 'matched', invoice_id = ?1 WHERE id = ?2",
+            params![invoice_id, transaction.id],
+        )?;
+        tx.commit()
+    }
+
+    /// Set a transaction aside so it's no longer offered for matching.
+    pub fn reject_transaction(&self, id: u32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE bank_transactions SET status = 'rejected' WHERE id = ?1",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Invoices with a balance left to pay, oldest due date first.
+    pub fn get_unpaid_invoices(&self) -> Result<Vec<Invoice>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{INVOICE_SELECT}
+             WHERE invoices.status NOT IN ({EXCLUDED_STATUSES})
+               AND {BALANCE} > 0
+             ORDER BY invoices.due_date, invoices.rowid"
+        ))?;
+        let rows = stmt.query_map([], invoice_from_row)?;
+        rows.collect()
+    }
+
+    pub fn add_project(&self, project: &Project) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO projects (client_id, name, po_number, budget, rate, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                project.client_id,
+                project.name,
+                project.po_number,
+                project.budget,
+                project.rate,
+                project.status,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    /// All projects, active ones first, by name.
+    pub fn get_projects(&self) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(client_id AS INTEGER), name, po_number, budget, rate, status
+             FROM projects
+             ORDER BY status != 'active', name COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map([], |row| project_from_row(row, 0))?;
+        rows.collect()
+    }
+
+    pub fn set_project_status(&self, id: u32, status: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE projects SET status = ?1 WHERE id = ?2",
+            params![status, id],
+        )?;
+        Ok(())
+    }
+
+    /// What each project has used of its budget per month: invoiced
+    /// amounts before tax, plus time and billable expenses not yet billed.
+    pub fn project_usage(&self) -> Result<Vec<(u32, String, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT project_id, month, SUM(amount)
+             FROM (SELECT project_id, substr(date, 1, 7) AS month, total - discount AS amount
+                   FROM invoices
+                   WHERE status NOT IN ('void', 'cancelled')
+                   UNION ALL
+                   SELECT project_id, substr(date, 1, 7), minutes / 60.0 * rate
+                   FROM time_entries
+                   WHERE invoice_id IS NULL
+                   UNION ALL
+                   SELECT project_id, substr(date, 1, 7), amount
+                   FROM expenses
+                   WHERE billable AND invoice_id IS NULL)
+             WHERE project_id IS NOT NULL
+             GROUP BY project_id, month
+             ORDER BY project_id, month",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect()
+    }
+
+    /// Store a manual entry, or start a timer when `started_at` is set.
+    pub fn add_time_entry(&self, entry: &TimeEntry) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO time_entries
+                 (client_id, project, description, date, minutes, rate, started_at, project_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.client.id,
+                entry.project,
+                entry.description,
+                entry.date,
+                entry.minutes,
+                entry.rate,
+                entry.started_at,
+                entry.project_id,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    /// The timer that is running, if any.
+    pub fn running_timer(&self) -> Result<Option<TimeEntry>> {
+        Ok(self
+            .query_time_entries("WHERE t.started_at IS NOT NULL", [])?
+            .into_iter()
+            .next())
+    }
+
+    pub fn stop_timer(&self, id: u32, minutes: u32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE time_entries SET minutes = ?1, started_at = NULL
+             WHERE id = ?2 AND started_at IS NOT NULL",
+            params![minutes, id],
+        )?;
+        Ok(())
+    }
+
+    /// All entries, unbilled first, newest first within each.
+    pub fn get_time_entries(&self) -> Result<Vec<TimeEntry>> {
+        self.query_time_entries(
+            "ORDER BY t.invoice_id IS NOT NULL, t.date DESC, t.id DESC",
+            [],
+        )
+    }
+
+    /// Stopped entries for `client_id` that haven't been billed, oldest first.
+    pub fn get_unbilled_time(&self, client_id: u32) -> Result<Vec<TimeEntry>> {
+        self.query_time_entries(
+            "WHERE t.client_id = ?1 AND t.invoice_id IS NULL AND t.started_at IS NULL
+             ORDER BY t.date, t.id",
+            [client_id.to_string()],
+        )
     }
 
-    pub fn add_invoice(&self, invoice: &Invoice) -> Result<()> {
-        let json_items = serde_json::to_string(&invoice.items).unwrap();
-        self.0.execute(
-            "INSERT INTO invoices (code, client_id, total, currency, discount, tax, status, date, items)
+    /// Delete an entry that hasn't been billed yet.
+    pub fn delete_time_entry(&self, id: u32) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM time_entries WHERE id = ?1 AND invoice_id IS NULL",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Insert `invoice` and mark `entry_ids` as billed on it, or do neither
+    /// if any of the entries was billed in the meantime.
+    pub fn bill_time(&self, invoice: &Invoice, entry_ids: &[u32]) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        let id = insert_invoice(&tx, invoice, None)?;
+        for entry_id in entry_ids {
+            let changed = tx.execute(
+                "UPDATE time_entries SET invoice_id = ?1
+                 WHERE id = ?2 AND invoice_id IS NULL AND started_at IS NULL",
+                params![id, entry_id],
+            )?;
+            if changed != 1 {
+                return Err(rusqlite::Error::StatementChangedRows(changed));
+            }
+        }
+        self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+        self.emit(EventKind::InvoiceCreated, id, None)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn query_time_entries<P: rusqlite::Params>(
+        &self,
+        clause: &str,
+        params: P,
+    ) -> Result<Vec<TimeEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.id, t.project, t.description, t.date, t.minutes, t.rate, t.started_at,
+                    CAST(t.invoice_id AS INTEGER),
+                    CAST(c.id AS INTEGER), c.name, c.phone, c.email, c.address, t.project_id
+             FROM time_entries t
+             JOIN clients c ON t.client_id = c.id
+             {clause}"
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(TimeEntry {
+                id: row.get(0)?,
+                client: client_from_row(row, 8)?,
+                project: row.get(1)?,
+                project_id: row.get(13)?,
+                description: row.get(2)?,
+                date: row.get(3)?,
+                minutes: row.get(4)?,
+                rate: row.get(5)?,
+                started_at: row.get(6)?,
+                invoice_id: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn add_expense(&self, expense: &Expense) -> Result<u32> {
+        self.conn.execute(
+            "INSERT INTO expenses
+                 (date, vendor, amount, currency, category, receipt, client_id, billable,
+                  project_id)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
-                invoice.code,
-                invoice.client.id,
-                invoice.total,
-                invoice.currency,
-                invoice.discount,
-                invoice.tax,
-                invoice.status,
-                invoice.date.to_string(),
-                json_items
+                expense.date,
+                expense.vendor,
+                expense.amount,
+                expense.currency,
+                expense.category,
+                expense.receipt,
+                expense.client.as_ref().map(|client| client.id),
+                expense.billable,
+                expense.project_id,
             ],
         )?;
+        Ok(self.conn.last_insert_rowid() as u32)
+    }
+
+    /// All expenses, those still to be billed first, newest first within each.
+    pub fn get_expenses(&self) -> Result<Vec<Expense>> {
+        self.query_expenses(
+            "ORDER BY NOT (e.billable AND e.invoice_id IS NULL), e.date DESC, e.id DESC",
+            [],
+        )
+    }
+
+    /// Billable expenses for `client_id` not yet passed on, oldest first.
+    pub fn get_billable_expenses(&self, client_id: u32) -> Result<Vec<Expense>> {
+        self.query_expenses(
+            "WHERE e.client_id = ?1 AND e.billable AND e.invoice_id IS NULL
+             ORDER BY e.date, e.id",
+            [client_id.to_string()],
+        )
+    }
+
+    /// Delete an expense that hasn't been billed.
+    pub fn delete_expense(&self, id: u32) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM expenses WHERE id = ?1 AND invoice_id IS NULL",
+            [id],
+        )?;
         Ok(())
     }
 
-    pub fn add_client(&self, client: &Client) -> Result<()> {
-        self.0.execute(
-            "INSERT INTO clients (id, name, phone, email, address)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+    /// The client's most recent draft invoice in `currency` for
+    /// `project_id`, with its items.
+    pub fn latest_draft(
+        &self,
+        client_id: u32,
+        currency: &str,
+        project_id: Option<u32>,
+    ) -> Result<Option<Invoice>> {
+        let id: Option<u32> = self
+            .conn
+            .query_row(
+                "SELECT rowid FROM invoices
+                 WHERE client_id = ?1 AND currency = ?2 AND project_id IS ?3
+                   AND status = 'draft'
+                 ORDER BY date DESC, rowid DESC
+                 LIMIT 1",
+                params![client_id.to_string(), currency, project_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match id {
+            Some(id) => self.get_invoice(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Store `invoice`, new when its id is 0, and mark `expense_ids` as
+    /// billed on it, or do neither if any was billed in the meantime.
+    pub fn bill_expenses(&self, invoice: &Invoice, expense_ids: &[u32]) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        let before = self.snapshot(AuditEntity::Invoice, invoice.id)?;
+        let (id, kind, action) = if invoice.id == 0 {
+            (
+                insert_invoice(&tx, invoice, None)?,
+                EventKind::InvoiceCreated,
+                AuditAction::Create,
+            )
+        } else {
+            update_invoice_row(&tx, invoice)?;
+            (invoice.id, EventKind::InvoiceUpdated, AuditAction::Update)
+        };
+        for expense_id in expense_ids {
+            let changed = tx.execute(
+                "UPDATE expenses SET invoice_id = ?1
+                 WHERE id = ?2 AND billable AND invoice_id IS NULL",
+                params![id, expense_id],
+            )?;
+            if changed != 1 {
+                return Err(rusqlite::Error::StatementChangedRows(changed));
+            }
+        }
+        self.audit(AuditEntity::Invoice, id, action, before)?;
+        self.emit(kind, id, None)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn query_expenses<P: rusqlite::Params>(&self, clause: &str, params: P) -> Result<Vec<Expense>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.id, e.date, e.vendor, e.amount, e.currency, e.category, e.receipt,
+                    e.billable, CAST(e.invoice_id AS INTEGER),
+                    CAST(c.id AS INTEGER), c.name, c.phone, c.email, c.address, e.project_id
+             FROM expenses e
+             LEFT JOIN clients c ON e.client_id = c.id
+             {clause}"
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            let client = match row.get::<_, Option<u32>>(9)? {
+                Some(_) => Some(client_from_row(row, 9)?),
+                None => None,
+            };
+            Ok(Expense {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                vendor: row.get(2)?,
+                amount: row.get(3)?,
+                currency: row.get(4)?,
+                category: row.get(5)?,
+                receipt: row.get(6)?,
+                billable: row.get(7)?,
+                invoice_id: row.get(8)?,
+                project_id: row.get(14)?,
+                client,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Queue an `invoice.overdue` event for each unpaid invoice past its due
+    /// date that hasn't had one yet. Returns how many were queued. Only the
+    /// first call on `today`, from any process, looks for them.
+    pub fn emit_overdue(&self, today: &str) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        // Writing first takes the database's write lock, so a second process
+        // waits here and then finds the day already checked.
+        let first_today = tx.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value
+             WHERE settings.value != excluded.value",
+            params![OVERDUE_SETTING, today],
+        )? > 0;
+        if !first_today {
+            return Ok(0);
+        }
+        let ids = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT invoices.rowid FROM invoices
+                 WHERE invoices.status NOT IN ({EXCLUDED_STATUSES})
+                   AND invoices.due_date != '' AND invoices.due_date < ?1
+                   AND {BALANCE} > 0
+                   AND NOT EXISTS (
+                       SELECT 1 FROM events
+                       WHERE events.invoice_id = invoices.rowid AND events.kind = ?2
+                   )
+                 ORDER BY invoices.rowid"
+            ))?;
+            stmt.query_map(
+                params![today, EventKind::InvoiceOverdue.to_string()],
+                |row| row.get::<_, u32>(0),
+            )?
+            .collect::<Result<Vec<_>>>()?
+        };
+        for &id in &ids {
+            self.emit(EventKind::InvoiceOverdue, id, None)?;
+        }
+        tx.commit()?;
+        Ok(ids.len())
+    }
+
+    /// Events not yet handed to the webhook endpoints, oldest first.
+    pub fn undispatched_events(&self) -> Result<Vec<WebhookEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, kind, CAST(invoice_id AS INTEGER), payload, created_at
+             FROM events
+             WHERE dispatched = 0
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], event_from_row)?;
+        rows.collect()
+    }
+
+    /// Mark the event dispatched and queue a delivery to each of `urls`,
+    /// due at `now`. Does nothing if another process dispatched it first.
+    pub fn queue_deliveries(&self, event_id: u32, urls: &[&str], now: i64) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let dispatched = tx.execute(
+            "UPDATE events SET dispatched = 1 WHERE id = ?1 AND dispatched = 0",
+            [event_id],
+        )?;
+        if dispatched == 0 {
+            return Ok(());
+        }
+        for url in urls {
+            tx.execute(
+                "INSERT INTO webhook_deliveries (event_id, url, next_attempt_at, updated_at)
+                 VALUES (?1, ?2, ?3, datetime('now'))",
+                params![event_id, url, now],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Claim the oldest pending delivery due at `now` for `owner`, so no
+    /// other process attempts it too. The claim pushes its next attempt to
+    /// `lease_until`, after which it is due again should `owner` not record
+    /// an outcome, e.g. because it was stopped.
+    pub fn claim_delivery(
+        &self,
+        now: i64,
+        owner: &str,
+        lease_until: i64,
+    ) -> Result<Option<WebhookDelivery>> {
+        let id: Option<i64> = self
+            .conn
+            .query_row(
+                "UPDATE webhook_deliveries
+                 SET claimed_by = ?2, next_attempt_at = ?3
+                 WHERE id = (
+                     SELECT id FROM webhook_deliveries
+                     WHERE status = 'pending' AND next_attempt_at <= ?1
+                     ORDER BY id
+                     LIMIT 1
+                 )
+                 RETURNING id",
+                params![now, owner, lease_until],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match id {
+            Some(id) => Ok(self.query_deliveries("WHERE d.id = ?1", [id])?.pop()),
+            None => Ok(None),
+        }
+    }
+
+    /// The most recent `limit` deliveries, newest first.
+    pub fn get_deliveries(&self, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        self.query_deliveries("ORDER BY d.id DESC LIMIT ?1", [limit as i64])
+    }
+
+    /// Store the outcome of `owner`'s attempt at `delivery` and release the
+    /// claim, unless the claim lapsed and another process took it over.
+    pub fn update_delivery(&self, delivery: &WebhookDelivery, owner: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE webhook_deliveries
+             SET attempts = ?1, status = ?2, response_code = ?3, error = ?4,
+                 next_attempt_at = ?5, updated_at = datetime('now'), claimed_by = NULL
+             WHERE id = ?6 AND claimed_by = ?7",
+            params![
+                delivery.attempts,
+                delivery.status,
+                delivery.response_code,
+                delivery.error,
+                delivery.next_attempt_at,
+                delivery.id,
+                owner,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn query_deliveries(&self, clause: &str, param: [i64; 1]) -> Result<Vec<WebhookDelivery>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.id, e.kind, CAST(e.invoice_id AS INTEGER), e.payload, e.created_at,
+                    d.id, d.url, d.attempts, d.status, d.response_code, d.error,
+                    d.next_attempt_at, d.updated_at
+             FROM webhook_deliveries d
+             JOIN events e ON d.event_id = e.id
+             {clause}"
+        ))?;
+        let rows = stmt.query_map(param, |row| {
+            Ok(WebhookDelivery {
+                event: event_from_row(row)?,
+                id: row.get(5)?,
+                url: row.get(6)?,
+                attempts: row.get(7)?,
+                status: row.get(8)?,
+                response_code: row.get(9)?,
+                error: row.get(10)?,
+                next_attempt_at: row.get(11)?,
+                updated_at: row.get(12)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Queue `kind` for the webhooks with the invoice as it is now, plus an
+    /// extra `data` field. Called inside the transaction making the change.
+    fn emit(
+        &self,
+        kind: EventKind,
+        invoice_id: u32,
+        extra: Option<(&str, serde_json::Value)>,
+    ) -> Result<()> {
+        let Some(invoice) = self.get_invoice(invoice_id)? else {
+            return Ok(());
+        };
+        let mut data = serde_json::json!({ "invoice": invoice });
+        if let Some((key, value)) = extra {
+            data[key] = value;
+        }
+        self.conn.execute(
+            "INSERT INTO events (kind, invoice_id, payload, created_at)
+             VALUES (?1, ?2, ?3, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))",
+            params![kind.to_string(), invoice_id, data.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// An invoice, client or payment as JSON, as the audit log records it.
+    fn snapshot(&self, entity: AuditEntity, id: u32) -> Result<Option<serde_json::Value>> {
+        let to_json = |value: serde_json::Result<serde_json::Value>| {
+            value.map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+        };
+        Ok(match entity {
+            AuditEntity::Invoice => match self.get_invoice(id)? {
+                Some(invoice) => Some(to_json(serde_json::to_value(invoice))?),
+                None => None,
+            },
+            AuditEntity::Client => match self.get_client(id)? {
+                Some(client) => Some(to_json(serde_json::to_value(client))?),
+                None => None,
+            },
+            AuditEntity::Payment => match self.get_payment(id)? {
+                Some(payment) => Some(to_json(serde_json::to_value(payment))?),
+                None => None,
+            },
+        })
+    }
+
+    /// Append what changed about `entity` `id` since the `before` snapshot
+    /// to the audit log, chained to the last entry. Called inside the
+    /// transaction making the change; changes that change nothing are
+    /// skipped.
+    fn audit(
+        &self,
+        entity: AuditEntity,
+        id: u32,
+        action: AuditAction,
+        before: Option<serde_json::Value>,
+    ) -> Result<()> {
+        let after = match action {
+            AuditAction::Delete => None,
+            _ => self.snapshot(entity, id)?,
+        };
+        let changes = audit::diff(before.as_ref(), after.as_ref());
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let invoice_id = match entity {
+            AuditEntity::Invoice => Some(id),
+            AuditEntity::Client => None,
+            AuditEntity::Payment => after
+                .as_ref()
+                .or(before.as_ref())
+                .and_then(|payment| payment["invoice_id"].as_u64())
+                .map(|invoice_id| invoice_id as u32),
+        };
+        let (last_id, prev_hash): (u32, String) = self
+            .conn
+            .query_row(
+                "SELECT id, hash FROM audit_log ORDER BY id DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .unwrap_or_default();
+        let mut entry = AuditEntry {
+            id: last_id + 1,
+            at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            actor: self.actor.clone(),
+            entity: entity.to_string(),
+            entity_id: id,
+            invoice_id,
+            action: action.to_string(),
+            changes: serde_json::Value::Object(changes).to_string(),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = audit::hash(&entry);
+        self.conn.execute(
+            "INSERT INTO audit_log
+                 (id, at, actor, entity, entity_id, invoice_id, action, changes, prev_hash, hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
-                client.id,
+                entry.id,
+                entry.at,
+                entry.actor,
+                entry.entity,
+                entry.entity_id,
+                entry.invoice_id,
+                entry.action,
+                entry.changes,
+                entry.prev_hash,
+                entry.hash,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The audit log, oldest first: everything, or the changes to one
+    /// invoice and its payments.
+    pub fn get_audit_log(&self, invoice_id: Option<u32>) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, at, actor, entity, entity_id, invoice_id, action, changes, prev_hash, hash
+             FROM audit_log
+             WHERE ?1 IS NULL OR invoice_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([invoice_id], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                at: row.get(1)?,
+                actor: row.get(2)?,
+                entity: row.get(3)?,
+                entity_id: row.get(4)?,
+                invoice_id: row.get(5)?,
+                action: row.get(6)?,
+                changes: row.get(7)?,
+                prev_hash: row.get(8)?,
+                hash: row.get(9)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Insert `client` with a fresh id and return the id.
+    pub fn add_client(&self, client: &Client) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        let id = next_client_id(&tx)?;
+        insert_client(
+            &tx,
+            &Client {
+                id,
+                ..client.clone()
+            },
+        )?;
+        self.audit(AuditEntity::Client, id, AuditAction::Create, None)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    /// Overwrite the client with `client.id`. Issued invoices keep the
+    /// client details they were issued with.
+    pub fn update_client(&self, client: &Client) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let before = self.snapshot(AuditEntity::Client, client.id)?;
+        tx.execute(
+            "UPDATE clients
+             SET name = ?2, phone = ?3, email = ?4, address = ?5, country = ?6, vat_id = ?7,
+                 endpoint = ?8, reference = ?9
+             WHERE id = ?1",
+            params![
+                client.id.to_string(),
                 client.name,
                 client.phone,
                 client.email,
                 client.address,
+                client.country,
+                client.vat_id,
+                client.endpoint,
+                client.reference,
             ],
         )?;
-        Ok(())
+        self.audit(AuditEntity::Client, client.id, AuditAction::Update, before)?;
+        tx.commit()
     }
 
-    pub fn get_all_invoices(&self) -> Result<Vec<Invoice>> {
-        let mut stmt = self.0
-            .prepare("
-                SELECT id, code, total, currency, discount, tax, status, date, items, client_id, c.name AS client_name, c.phone AS client_phone, c.email AS client_email, c.address AS client_address
-                FROM invoices
-                JOIN clients c ON invoices.client_id = c.id GROUP BY invoices.id")?;
-        let rows = stmt.query_map([], |row| {
-            let items_json: String = row.get(8)?;
-            let items: Vec<Item> = serde_json::from_str(&items_json).unwrap();
+    pub fn get_client(&self, id: u32) -> Result<Option<Client>> {
+        self.conn
+            .query_row(
+                "SELECT CAST(id AS INTEGER), name, phone, email, address, country, vat_id, endpoint,
+                        reference
+                 FROM clients
+                 WHERE id = ?1",
+                [id.to_string()],
+                |row| client_from_row(row, 0),
+            )
+            .optional()
+    }
 
-            Ok(Invoice {
-                id: row.get(0)?,
-                code: row.get(1)?,
-                total: row.get(2)?,
-                currency: row.get(3)?,
-                discount: row.get(4)?,
-                tax: row.get(5)?,
-                status: row.get(6)?,
-                date: row.get(7)?,
-                items,
-                client: Client {
-                    id: row.get(9)?,
-                    name: row.get(10)?,
-                    phone: row.get(11)?,
-                    email: row.get(12)?,
-                    address: row.get(13)?,
+    /// Insert all of `clients` with fresh ids in one transaction, or none if
+    /// any fails.
+    pub fn import_clients(&self, clients: &[Client]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, client) in (next_client_id(&tx)?..).zip(clients) {
+            insert_client(
+                &tx,
+                &Client {
+                    id,
+                    ..client.clone()
                 },
+            )?;
+            self.audit(AuditEntity::Client, id, AuditAction::Create, None)?;
+        }
+        tx.commit()?;
+        Ok(clients.len())
+    }
+
+    pub fn get_invoice_codes(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT code FROM invoices")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Fetch one page of invoices without their items, ordered by `sort`
+    /// and starting right after `after`.
+    pub fn list_invoices(
+        &self,
+        filter: &InvoiceFilter,
+        sort: InvoiceSort,
+        limit: usize,
+        after: Option<&InvoiceCursor>,
+    ) -> Result<Vec<Invoice>> {
+        let mut values = Vec::new();
+        let mut sql = format!(
+            "{INVOICE_SELECT} WHERE {}",
+            filter_clause(filter, &mut values)
+        );
+
+        let expr = sort_expr(sort.column);
+        let (op, dir) = match sort.direction {
+            SortDirection::Asc => (">", "ASC"),
+            SortDirection::Desc => ("<", "DESC"),
+        };
+        if let Some(cursor) = after {
+            sql.push_str(&format!(" AND ({expr}, invoices.rowid) {op} (?, ?)"));
+            values.push(cursor.key.clone());
+            values.push(Value::Integer(cursor.id.into()));
+        }
+        sql.push_str(&format!(
+            " ORDER BY {expr} {dir}, invoices.rowid {dir} LIMIT ?"
+        ));
+        values.push(Value::Integer(limit as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), invoice_from_row)?;
+        rows.collect()
+    }
+
+    pub fn count_invoices(&self, filter: &InvoiceFilter) -> Result<usize> {
+        let mut values = Vec::new();
+        let sql = format!(
+            "SELECT COUNT(*) FROM invoices JOIN clients c ON invoices.client_id = c.id WHERE {}",
+            filter_clause(filter, &mut values)
+        );
+        self.conn
+            .query_row(&sql, params_from_iter(values), |row| row.get(0))
+    }
+
+    /// Load a single invoice including its items.
+    pub fn get_invoice(&self, id: u32) -> Result<Option<Invoice>> {
+        let invoice = self
+            .conn
+            .query_row(
+                &format!("{INVOICE_SELECT} WHERE invoices.rowid = ?1"),
+                [id],
+                invoice_from_row,
+            )
+            .optional()?;
+        let Some(mut invoice) = invoice else {
+            return Ok(None);
+        };
+        invoice.items = self.get_items(id)?;
+        Ok(Some(invoice))
+    }
+
+    pub fn get_items(&self, invoice_id: u32) -> Result<Vec<Item>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, title, price, quantity, tax_rate, remark
+             FROM invoice_items
+             WHERE invoice_id = ?1
+             ORDER BY position",
+        )?;
+        let rows = stmt.query_map([invoice_id], |row| {
+            Ok(Item {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                price: row.get(2)?,
+                quantity: row.get(3)?,
+                tax_rate: row.get(4)?,
+                remark: row.get(5)?,
             })
         })?;
+        rows.collect()
+    }
 
-        Ok(rows.filter_map(Result::ok).collect())
+    pub fn get_invoice_by_code(&self, code: &str) -> Result<Option<Invoice>> {
+        let id = self
+            .conn
+            .query_row(
+                "SELECT rowid FROM invoices WHERE code = ?1",
+                [code],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match id {
+            Some(id) => self.get_invoice(id),
+            None => Ok(None),
+        }
     }
 
     pub fn get_all_clients(&self) -> Result<Vec<Client>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST(id AS INTEGER), name, phone, email, address, country, vat_id, endpoint,
+                    reference
+             FROM clients",
+        )?;
+        let rows = stmt.query_map([], |row| client_from_row(row, 0))?;
+
+        Ok(rows.filter_map(Result::ok).collect())
+    }
+
+    /// Fetch one page of clients ordered by id, starting right after `after`.
+    pub fn list_clients(&self, limit: usize, after: Option<u32>) -> Result<Vec<Client>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT CAST(id AS INTEGER), name, phone, email, address, country, vat_id, endpoint,
+                    reference
+             FROM clients
+             WHERE CAST(id AS INTEGER) > ?1
+             ORDER BY CAST(id AS INTEGER)
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![after.unwrap_or(0), limit as i64], |row| {
+            client_from_row(row, 0)
+        })?;
+        rows.collect()
+    }
+
+    pub fn dashboard(&self, months: &[String], today: &str) -> Result<DashboardStats> {
+        let mut stats = DashboardStats {
+            months: months
+                .iter()
+                .map(|month| MonthlyTotals {
+                    month: month.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let Some(first) = months.first() else {
+            return Ok(stats);
+        };
+        let monthly = |sql: &str| -> Result<Vec<(String, f64)>> {
+            let mut stmt = self.conn.prepare(sql)?;
+            let rows = stmt.query_map([first], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        };
+        let invoiced = monthly(&format!(
+            "SELECT substr(date, 1, 7) AS month, SUM({NET_TOTAL})
+             FROM invoices
+             WHERE status NOT IN ({EXCLUDED_STATUSES}) AND substr(date, 1, 7) >= ?1
+             GROUP BY month"
+        ))?;
+        let collected = monthly(
+            "SELECT substr(date, 1, 7) AS month, SUM(amount)
+             FROM payments
+             WHERE substr(date, 1, 7) >= ?1
+             GROUP BY month",
+        )?;
+        for totals in &mut stats.months {
+            for (month, amount) in &invoiced {
+                if *month == totals.month {
+                    totals.invoiced = *amount;
+                }
+            }
+            for (month, amount) in &collected {
+                if *month == totals.month {
+                    totals.collected = *amount;
+                }
+            }
+        }
+
+        (stats.receivables, stats.overdue, stats.overdue_count) = self.conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM({BALANCE}), 0),
+                        COALESCE(SUM(CASE WHEN due_date != '' AND due_date < ?1
+                                     THEN {BALANCE} ELSE 0 END), 0),
+                        COUNT(CASE WHEN due_date != '' AND due_date < ?1 THEN 1 END)
+                 FROM invoices
+                 WHERE status NOT IN ({EXCLUDED_STATUSES}) AND {BALANCE} > 0"
+            ),
+            [today],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.name, SUM({NET_TOTAL}) AS revenue
+             FROM invoices
+             JOIN clients c ON invoices.client_id = c.id
+             WHERE invoices.status NOT IN ({EXCLUDED_STATUSES})
+             GROUP BY c.id
+             ORDER BY revenue DESC
+             LIMIT 5"
+        ))?;
+        stats.top_clients = stmt
+            .query_map([], |row| {
+                Ok(ClientRevenue {
+                    name: row.get(0)?,
+                    revenue: row.get(1)?,
+                })
+            })?
+            .collect::<Result<_>>()?;
+
+        // An invoice counts as paid on the date of its last payment.
+        stats.avg_days_to_pay = self.conn.query_row(
+            "SELECT AVG(julianday(p.paid_on) - julianday(substr(i.date, 1, 10)))
+             FROM (SELECT invoice_id, MAX(date) AS paid_on FROM payments GROUP BY invoice_id) p
+             JOIN invoices i ON i.id = p.invoice_id
+             WHERE i.status = 'paid'",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(stats)
+    }
+
+    /// Raw rows for a report over the dates `from` to `to` (inclusive), in
+    /// the column order of `ReportKind::columns`.
+    pub fn report(&self, kind: ReportKind, from: &str, to: &str) -> Result<Vec<Vec<Value>>> {
+        let in_range = "substr(invoices.date, 1, 10) BETWEEN ?1 AND ?2";
+        let sql = match kind {
+            ReportKind::SalesByPeriod => format!(
+                "SELECT substr(date, 1, 7) AS month, COUNT(*), SUM(total - discount), SUM(tax),
+                        SUM({NET_TOTAL})
+                 FROM invoices
+                 WHERE status NOT IN ({EXCLUDED_STATUSES}) AND {in_range}
+                 GROUP BY month
+                 ORDER BY month"
+            ),
+            ReportKind::SalesByClient => format!(
+                "SELECT c.name, COUNT(*), SUM(invoices.total - invoices.discount),
+                        SUM(invoices.tax), SUM({NET_TOTAL}) AS revenue, SUM(invoices.paid)
+                 FROM invoices
+                 JOIN clients c ON invoices.client_id = c.id
+                 WHERE invoices.status NOT IN ({EXCLUDED_STATUSES}) AND {in_range}
+                 GROUP BY c.id
+                 ORDER BY revenue DESC"
+            ),
+            ReportKind::SalesByItem => format!(
+                "SELECT it.title, SUM(COALESCE(it.quantity, 0)),
+                        SUM(COALESCE(it.quantity, 0) * COALESCE(it.price, 0)) AS amount
+                 FROM invoice_items it
+                 JOIN invoices ON invoices.id = it.invoice_id
+                 WHERE invoices.status NOT IN ({EXCLUDED_STATUSES}) AND {in_range}
+                 GROUP BY it.title
+                 ORDER BY amount DESC"
+            ),
+            // Invoices carry a single tax amount, so the rate is derived from it.
+            ReportKind::TaxByRate => format!(
+                "SELECT printf('%.2f%%', rate), COUNT(*), SUM(taxable), SUM(tax)
+                 FROM (SELECT total - discount AS taxable, tax,
+                              CASE WHEN total - discount > 0
+                                   THEN round(tax * 100.0 / (total - discount), 2)
+                                   ELSE 0 END AS rate
+                       FROM invoices
+                       WHERE status NOT IN ({EXCLUDED_STATUSES}) AND {in_range})
+                 GROUP BY rate
+                 ORDER BY rate"
+            ),
+            // Open balances of invoices issued up to `to`, aged against their
+            // due date (or issue date when there is none) as of `to`.
+            ReportKind::ReceivablesAging => format!(
+                "SELECT c.name,
+                        SUM(CASE WHEN days <= 0 THEN balance ELSE 0.0 END),
+                        SUM(CASE WHEN days > 0 AND days <= 30 THEN balance ELSE 0.0 END),
+                        SUM(CASE WHEN days > 30 AND days <= 60 THEN balance ELSE 0.0 END),
+                        SUM(CASE WHEN days > 60 AND days <= 90 THEN balance ELSE 0.0 END),
+                        SUM(CASE WHEN days > 90 THEN balance ELSE 0.0 END),
+                        SUM(balance) AS outstanding
+                 FROM (SELECT client_id, {BALANCE} AS balance,
+                              julianday(?2) - julianday(substr(
+                                  CASE WHEN due_date != '' THEN due_date ELSE date END, 1, 10
+                              )) AS days
+                       FROM invoices
+                       WHERE status NOT IN ({EXCLUDED_STATUSES})
+                             AND {BALANCE} > 0
+                             AND substr(date, 1, 10) <= ?2) i
+                 JOIN clients c ON i.client_id = c.id
+                 GROUP BY c.id
+                 ORDER BY outstanding DESC"
+            ),
+            ReportKind::PaymentsJournal => {
+                "SELECT p.date, invoices.code, c.name, p.reference, p.amount
+                 FROM payments p
+                 JOIN invoices ON invoices.id = p.invoice_id
+                 JOIN clients c ON invoices.client_id = c.id
+                 WHERE substr(p.date, 1, 10) BETWEEN ?1 AND ?2
+                 ORDER BY p.date, p.id"
+                    .to_string()
+            }
+            // Revenue before tax against all expenses, re-billed ones included,
+            // so passed-on costs only count with their markup.
+            ReportKind::ProfitByPeriod => format!(
+                "SELECT month, SUM(revenue), SUM(spent), SUM(revenue) - SUM(spent)
+                 FROM (SELECT substr(date, 1, 7) AS month, total - discount AS revenue,
+                              0.0 AS spent
+                       FROM invoices
+                       WHERE status NOT IN ({EXCLUDED_STATUSES}) AND {in_range}
+                       UNION ALL
+                       SELECT substr(date, 1, 7), 0.0, amount
+                       FROM expenses
+                       WHERE substr(date, 1, 10) BETWEEN ?1 AND ?2)
+                 GROUP BY month
+                 ORDER BY month"
+            ),
+        };
+
+        let columns = kind.columns().len();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map([from, to], |row| {
+            (0..columns)
+                .map(|index| row.get::<_, Value>(index))
+                .collect()
+        })?;
+        rows.collect()
+    }
+
+    /// Every invoice with its items, in id order.
+    pub fn get_all_invoices(&self) -> Result<Vec<Invoice>> {
         let mut stmt = self
-            .0
-            .prepare("SELECT id, name, phone, email, address FROM clients")?;
+            .conn
+            .prepare(&format!("{INVOICE_SELECT} ORDER BY invoices.rowid"))?;
+        let mut invoices = stmt
+            .query_map([], invoice_from_row)?
+            .collect::<Result<Vec<Invoice>>>()?;
+        for invoice in &mut invoices {
+            invoice.items = self.get_items(invoice.id)?;
+        }
+        Ok(invoices)
+    }
+
+    /// Invoices past draft and not voided or cancelled, dated from `from` to
+    /// `to`, with their items.
+    pub fn get_booked_invoices(&self, from: &str, to: &str) -> Result<Vec<Invoice>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{INVOICE_SELECT}
+             WHERE invoices.status NOT IN ({EXCLUDED_STATUSES})
+               AND substr(invoices.date, 1, 10) BETWEEN ?1 AND ?2
+             ORDER BY invoices.rowid"
+        ))?;
+        let mut invoices = stmt
+            .query_map([from, to], invoice_from_row)?
+            .collect::<Result<Vec<Invoice>>>()?;
+        for invoice in &mut invoices {
+            invoice.items = self.get_items(invoice.id)?;
+        }
+        Ok(invoices)
+    }
+
+    /// Payments received from `from` to `to`.
+    pub fn get_payments_between(&self, from: &str, to: &str) -> Result<Vec<Payment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(invoice_id AS INTEGER), amount, date, reference
+             FROM payments
+             WHERE substr(date, 1, 10) BETWEEN ?1 AND ?2
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([from, to], |row| {
+            Ok(Payment {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                amount: row.get(2)?,
+                date: row.get(3)?,
+                reference: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_payments(&self) -> Result<Vec<Payment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(invoice_id AS INTEGER), amount, date, reference
+             FROM payments
+             ORDER BY id",
+        )?;
         let rows = stmt.query_map([], |row| {
-            Ok(Client {
+            Ok(Payment {
                 id: row.get(0)?,
-                name: row.get(1)?,
-                phone: row.get(2)?,
-                email: row.get(3)?,
-                address: row.get(4)?,
+                invoice_id: row.get(1)?,
+                amount: row.get(2)?,
+                date: row.get(3)?,
+                reference: row.get(4)?,
             })
         })?;
+        rows.collect()
+    }
 
-        Ok(rows.filter_map(Result::ok).collect())
+    fn get_payment(&self, id: u32) -> Result<Option<Payment>> {
+        self.conn
+            .query_row(
+                "SELECT id, CAST(invoice_id AS INTEGER), amount, date, reference
+                 FROM payments
+                 WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok(Payment {
+                        id: row.get(0)?,
+                        invoice_id: row.get(1)?,
+                        amount: row.get(2)?,
+                        date: row.get(3)?,
+                        reference: row.get(4)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Payments recorded against one invoice, oldest first.
+    pub fn get_invoice_payments(&self, invoice_id: u32) -> Result<Vec<Payment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, CAST(invoice_id AS INTEGER), amount, date, reference
+             FROM payments
+             WHERE invoice_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([invoice_id], |row| {
+            Ok(Payment {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                amount: row.get(2)?,
+                date: row.get(3)?,
+                reference: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_settings(&self) -> Result<BTreeMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT key, value FROM settings")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Highest id used in each table.
+    pub fn get_sequences(&self) -> Result<BTreeMap<String, i64>> {
+        let mut sequences = BTreeMap::new();
+        for (table, id) in [
+            ("clients", "CAST(id AS INTEGER)"),
+            ("invoices", "rowid"),
+            ("invoice_items", "id"),
+            ("payments", "id"),
+        ] {
+            let max: i64 = self.conn.query_row(
+                &format!("SELECT COALESCE(MAX({id}), 0) FROM {table}"),
+                [],
+                |row| row.get(0),
+            )?;
+            sequences.insert(table.to_string(), max);
+        }
+        Ok(sequences)
+    }
+
+    /// Every row of the tables in `BACKUP_TABLES`, of projects and of the
+    /// audit log, column by column.
+    pub fn get_backup_tables(&self) -> Result<BTreeMap<String, Vec<backup::Row>>> {
+        let mut tables = BTreeMap::new();
+        for table in BACKUP_TABLES
+            .iter()
+            .map(|(table, _)| *table)
+            .chain(["projects", "audit_log"])
+        {
+            let mut stmt = self
+                .conn
+                .prepare(&format!("SELECT * FROM {table} ORDER BY id"))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let rows = stmt
+                .query_map([], |row| {
+                    columns
+                        .iter()
+                        .enumerate()
+                        .map(|(index, column)| {
+                            Ok((column.clone(), json_value(row.get_ref(index)?)))
+                        })
+                        .collect()
+                })?
+                .collect::<Result<_>>()?;
+            tables.insert(table.to_string(), rows);
+        }
+        Ok(tables)
+    }
+
+    /// Tables with rows that replacing the data with `backup` would lose,
+    /// because the backup was written before it carried them.
+    pub fn unsaved_tables(&self, backup: &Backup) -> Result<Vec<&'static str>> {
+        let mut unsaved = Vec::new();
+        for table in BACKUP_TABLES
+            .iter()
+            .map(|(table, _)| *table)
+            .chain(["projects"])
+            .filter(|table| !backup.tables.contains_key(*table))
+        {
+            let has_rows: bool = self.conn.query_row(
+                &format!("SELECT EXISTS (SELECT 1 FROM {table})"),
+                [],
+                |row| row.get(0),
+            )?;
+            if has_rows {
+                unsaved.push(table);
+            }
+        }
+        Ok(unsaved)
+    }
+
+    /// Copy the backup's audit log when this database has none yet; an
+    /// existing log can't be replaced. Returns whether it was copied.
+    fn restore_audit_log(&self, backup: &Backup) -> Result<bool> {
+        let empty: bool =
+            self.conn
+                .query_row("SELECT NOT EXISTS (SELECT 1 FROM audit_log)", [], |row| {
+                    row.get(0)
+                })?;
+        let Some(rows) = backup.tables.get("audit_log").filter(|_| empty) else {
+            return Ok(false);
+        };
+        for row in rows {
+            insert_row(&self.conn, "audit_log", row, true)?;
+        }
+        Ok(!rows.is_empty())
+    }
+
+    /// Write a consistent copy of the database to `path`.
+    pub fn copy_to(&self, path: &Path) -> Result<()> {
+        copy_to(&self.conn, path)
+    }
+
+    /// Replace all data with the contents of `backup`, keeping its ids.
+    pub fn restore_replace(&self, backup: &Backup) -> Result<RestoreReport> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.restore_audit_log(backup)?;
+        // The audit log outlives the data, so record what is thrown away.
+        let mut removed = Vec::new();
+        for (entity, sql) in [
+            (AuditEntity::Payment, "SELECT id FROM payments"),
+            (AuditEntity::Invoice, "SELECT rowid FROM invoices"),
+            (
+                AuditEntity::Client,
+                "SELECT CAST(id AS INTEGER) FROM clients",
+            ),
+        ] {
+            let ids: Vec<u32> = tx
+                .prepare(sql)?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_>>()?;
+            for id in ids {
+                removed.push((entity, id, self.snapshot(entity, id)?));
+            }
+        }
+        for (table, _) in BACKUP_TABLES.iter().rev() {
+            tx.execute(&format!("DELETE FROM {table}"), [])?;
+        }
+        tx.execute_batch(
+            "DELETE FROM payments;
+             DELETE FROM invoice_items;
+             DELETE FROM invoices;
+             DELETE FROM projects;
+             DELETE FROM clients;
+             DELETE FROM settings;",
+        )?;
+        for (entity, id, before) in removed {
+            self.audit(entity, id, AuditAction::Delete, before)?;
+        }
+        for client in &backup.clients {
+            insert_client(&tx, client)?;
+            self.audit(AuditEntity::Client, client.id, AuditAction::Create, None)?;
+        }
+        let mut rows = 0;
+        for row in backup.tables.get("projects").into_iter().flatten() {
+            insert_row(&tx, "projects", row, true)?;
+            rows += 1;
+        }
+        for invoice in &backup.invoices {
+            let id = insert_invoice(&tx, invoice, Some(invoice.id))?;
+            self.issue(id)?;
+            self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+        }
+        for payment in &backup.payments {
+            let id = insert_payment(&tx, payment, Some(payment.id))?;
+            self.audit(AuditEntity::Payment, id, AuditAction::Create, None)?;
+        }
+        for (table, _) in BACKUP_TABLES {
+            for row in backup.tables.get(*table).into_iter().flatten() {
+                insert_row(&tx, table, row, true)?;
+                rows += 1;
+            }
+        }
+        for (key, value) in &backup.settings {
+            tx.execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?;
+        }
+        tx.commit()?;
+        Ok(RestoreReport {
+            clients: backup.clients.len(),
+            invoices: backup.invoices.len(),
+            payments: backup.payments.len(),
+            rows,
+            settings: backup.settings.len(),
+            conflicts: Vec::new(),
+        })
+    }
+
+    /// Add the records of `backup` that aren't stored yet. Clients are
+    /// matched by id and invoices by code; a match whose contents differ is
+    /// reported as a conflict and the stored record is kept. A backup client
+    /// whose id belongs to a different stored client is added under a new id,
+    /// and its projects, invoices and other rows follow it.
+    pub fn restore_merge(&self, backup: &Backup) -> Result<RestoreReport> {
+        let mut report = RestoreReport::default();
+        let tx = self.conn.unchecked_transaction()?;
+        // The backup's log only counts as missing when its last entry isn't
+        // part of the stored one.
+        let last_hash = backup
+            .tables
+            .get("audit_log")
+            .and_then(|rows| rows.last())
+            .and_then(|row| row.get("hash"))
+            .and_then(serde_json::Value::as_str);
+        if !self.restore_audit_log(backup)?
+            && let Some(hash) = last_hash
+            && !tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM audit_log WHERE hash = ?1)",
+                [hash],
+                |row| row.get::<_, bool>(0),
+            )?
+        {
+            report
+                .conflicts
+                .push("audit log: the stored log is kept, the backup's isn't added".to_string());
+        }
+
+        let existing: HashMap<u32, Client> = self
+            .get_all_clients()?
+            .into_iter()
+            .map(|client| (client.id, client))
+            .collect();
+        // Clients keep their ids when they are free. The ones whose id is
+        // taken get a new id once all free ids are used, so the new ids
+        // can't clash with a later client of the backup.
+        let mut ids: HashMap<&str, HashMap<i64, i64>> = HashMap::new();
+        let mut taken = Vec::new();
+        for client in &backup.clients {
+            match existing.get(&client.id) {
+                None => {
+                    insert_client(&tx, client)?;
+                    self.audit(AuditEntity::Client, client.id, AuditAction::Create, None)?;
+                    report.clients += 1;
+                }
+                Some(stored) if stored != client => {
+                    taken.push((client, stored));
+                    continue;
+                }
+                Some(_) => {}
+            }
+            ids.entry("clients")
+                .or_default()
+                .insert(client.id.into(), client.id.into());
+        }
+        for (client, stored) in taken {
+            // An earlier merge of the same backup already added it.
+            let added = existing.values().find(|other| {
+                **other
+                    == Client {
+                        id: other.id,
+                        ..client.clone()
+                    }
+            });
+            let id = match added {
+                Some(added) => added.id,
+                None => {
+                    let id = next_client_id(&tx)?;
+                    insert_client(
+                        &tx,
+                        &Client {
+                            id,
+                            ..client.clone()
+                        },
+                    )?;
+                    self.audit(AuditEntity::Client, id, AuditAction::Create, None)?;
+                    report.clients += 1;
+                    report.conflicts.push(format!(
+                        "client {}: {} <{}> differs from the stored {} <{}>, added as client {}",
+                        client.id, client.name, client.email, stored.name, stored.email, id
+                    ));
+                    id
+                }
+            };
+            ids.entry("clients")
+                .or_default()
+                .insert(client.id.into(), id.into());
+        }
+
+        // Projects are matched by client and name.
+        for row in backup.tables.get("projects").into_iter().flatten() {
+            let old_id = row.get("id").and_then(serde_json::Value::as_i64);
+            let mut row = row.clone();
+            row.remove("id");
+            if let Some(value) = row.get_mut("client_id")
+                && let Some(&id) = id_of(value).and_then(|id| ids.get("clients")?.get(&id))
+            {
+                *value = remap(value, id);
+            }
+            let client_id = row
+                .get("client_id")
+                .and_then(id_of)
+                .map(|id| id.to_string());
+            let name = row
+                .get("name")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let stored: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM projects WHERE client_id = ?1 AND name = ?2",
+                    params![client_id, name],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let id = match stored {
+                Some(id) => {
+                    if find_row(&tx, "projects", &row)?.is_none() {
+                        report
+                            .conflicts
+                            .push(format!("project {}: differs from the stored project", name));
+                    }
+                    id
+                }
+                None => {
+                    report.rows += 1;
+                    insert_row(&tx, "projects", &row, false)?
+                }
+            };
+            if let Some(old_id) = old_id {
+                ids.entry("projects").or_default().insert(old_id, id);
+            }
+        }
+
+        // Invoices keep their id when it is free; payments follow them.
+        // Other rows follow invoices that were already stored too.
+        let mut new_ids = HashMap::new();
+        for invoice in &backup.invoices {
+            let mut invoice = invoice.clone();
+            if let Some(&id) = ids
+                .get("clients")
+                .and_then(|ids| ids.get(&invoice.client.id.into()))
+            {
+                invoice.client.id = id as u32;
+            }
+            let stored: Option<u32> = tx
+                .query_row(
+                    "SELECT rowid FROM invoices WHERE code = ?1",
+                    [&invoice.code],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(id) = stored {
+                ids.entry("invoices")
+                    .or_default()
+                    .insert(invoice.id.into(), id.into());
+                let stored = self.get_invoice(id)?;
+                if stored.is_some_and(|stored| !same_invoice(&stored, &invoice)) {
+                    report.conflicts.push(format!(
+                        "invoice {}: differs from the stored invoice",
+                        invoice.code
+                    ));
+                }
+                continue;
+            }
+            let id_taken: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM invoices WHERE rowid = ?1)",
+                [invoice.id],
+                |row| row.get(0),
+            )?;
+            let rowid = if id_taken { None } else { Some(invoice.id) };
+            if let Some(project) = invoice.project.as_mut()
+                && let Some(&id) = ids
+                    .get("projects")
+                    .and_then(|ids| ids.get(&project.id.into()))
+            {
+                project.id = id as u32;
+            }
+            let id = insert_invoice(&tx, &invoice, rowid)?;
+            self.issue(id)?;
+            self.audit(AuditEntity::Invoice, id, AuditAction::Create, None)?;
+            new_ids.insert(invoice.id, id);
+            ids.entry("invoices")
+                .or_default()
+                .insert(invoice.id.into(), id.into());
+            report.invoices += 1;
+        }
+        for payment in &backup.payments {
+            if let Some(&invoice_id) = new_ids.get(&payment.invoice_id) {
+                let payment = Payment {
+                    invoice_id,
+                    ..payment.clone()
+                };
+                let id = insert_payment(&tx, &payment, None)?;
+                self.audit(AuditEntity::Payment, id, AuditAction::Create, None)?;
+                report.payments += 1;
+            }
+        }
+
+        for (table, refs) in BACKUP_TABLES {
+            for row in backup.tables.get(*table).into_iter().flatten() {
+                let old_id = row.get("id").and_then(serde_json::Value::as_i64);
+                let mut row = row.clone();
+                row.remove("id");
+                let mut missing = None;
+                for (column, parent) in *refs {
+                    let Some(value) = row.get_mut(*column).filter(|value| !value.is_null()) else {
+                        continue;
+                    };
+                    match id_of(value).and_then(|id| ids.get(parent)?.get(&id)) {
+                        Some(&id) => *value = remap(value, id),
+                        None => missing = Some((column, value.clone())),
+                    }
+                }
+                if let Some((column, value)) = missing {
+                    report.conflicts.push(format!(
+                        "{} {}: {} {} is neither in the backup nor stored",
+                        table,
+                        old_id.unwrap_or_default(),
+                        column,
+                        value
+                    ));
+                    continue;
+                }
+                let id = match find_row(&tx, table, &row)? {
+                    Some(id) => id,
+                    None => match insert_row(&tx, table, &row, false) {
+                        Ok(id) => {
+                            report.rows += 1;
+                            id
+                        }
+                        Err(rusqlite::Error::SqliteFailure(error, _))
+                            if error.code == rusqlite::ErrorCode::ConstraintViolation =>
+                        {
+                            report.conflicts.push(format!(
+                                "{} {}: clashes with a stored row",
+                                table,
+                                old_id.unwrap_or_default()
+                            ));
+                            continue;
+                        }
+                        Err(e) => return Err(e),
+                    },
+                };
+                if let Some(old_id) = old_id {
+                    ids.entry(table).or_default().insert(old_id, id);
+                }
+            }
+        }
+
+        for (key, value) in &backup.settings {
+            match self.get_setting(key)? {
+                None => {
+                    tx.execute(
+                        "INSERT INTO settings (key, value) VALUES (?1, ?2)",
+                        params![key, value],
+                    )?;
+                    report.settings += 1;
+                }
+                Some(stored) if stored != *value => report.conflicts.push(format!(
+                    "setting {}: '{}' differs from the stored '{}'",
+                    key, value, stored
+                )),
+                Some(_) => {}
+            }
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+}
+
+/// Columns of `table`, for checking the keys of a backup row against.
+fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    conn.prepare(&format!("SELECT name FROM pragma_table_info('{table}')"))?
+        .query_map([], |row| row.get(0))?
+        .collect()
+}
+
+/// Insert a backup row into `table` and return its id. Without `keep_id`
+/// the row gets a fresh one. Keys that aren't columns are ignored.
+fn insert_row(conn: &Connection, table: &str, row: &backup::Row, keep_id: bool) -> Result<i64> {
+    let columns = table_columns(conn, table)?;
+    let (names, values): (Vec<&str>, Vec<Value>) = row
+        .iter()
+        .filter(|(name, _)| columns.contains(*name) && (keep_id || *name != "id"))
+        .map(|(name, value)| (name.as_str(), sql_value(value)))
+        .unzip();
+    let placeholders = vec!["?"; names.len()].join(", ");
+    conn.execute(
+        &format!(
+            "INSERT INTO {table} ({}) VALUES ({placeholders})",
+            names.join(", ")
+        ),
+        params_from_iter(values),
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Id of a stored row of `table` equal to `row` in every column but the id.
+fn find_row(conn: &Connection, table: &str, row: &backup::Row) -> Result<Option<i64>> {
+    let columns = table_columns(conn, table)?;
+    let (conditions, values): (Vec<String>, Vec<Value>) = row
+        .iter()
+        .filter(|(name, _)| columns.contains(*name) && *name != "id")
+        .map(|(name, value)| (format!("{name} IS ?"), sql_value(value)))
+        .unzip();
+    conn.query_row(
+        &format!(
+            "SELECT id FROM {table} WHERE {} LIMIT 1",
+            if conditions.is_empty() {
+                "1 = 1".to_string()
+            } else {
+                conditions.join(" AND ")
+            }
+        ),
+        params_from_iter(values),
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn json_value(value: rusqlite::types::ValueRef) -> serde_json::Value {
+    use rusqlite::types::ValueRef;
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(n) => n.into(),
+        ValueRef::Real(x) => x.into(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+        ValueRef::Blob(bytes) => bytes.into(),
+    }
+}
+
+fn sql_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(text) => Value::Text(text.clone()),
+        serde_json::Value::Array(bytes) => Value::Blob(
+            bytes
+                .iter()
+                .filter_map(|byte| byte.as_u64().map(|byte| byte as u8))
+                .collect(),
+        ),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+/// An id in a reference column, stored as an integer or as text.
+fn id_of(value: &serde_json::Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|text| text.parse().ok()))
+}
+
+/// `id` in the same form as the reference `value` it replaces.
+fn remap(value: &serde_json::Value, id: i64) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(_) => id.to_string().into(),
+        _ => id.into(),
+    }
+}
+
+fn insert_client(conn: &Connection, client: &Client) -> Result<()> {
+    conn.execute(
+        "INSERT INTO clients (id, name, phone, email, address, country, vat_id, endpoint, reference)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            client.id,
+            client.name,
+            client.phone,
+            client.email,
+            client.address,
+            client.country,
+            client.vat_id,
+            client.endpoint,
+            client.reference,
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_payment(conn: &Connection, payment: &Payment, id: Option<u32>) -> Result<u32> {
+    conn.execute(
+        "INSERT INTO payments (id, invoice_id, amount, date, reference)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            payment.invoice_id,
+            payment.amount,
+            payment.date,
+            payment.reference,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid() as u32)
+}
+
+/// Whether two invoices hold the same data, ignoring their ids.
+fn same_invoice(a: &Invoice, b: &Invoice) -> bool {
+    let value = |invoice: &Invoice| {
+        let mut value = serde_json::to_value(invoice).unwrap_or_default();
+        value["id"] = serde_json::Value::Null;
+        value["client"] = invoice.client.id.into();
+        if let Some(items) = value["items"].as_array_mut() {
+            for item in items {
+                item["id"] = serde_json::Value::Null;
+            }
+        }
+        value
+    };
+    value(a) == value(b)
+}
+
+fn copy_to(conn: &Connection, path: &Path) -> Result<()> {
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+    Ok(())
+}
+
+/// Whether `conn` holds data from an older schema version.
+fn needs_migration(conn: &Connection) -> Result<bool> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let has_tables: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'invoices')",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(has_tables && version < SCHEMA_VERSION)
+}
+
+/// Insert `invoice` and its items. `rowid` keeps an existing id, `None`
+/// assigns the next one.
+fn insert_invoice(conn: &Connection, invoice: &Invoice, rowid: Option<u32>) -> Result<u32> {
+    conn.execute(
+        "INSERT INTO invoices (rowid, code, client_id, total, currency, discount, tax, paid, status, date, due_date, void_reason, project_id,
+                               issued_at, issued_client, issued_issuer, pdf_hash, credit_note_for, issued_project)
+         VALUES (?11, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        params![
+            invoice.code,
+            invoice.client.id,
+            invoice.total,
+            invoice.currency,
+            invoice.discount,
+            invoice.tax,
+            invoice.paid,
+            invoice.status,
+            invoice.date.to_string(),
+            invoice.due_date,
+            rowid,
+            invoice.void_reason,
+            invoice.project.as_ref().map(|project| project.id),
+            invoice.issued_at,
+            match invoice.is_issued() {
+                true => Some(to_json(&invoice.client)?),
+                false => None,
+            },
+            invoice.issuer.as_ref().map(to_json).transpose()?,
+            invoice.pdf_hash,
+            invoice.credit_note_for,
+            match invoice.is_issued() {
+                true => invoice.project.as_ref().map(to_json).transpose()?,
+                false => None,
+            },
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute("UPDATE invoices SET id = ?1 WHERE rowid = ?1", [id])?;
+    insert_items(conn, id as u32, &invoice.items, 0)?;
+    Ok(id as u32)
+}
+
+fn update_invoice_row(conn: &Connection, invoice: &Invoice) -> Result<()> {
+    conn.execute(
+        "UPDATE invoices
+         SET code = ?1, client_id = ?2, total = ?3, currency = ?4, discount = ?5, tax = ?6,
+             paid = ?7, status = ?8, date = ?9, due_date = ?10, project_id = ?12
+         WHERE rowid = ?11",
+        params![
+            invoice.code,
+            invoice.client.id,
+            invoice.total,
+            invoice.currency,
+            invoice.discount,
+            invoice.tax,
+            invoice.paid,
+            invoice.status,
+            invoice.date,
+            invoice.due_date,
+            invoice.id,
+            invoice.project.as_ref().map(|project| project.id),
+        ],
+    )?;
+    update_items(conn, invoice.id, &invoice.items)
+}
+
+/// Overwrite the invoice's item rows in order, so items keep their ids and
+/// an unchanged item isn't logged as changed; rows left over are removed.
+fn update_items(conn: &Connection, invoice_id: u32, items: &[Item]) -> Result<()> {
+    let ids: Vec<u32> = conn
+        .prepare("SELECT id FROM invoice_items WHERE invoice_id = ?1 ORDER BY position")?
+        .query_map([invoice_id], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    let mut update = conn.prepare(
+        "UPDATE invoice_items
+         SET position = ?2, title = ?3, quantity = ?4, price = ?5, tax_rate = ?6, remark = ?7
+         WHERE id = ?1",
+    )?;
+    for (position, (id, item)) in ids.iter().zip(items).enumerate() {
+        update.execute(params![
+            id,
+            position,
+            item.title,
+            item.quantity,
+            item.price,
+            item.tax_rate,
+            item.remark,
+        ])?;
+    }
+    for id in ids.iter().skip(items.len()) {
+        conn.execute("DELETE FROM invoice_items WHERE id = ?1", [id])?;
+    }
+    insert_items(conn, invoice_id, items, ids.len())
+}
+
+/// Insert `items` from position `from` on.
+fn insert_items(conn: &Connection, invoice_id: u32, items: &[Item], from: usize) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO invoice_items (invoice_id, position, title, quantity, price, tax_rate, remark)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (position, item) in items.iter().enumerate().skip(from) {
+        stmt.execute(params![
+            invoice_id,
+            position,
+            item.title,
+            item.quantity,
+            item.price,
+            item.tax_rate,
+            item.remark,
+        ])?;
+    }
+    Ok(())
+}
+
+fn event_from_row(row: &Row) -> Result<WebhookEvent> {
+    Ok(WebhookEvent {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        invoice_id: row.get(2)?,
+        payload: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+fn invoice_from_row(row: &Row) -> Result<Invoice> {
+    Ok(Invoice {
+        id: row.get(0)?,
+        code: row.get(1)?,
+        total: row.get(2)?,
+        currency: row.get(3)?,
+        discount: row.get(4)?,
+        tax: row.get(5)?,
+        paid: row.get(6)?,
+        status: row.get(7)?,
+        date: row.get(8)?,
+        due_date: row.get(9)?,
+        void_reason: row.get(15)?,
+        // Issued invoices show their project as it was when they were issued.
+        project: match (
+            from_json::<Project>(row, 33)?,
+            row.get::<_, Option<u32>>(20)?,
+        ) {
+            (Some(project), _) => Some(project),
+            (None, Some(_)) => Some(project_from_row(row, 20)?),
+            (None, None) => None,
+        },
+        issued_at: row.get(27)?,
+        issuer: from_json(row, 29)?,
+        pdf_hash: row.get(30)?,
+        credit_note_for: row.get(31)?,
+        credited: row.get(32)?,
+        items: Vec::new(),
+        // Issued invoices show the client as it was when they were issued.
+        client: match from_json::<Client>(row, 28)? {
+            Some(client) => Client {
+                id: row.get(10)?,
+                ..client
+            },
+            None => Client {
+                country: row.get(16)?,
+                vat_id: row.get(17)?,
+                endpoint: row.get(18)?,
+                reference: row.get(19)?,
+                ..client_from_row(row, 10)?
+            },
+        },
+    })
+}
+
+/// Parse the JSON in column `index`, if it isn't NULL.
+fn from_json<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> Result<Option<T>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(text) => serde_json::from_str(&text).map(Some).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(e),
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+/// JSON for `value`, to be stored in a TEXT column.
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Read a client from the columns id, name, phone, email and address
+/// starting at `start`, followed by country, vat_id, endpoint and reference
+/// when `start` is 0.
+fn next_client_id(conn: &Connection) -> Result<u32> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(CAST(id AS INTEGER)), 0) + 1 FROM clients",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn client_from_row(row: &Row, start: usize) -> Result<Client> {
+    let mut client = Client::new(
+        row.get(start)?,
+        &row.get::<_, String>(start + 1)?,
+        &row.get::<_, String>(start + 2)?,
+        &row.get::<_, String>(start + 3)?,
+        &row.get::<_, String>(start + 4)?,
+    );
+    if start == 0 {
+        client.country = row.get(5)?;
+        client.vat_id = row.get(6)?;
+        client.endpoint = row.get(7)?;
+        client.reference = row.get(8)?;
+    }
+    Ok(client)
+}
+
+/// Read a project from the columns id, client_id, name, po_number, budget,
+/// rate and status starting at `start`.
+fn project_from_row(row: &Row, start: usize) -> Result<Project> {
+    Ok(Project {
+        id: row.get(start)?,
+        client_id: row.get(start + 1)?,
+        name: row.get(start + 2)?,
+        po_number: row.get(start + 3)?,
+        budget: row.get(start + 4)?,
+        rate: row.get(start + 5)?,
+        status: row.get(start + 6)?,
+    })
+}
+
+fn sort_expr(column: SortColumn) -> &'static str {
+    match column {
+        SortColumn::Code => "invoices.code",
+        SortColumn::Date => "invoices.date",
+        SortColumn::DueDate => "invoices.due_date",
+        SortColumn::Client => CLIENT_NAME,
+        SortColumn::NetTotal => "(invoices.total - invoices.discount + invoices.tax)",
+        SortColumn::Balance => BALANCE,
+        SortColumn::Status => "invoices.status",
+    }
+}
+
+/// Build the WHERE conditions for `filter`, pushing their bound values.
+fn filter_clause(filter: &InvoiceFilter, values: &mut Vec<Value>) -> String {
+    let mut conditions = vec!["1 = 1".to_string()];
+    if let Some(status) = &filter.status {
+        conditions.push("invoices.status = ?".to_string());
+        values.push(Value::Text(status.clone()));
+    }
+    if let Some(client_id) = filter.client_id {
+        conditions.push("c.id = ?".to_string());
+        values.push(Value::Text(client_id.to_string()));
+    }
+    if let Some(search) = &filter.search {
+        conditions.push(format!("(invoices.code LIKE ? OR {CLIENT_NAME} LIKE ?)"));
+        let pattern = format!("%{search}%");
+        values.push(Value::Text(pattern.clone()));
+        values.push(Value::Text(pattern));
+    }
+    conditions.join(" AND ")
+}
+
+/// Bring databases created by older versions up to the current schema.
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let tx = conn.unchecked_transaction()?;
+
+    if version < 1 {
+        add_column(conn, "invoices", "discount", "REAL NOT NULL DEFAULT 0")?;
+        add_column(conn, "invoices", "tax", "REAL NOT NULL DEFAULT 0")?;
+        add_column(conn, "invoices", "paid", "REAL NOT NULL DEFAULT 0")?;
+        add_column(conn, "invoices", "due_date", "TEXT NOT NULL DEFAULT ''")?;
+    }
+
+    if version < 2 {
+        conn.execute_batch(
+            "
+            UPDATE invoices SET id = rowid WHERE id IS NULL;
+            CREATE INDEX IF NOT EXISTS invoices_date ON invoices (date);
+            CREATE INDEX IF NOT EXISTS invoices_due_date ON invoices (due_date);
+            CREATE INDEX IF NOT EXISTS invoices_status ON invoices (status);
+            CREATE INDEX IF NOT EXISTS invoices_client_id ON invoices (client_id);
+            ",
+        )?;
+    }
+
+    // Line items used to live in a JSON blob on the invoice row. Blobs that
+    // aren't a JSON array of objects are kept as they are in
+    // `legacy_invoice_items` before the column is dropped, so nothing is lost.
+    if version < 3 && has_column(conn, "invoices", "items")? {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS legacy_invoice_items (
+                invoice_id TEXT PRIMARY KEY,
+                items TEXT NOT NULL
+            );
+
+            INSERT INTO legacy_invoice_items (invoice_id, items)
+            SELECT invoices.id, invoices.items
+            FROM invoices
+            WHERE invoices.items IS NOT NULL AND TRIM(invoices.items) != ''
+              AND NOT (
+                  CASE WHEN json_valid(invoices.items) THEN json_type(invoices.items) END IS 'array'
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM json_each(CASE WHEN json_valid(invoices.items) THEN invoices.items ELSE '[]' END) AS item
+                      WHERE item.type != 'object'
+                  )
+              );
+
+            INSERT INTO invoice_items (invoice_id, position, title, quantity, price, tax_rate, remark)
+            SELECT invoices.id,
+                   item.key,
+                   COALESCE(json_extract(item.value, '$.title'), ''),
+                   json_extract(item.value, '$.quantity'),
+                   json_extract(item.value, '$.price'),
+                   json_extract(item.value, '$.tax_rate'),
+                   json_extract(item.value, '$.remark')
+            FROM invoices,
+                 json_each(CASE WHEN json_valid(invoices.items) THEN invoices.items ELSE '[]' END) AS item
+            WHERE CASE WHEN json_valid(invoices.items) THEN json_type(invoices.items) END = 'array'
+              AND invoices.id NOT IN (SELECT invoice_id FROM legacy_invoice_items);
+
+            ALTER TABLE invoices DROP COLUMN items;
+            ",
+        )?;
+    }
+
+    if version < 4 {
+        add_column(conn, "invoices", "void_reason", "TEXT NOT NULL DEFAULT ''")?;
+    }
+
+    if version < 5 {
+        for column in ["country", "vat_id", "endpoint", "reference"] {
+            add_column(conn, "clients", column, "TEXT NOT NULL DEFAULT ''")?;
+        }
+    }
+
+    if version < 6 {
+        for table in ["invoices", "time_entries", "expenses"] {
+            add_column(conn, table, "project_id", "INTEGER")?;
+        }
+    }
+
+    // Invoices already past draft count as issued on their date, with the
+    // client as it is now.
+    if version < 7 {
+        for column in ["issued_at", "pdf_hash", "credit_note_for"] {
+            add_column(conn, "invoices", column, "TEXT NOT NULL DEFAULT ''")?;
+        }
+        for column in ["issued_client", "issued_issuer"] {
+            add_column(conn, "invoices", column, "TEXT")?;
+        }
+        conn.execute_batch(&format!(
+            "
+            UPDATE invoices
+            SET issued_at = date,
+                issued_client = (
+                    SELECT json_object(
+                        'id', CAST(c.id AS INTEGER), 'name', c.name, 'phone', c.phone,
+                        'email', c.email, 'address', c.address, 'country', c.country,
+                        'vat_id', c.vat_id, 'endpoint', c.endpoint, 'reference', c.reference
+                    )
+                    FROM clients c WHERE c.id = invoices.client_id
+                )
+            WHERE status NOT IN ({EXCLUDED_STATUSES}) AND issued_at = '';
+            "
+        ))?;
+    }
+
+    // Issued credit notes count against the invoice they credit.
+    if version < 8 {
+        conn.execute_batch(
+            "
+            CREATE VIEW IF NOT EXISTS credits AS
+            SELECT credit_note_for AS code, -SUM(total - discount + tax) AS amount
+            FROM invoices
+            WHERE credit_note_for != '' AND issued_at != '' AND status NOT IN ('void', 'cancelled')
+            GROUP BY credit_note_for;
+            ",
+        )?;
+    }
+
+    if version < 9 {
+        add_column(conn, "webhook_deliveries", "claimed_by", "TEXT")?;
+    }
+
+    // Fee invoices were only known by their `<code>-F<level>` code.
+    if version < 10 {
+        add_column(conn, "reminders", "fee_invoice_id", "INTEGER")?;
+        conn.execute_batch(
+            "
+            UPDATE reminders
+            SET fee_invoice_id = (
+                SELECT fee.rowid
+                FROM invoices fee, invoices reminded
+                WHERE reminded.rowid = reminders.invoice_id
+                  AND fee.code = reminded.code || '-F' || reminders.level
+            )
+            WHERE fees > 0 AND fee_invoice_id IS NULL;
+            ",
+        )?;
+    }
+
+    // Issued invoices kept showing their project as it is now.
+    if version < 11 {
+        add_column(conn, "invoices", "issued_project", "TEXT")?;
+        conn.execute_batch(
+            "
+            UPDATE invoices
+            SET issued_project = (
+                SELECT json_object(
+                    'id', p.id, 'client_id', CAST(p.client_id AS INTEGER), 'name', p.name,
+                    'po_number', p.po_number, 'budget', p.budget, 'rate', p.rate,
+                    'status', p.status
+                )
+                FROM projects p WHERE p.id = invoices.project_id
+            )
+            WHERE issued_at != '' AND issued_project IS NULL;
+            ",
+        )?;
+    }
+
+    tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    tx.commit()
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    conn.query_row(
+        &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{table}') WHERE name = ?1"),
+        [column],
+        |row| row.get(0),
+    )
+}
+
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+        ))?;
     }
+    Ok(())
 }
@@ -0,0 +1,264 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+
+use crate::config::IssuerConfig;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::mail::{self, Email, MailConfig};
+use crate::models::{Invoice, Item, Reminder};
+use crate::pdf;
+
+/// One step of the dunning process, from `[[dunning.levels]]`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DunningLevel {
+    pub name: String,
+    /// Days past the due date before this level is proposed.
+    pub days: i64,
+    /// Prefix of the `templates/dunning/` files: `<template>.html` for the
+    /// PDF and `<template>_subject.txt` / `<template>_body.txt` for the email.
+    pub template: String,
+    /// Flat late fee, billed on its own invoice.
+    #[serde(default)]
+    pub fee: f64,
+    /// Yearly interest in percent on the balance, for the days since the
+    /// last reminder that charged fees, or since the due date.
+    #[serde(default)]
+    pub interest: f64,
+}
+
+impl DunningLevel {
+    fn new(name: &str, days: i64, template: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            days,
+            template: template.to_string(),
+            fee: 0.0,
+            interest: 0.0,
+        }
+    }
+}
+
+/// Dunning levels, from the `[dunning]` table of the config file. Levels are
+/// sent in the order given, each only after the one before it.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DunningConfig {
+    pub levels: Vec<DunningLevel>,
+}
+
+impl Default for DunningConfig {
+    fn default() -> Self {
+        Self {
+            levels: vec![
+                DunningLevel::new("Friendly reminder", 3, "reminder"),
+                DunningLevel::new("Second reminder", 14, "firm"),
+                DunningLevel::new("Final notice", 30, "final"),
+            ],
+        }
+    }
+}
+
+/// A fee line as shown in the reminder templates.
+#[derive(Serialize)]
+struct FeeLine<'a> {
+    title: &'a str,
+    amount: String,
+}
+
+/// A reminder that is due and waiting for approval.
+#[derive(Clone)]
+pub struct PendingReminder {
+    pub invoice: Invoice,
+    /// Index into the configured levels.
+    pub level: usize,
+    pub days_overdue: i64,
+    /// Days the interest is charged for.
+    pub interest_days: i64,
+    pub fee: f64,
+    pub interest: f64,
+}
+
+impl PendingReminder {
+    pub fn fees(&self) -> f64 {
+        self.fee + self.interest
+    }
+
+    /// The late fee and interest as line items, leaving out zero amounts.
+    /// They carry no VAT.
+    fn fee_items(&self, level: &DunningLevel) -> Vec<Item> {
+        [
+            (format!("Late fee ({})", level.name), self.fee),
+            (
+                format!(
+                    "Interest {}% p.a. for {} days",
+                    level.interest, self.interest_days
+                ),
+                self.interest,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0.0)
+        .map(|(title, amount)| Item {
+            tax_rate: Some(0.0),
+            ..Item::new(&title, Some(amount), Some(1), None)
+        })
+        .collect()
+    }
+}
+
+/// Overdue invoices whose next dunning level is due on `today`.
+pub fn pending(
+    db: &Database,
+    config: &DunningConfig,
+    today: NaiveDate,
+) -> Result<Vec<PendingReminder>, AppError> {
+    let mut pending = Vec::new();
+    for (invoice, reached) in db.get_overdue(&today.to_string())? {
+        let level = reached as usize;
+        let (Some(next), Ok(due_date)) = (
+            config.levels.get(level),
+            NaiveDate::parse_from_str(&invoice.due_date, "%Y-%m-%d"),
+        ) else {
+            continue;
+        };
+        let days_overdue = (today - due_date).num_days();
+        if days_overdue < next.days {
+            continue;
+        }
+        // Earlier reminders already charged interest up to their date.
+        let charged_until = db
+            .get_reminders(invoice.id)?
+            .iter()
+            .filter(|reminder| reminder.status != "skipped")
+            .filter_map(|reminder| NaiveDate::parse_from_str(&reminder.date, "%Y-%m-%d").ok())
+            .max()
+            .map_or(due_date, |date| date.max(due_date));
+        let interest_days = (today - charged_until).num_days().max(0);
+        let interest = invoice.balance() * next.interest / 100.0 * interest_days as f64 / 365.0;
+        pending.push(PendingReminder {
+            level,
+            days_overdue,
+            interest_days,
+            fee: round(next.fee),
+            interest: round(interest),
+            invoice,
+        });
+    }
+    Ok(pending)
+}
+
+/// Record the reminder and bill its fees on a new invoice, then email it with
+/// its PDF. When the email fails the reminder stays recorded as `failed`. In
+/// dry-run mode only the `.eml` file is written.
+pub fn approve(
+    db: &Database,
+    reminder: &PendingReminder,
+    config: &DunningConfig,
+    mail_config: &MailConfig,
+    issuer: &IssuerConfig,
+) -> Result<String, AppError> {
+    let level = level(config, reminder)?;
+    let invoice = db
+        .get_invoice(reminder.invoice.id)?
+        .ok_or_else(|| AppError::Validation("Invoice not found.".to_string()))?;
+    mail::check(&invoice, mail_config)?;
+
+    let fees = reminder.fee_items(level);
+    let mut ctx = Context::new();
+    ctx.insert("invoice", &invoice);
+    ctx.insert("issuer", issuer);
+    ctx.insert("level", level);
+    ctx.insert("level_number", &(reminder.level + 1));
+    ctx.insert("days_overdue", &reminder.days_overdue);
+    let lines: Vec<FeeLine> = fees
+        .iter()
+        .map(|item| FeeLine {
+            title: &item.title,
+            amount: format!("{:.2}", item.price.unwrap_or(0.0)),
+        })
+        .collect();
+    ctx.insert("fees", &lines);
+    ctx.insert(
+        "net_total",
+        &format!("{:.2}", invoice.calculate_net_total()),
+    );
+    ctx.insert("paid", &format!("{:.2}", invoice.paid));
+    ctx.insert("balance", &format!("{:.2}", invoice.balance()));
+    ctx.insert(
+        "amount_due",
+        &format!("{:.2}", invoice.balance() + reminder.fees()),
+    );
+    let template = format!("dunning/{}", level.template);
+    let html = Tera::new("templates/**/*")?.render(&format!("{}.html", template), &ctx)?;
+    let pdf_path = pdf::write_pdf(
+        &html,
+        &format!("reminder_{}_{}", invoice.code, reminder.level + 1),
+    )?;
+
+    let email = Email::compose(
+        &invoice,
+        issuer,
+        mail_config,
+        &template,
+        ctx,
+        &pdf_path,
+        std::fs::read(&pdf_path)?,
+    )?;
+    // Recorded first, so an email that went out is never sent again.
+    let id = if mail_config.dry_run {
+        None
+    } else {
+        Some(db.add_reminder(&record(reminder, level, "sent"), &fees)?)
+    };
+    let result = mail::deliver(db, &invoice, mail_config, &email);
+    if result.is_err()
+        && let Some(id) = id
+    {
+        db.set_reminder_status(id, "failed")?;
+    }
+    result
+}
+
+/// Record the level as dealt with without sending anything, so the next
+/// level comes up in its turn.
+pub fn skip(
+    db: &Database,
+    reminder: &PendingReminder,
+    config: &DunningConfig,
+) -> Result<String, AppError> {
+    let level = level(config, reminder)?;
+    let mut record = record(reminder, level, "skipped");
+    record.fees = 0.0;
+    db.add_reminder(&record, &[])?;
+    Ok(format!(
+        "Skipped {} for invoice {}",
+        level.name, reminder.invoice.code
+    ))
+}
+
+fn level<'a>(
+    config: &'a DunningConfig,
+    reminder: &PendingReminder,
+) -> Result<&'a DunningLevel, AppError> {
+    config
+        .levels
+        .get(reminder.level)
+        .ok_or_else(|| AppError::Config("dunning levels changed; reload the reminders".to_string()))
+}
+
+fn record(reminder: &PendingReminder, level: &DunningLevel, status: &str) -> Reminder {
+    Reminder {
+        id: 0,
+        invoice_id: reminder.invoice.id,
+        level: reminder.level as u32 + 1,
+        name: level.name.clone(),
+        date: Local::now().date_naive().to_string(),
+        fees: reminder.fees(),
+        status: status.to_string(),
+    }
+}
+
+fn round(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
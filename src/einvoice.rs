@@ -0,0 +1,302 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::config::IssuerConfig;
+use crate::error::AppError;
+use crate::models::{Invoice, ValidationErrors, is_country_code};
+
+pub const REQUIRED: &str = "Required for e-invoices; set it in invoice-rs.toml";
+
+/// Commercial invoice (UNTDID 1001).
+const INVOICE_TYPE_CODE: &str = "380";
+/// Credit note (UNTDID 1001).
+const CREDIT_NOTE_TYPE_CODE: &str = "381";
+/// Credit transfer (UNTDID 4461).
+const PAYMENT_MEANS_CODE: &str = "30";
+/// "One", the unit for items counted in pieces (UN/ECE Rec 20).
+const UNIT_CODE: &str = "C62";
+
+/// A party as it appears in the XML: seller or buyer.
+#[derive(Serialize)]
+pub struct Party {
+    pub endpoint_scheme: String,
+    pub endpoint_id: String,
+    pub name: String,
+    pub street: String,
+    pub city: String,
+    pub postal_code: String,
+    pub country: String,
+    pub vat_id: String,
+    pub email: String,
+    pub phone: String,
+}
+
+/// Tax category (UNCL 5305) and rate shared by lines, allowances and the
+/// tax breakdown.
+#[derive(Serialize)]
+pub struct TaxCategory {
+    pub id: &'static str,
+    pub percent: String,
+}
+
+#[derive(Serialize)]
+pub struct Line {
+    pub id: usize,
+    pub note: String,
+    pub quantity: u32,
+    pub unit_code: &'static str,
+    pub amount: String,
+    pub name: String,
+    pub price: String,
+    pub tax: TaxCategory,
+}
+
+#[derive(Serialize)]
+pub struct Subtotal {
+    pub taxable: String,
+    pub tax_amount: String,
+    /// The discount share taken off this category, "0.00" when none.
+    pub allowance: String,
+    pub tax: TaxCategory,
+}
+
+/// An invoice mapped onto the EN 16931 business terms shared by the UBL and
+/// CII syntaxes, amounts already rounded and formatted.
+#[derive(Serialize)]
+pub struct EInvoice {
+    pub id: String,
+    pub issue_date: String,
+    pub due_date: String,
+    pub type_code: &'static str,
+    /// Code of the credited invoice, for credit notes.
+    pub preceding_invoice: String,
+    pub currency: String,
+    pub buyer_reference: String,
+    pub seller: Party,
+    pub buyer: Party,
+    pub payment_means_code: &'static str,
+    pub iban: String,
+    pub bic: String,
+    pub has_allowance: bool,
+    pub subtotals: Vec<Subtotal>,
+    pub tax_total: String,
+    pub line_total: String,
+    pub allowance_total: String,
+    pub tax_exclusive: String,
+    pub tax_inclusive: String,
+    pub prepaid: String,
+    pub payable: String,
+    pub lines: Vec<Line>,
+}
+
+impl EInvoice {
+    /// Map `invoice` and the issuer, rejecting invoices that would produce a
+    /// document failing the EN 16931 rules. `errors` holds problems the
+    /// caller already found, so they are reported together.
+    pub fn new(
+        invoice: &Invoice,
+        issuer: &IssuerConfig,
+        mut errors: ValidationErrors,
+    ) -> Result<Self, AppError> {
+        // Credit notes are stored negated but state positive amounts.
+        let credited;
+        let (invoice, type_code) = if invoice.is_credit_note() {
+            credited = invoice.negated();
+            (&credited, CREDIT_NOTE_TYPE_CODE)
+        } else {
+            (invoice, INVOICE_TYPE_CODE)
+        };
+        check_issuer(&mut errors, issuer);
+        check_buyer(&mut errors, invoice);
+        if invoice.items.is_empty() {
+            errors.add("items", "At least one line item is required");
+        }
+
+        // Lines without their own rate use the rate of the invoice as a whole.
+        let taxable = invoice.total - invoice.discount;
+        let invoice_rate = if taxable > 0.0 {
+            round(invoice.tax * 100.0 / taxable)
+        } else {
+            0.0
+        };
+
+        let mut lines = Vec::new();
+        let mut line_total = 0.0;
+        // Line amounts per rate, keyed in hundredths of a percent.
+        let mut by_rate: BTreeMap<i64, f64> = BTreeMap::new();
+        for (index, item) in invoice.items.iter().enumerate() {
+            let quantity = item.quantity.unwrap_or(0);
+            let price = item.price.unwrap_or(0.0);
+            let amount = round(item.amount());
+            let rate = item.tax_rate.unwrap_or(invoice_rate);
+            line_total += amount;
+            *by_rate.entry(rate_key(rate)).or_default() += amount;
+            lines.push(Line {
+                id: index + 1,
+                note: item.remark.clone().unwrap_or_default(),
+                quantity,
+                unit_code: UNIT_CODE,
+                amount: money(amount),
+                name: item.title.clone(),
+                price: money(price),
+                tax: category(rate),
+            });
+        }
+        let line_total = round(line_total);
+        if (line_total - invoice.total).abs() > 0.005 {
+            errors.add(
+                "total",
+                format!(
+                    "Total {} doesn't match the line items, which add up to {}",
+                    money(invoice.total),
+                    money(line_total)
+                ),
+            );
+        }
+
+        // Spread the discount over the rates in proportion to their lines,
+        // giving the last rate whatever rounding leaves over.
+        let mut subtotals = Vec::new();
+        let mut allowance_left = round(invoice.discount);
+        let mut tax_total = 0.0;
+        let count = by_rate.len();
+        for (position, (key, amount)) in by_rate.into_iter().enumerate() {
+            let allowance = if position + 1 == count {
+                allowance_left
+            } else if line_total > 0.0 {
+                round(invoice.discount * amount / line_total)
+            } else {
+                0.0
+            };
+            allowance_left = round(allowance_left - allowance);
+            let rate = key as f64 / 100.0;
+            let taxable = round(amount - allowance);
+            let tax_amount = round(taxable * rate / 100.0);
+            tax_total += tax_amount;
+            subtotals.push(Subtotal {
+                taxable: money(taxable),
+                tax_amount: money(tax_amount),
+                allowance: money(allowance),
+                tax: category(rate),
+            });
+        }
+        let tax_total = round(tax_total);
+        if (tax_total - invoice.tax).abs() > 0.015 {
+            errors.add(
+                "tax",
+                format!(
+                    "Tax {} doesn't match the line rates, which give {}",
+                    money(invoice.tax),
+                    money(tax_total)
+                ),
+            );
+        }
+
+        errors.into_result()?;
+
+        let tax_exclusive = round(line_total - invoice.discount);
+        let tax_inclusive = round(tax_exclusive + tax_total);
+        let (endpoint_scheme, endpoint_id) = split_endpoint(&issuer.endpoint);
+        let seller = Party {
+            endpoint_scheme,
+            endpoint_id,
+            name: issuer.name.clone(),
+            street: issuer.street.clone(),
+            city: issuer.city.clone(),
+            postal_code: issuer.postal_code.clone(),
+            country: issuer.country.clone(),
+            vat_id: issuer.vat_id.clone(),
+            email: issuer.email.clone(),
+            phone: String::new(),
+        };
+        let client = &invoice.client;
+        let (endpoint_scheme, endpoint_id) = split_endpoint(&client.endpoint);
+        let buyer = Party {
+            endpoint_scheme,
+            endpoint_id,
+            name: client.name.clone(),
+            street: client.address.clone(),
+            city: String::new(),
+            postal_code: String::new(),
+            country: client.country.clone(),
+            vat_id: client.vat_id.clone(),
+            email: client.email.clone(),
+            phone: client.phone.clone(),
+        };
+
+        Ok(Self {
+            id: invoice.code.clone(),
+            issue_date: invoice.date.chars().take(10).collect(),
+            due_date: invoice.due_date.chars().take(10).collect(),
+            type_code,
+            preceding_invoice: invoice.credit_note_for.clone(),
+            currency: invoice.currency.clone(),
+            buyer_reference: client.reference.clone(),
+            seller,
+            buyer,
+            payment_means_code: PAYMENT_MEANS_CODE,
+            iban: issuer.iban.replace(' ', ""),
+            bic: issuer.bic.clone(),
+            has_allowance: invoice.discount > 0.0,
+            subtotals,
+            tax_total: money(tax_total),
+            line_total: money(line_total),
+            allowance_total: money(invoice.discount),
+            tax_exclusive: money(tax_exclusive),
+            tax_inclusive: money(tax_inclusive),
+            prepaid: money(invoice.paid),
+            payable: money(tax_inclusive - invoice.paid),
+            lines,
+        })
+    }
+}
+
+fn check_issuer(errors: &mut ValidationErrors, issuer: &IssuerConfig) {
+    for (field, value) in [
+        ("issuer.name", &issuer.name),
+        ("issuer.vat_id", &issuer.vat_id),
+    ] {
+        if value.trim().is_empty() {
+            errors.add(field, REQUIRED);
+        }
+    }
+    if !is_country_code(&issuer.country) {
+        errors.add("issuer.country", "Use a two-letter country code, e.g. DE");
+    }
+}
+
+fn check_buyer(errors: &mut ValidationErrors, invoice: &Invoice) {
+    let client = &invoice.client;
+    if client.name.trim().is_empty() {
+        errors.add("client", "Client name is required");
+    }
+    if invoice.date.trim().is_empty() {
+        errors.add("date", "Date is required");
+    }
+}
+
+/// Standard rate for positive rates, zero rated otherwise.
+fn category(rate: f64) -> TaxCategory {
+    TaxCategory {
+        id: if rate > 0.0 { "S" } else { "Z" },
+        percent: money(rate),
+    }
+}
+
+fn rate_key(rate: f64) -> i64 {
+    (rate * 100.0).round() as i64
+}
+
+pub fn split_endpoint(endpoint: &str) -> (String, String) {
+    let (scheme, id) = endpoint.split_once(':').unwrap_or_default();
+    (scheme.to_string(), id.trim().to_string())
+}
+
+fn round(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+pub fn money(amount: f64) -> String {
+    // Avoid "-0.00" after rounding.
+    format!("{:.2}", round(amount) + 0.0)
+}
@@ -0,0 +1,67 @@
+use std::fmt;
+
+use crate::models::ValidationErrors;
+
+/// Errors surfaced to the user from app actions.
+#[derive(Debug)]
+pub enum AppError {
+    Db(rusqlite::Error),
+    Render(tera::Error),
+    Pdf(String),
+    Mail(String),
+    Io(std::io::Error),
+    Config(String),
+    Validation(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Db(e) => write!(f, "Database error: {}", e),
+            AppError::Render(e) => write!(f, "Template error: {}", e),
+            AppError::Pdf(e) => write!(f, "PDF export failed: {}", e),
+            AppError::Mail(e) => write!(f, "Sending email failed: {}", e),
+            AppError::Io(e) => write!(f, "File error: {}", e),
+            AppError::Config(e) => write!(f, "Config error: {}", e),
+            AppError::Validation(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Db(e) => Some(e),
+            AppError::Render(e) => Some(e),
+            AppError::Io(e) => Some(e),
+            AppError::Pdf(_)
+            | AppError::Mail(_)
+            | AppError::Config(_)
+            | AppError::Validation(_) => None,
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(e: ValidationErrors) -> Self {
+        AppError::Validation(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        AppError::Db(e)
+    }
+}
+
+impl From<tera::Error> for AppError {
+    fn from(e: tera::Error) -> Self {
+        AppError::Render(e)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
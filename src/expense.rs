@@ -0,0 +1,233 @@
+use chrono::{Days, Local, NaiveDate};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Client, Expense, Invoice, Item, Project, ValidationErrors};
+use crate::project::find_project;
+use crate::timesheet::find_client;
+use crate::utils::generate_unique_id;
+
+/// Expense settings, from the `[expenses]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct ExpenseConfig {
+    /// Markup in percent added when expenses are billed, unless another is given.
+    pub markup: f64,
+    /// Currency of expenses entered without one.
+    pub currency: String,
+    /// Days until a new invoice for expenses is due.
+    pub due_days: u64,
+}
+
+impl Default for ExpenseConfig {
+    fn default() -> Self {
+        Self {
+            markup: 0.0,
+            currency: "USD".to_string(),
+            due_days: 30,
+        }
+    }
+}
+
+/// Names of the fields `validate_expense` takes and reports problems for.
+pub const EXPENSE_FIELDS: [&str; 7] = [
+    "vendor", "amount", "category", "client", "project", "receipt", "date",
+];
+
+/// Build an expense from
+/// `vendor, amount[, category, client, project, receipt, date]`.
+pub fn entry(
+    fields: &[&str],
+    clients: &[Client],
+    projects: &[Project],
+    config: &ExpenseConfig,
+) -> Result<Expense, AppError> {
+    if !(2..=EXPENSE_FIELDS.len()).contains(&fields.len()) {
+        return Err(AppError::Validation(
+            "Give vendor; amount and optionally category; client; project; receipt; date."
+                .to_string(),
+        ));
+    }
+    let mut values = [""; 7];
+    values[..fields.len()].copy_from_slice(fields);
+    Ok(validate_expense(values, clients, projects, config)?)
+}
+
+/// Build an expense from the values of `EXPENSE_FIELDS`, with a problem per
+/// field. The amount may be followed by a currency, e.g. `120.50 EUR`.
+/// Expenses with a client are billable; a project must be one of that
+/// client's. The date defaults to today.
+pub fn validate_expense(
+    values: [&str; 7],
+    clients: &[Client],
+    projects: &[Project],
+    config: &ExpenseConfig,
+) -> Result<Expense, ValidationErrors> {
+    let [vendor, amount, category, client, project, receipt, date] = values.map(str::trim);
+    let mut errors = ValidationErrors::default();
+
+    if vendor.is_empty() {
+        errors.add("vendor", "Give the vendor.");
+    }
+    let (value, currency) = match amount.split_once(' ') {
+        Some((value, currency)) => (value, currency.trim().to_uppercase()),
+        None => (amount, config.currency.clone()),
+    };
+    let amount = match value.parse::<f64>() {
+        Ok(amount) if amount > 0.0 => amount,
+        _ => {
+            errors.add("amount", format!("'{}' is not a valid amount.", amount));
+            0.0
+        }
+    };
+    let client = match client {
+        "" => None,
+        query => find_client(clients, query)
+            .map_err(|e| errors.add("client", e.to_string()))
+            .ok()
+            .cloned(),
+    };
+    let project = match (project, &client) {
+        ("", _) => None,
+        (name, Some(client)) => {
+            let found = find_project(projects, client.id, name);
+            if found.is_none() {
+                errors.add(
+                    "project",
+                    format!("{} has no active project '{}'.", client.name, name),
+                );
+            }
+            found
+        }
+        (_, None) => {
+            errors.add("project", "Give a client for an expense on a project.");
+            None
+        }
+    };
+    if !receipt.is_empty() && !Path::new(receipt).is_file() {
+        errors.add("receipt", format!("Receipt file '{}' not found.", receipt));
+    }
+    let date = match date {
+        "" => Local::now().date_naive(),
+        text => NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap_or_else(|_| {
+            errors.add("date", format!("'{}' is not a date like 2025-01-31.", text));
+            Local::now().date_naive()
+        }),
+    };
+
+    errors.into_result()?;
+    Ok(Expense {
+        id: 0,
+        date: date.to_string(),
+        vendor: vendor.to_string(),
+        amount,
+        currency,
+        category: category.to_string(),
+        receipt: receipt.to_string(),
+        billable: client.is_some(),
+        client,
+        project_id: project.map(|project| project.id),
+        invoice_id: None,
+    })
+}
+
+/// The markup typed into a prompt, or the configured one when left empty.
+pub fn parse_markup(text: &str, config: &ExpenseConfig) -> Result<f64, AppError> {
+    let text = text.trim().trim_end_matches('%').trim();
+    if text.is_empty() {
+        return Ok(config.markup);
+    }
+    text.parse::<f64>()
+        .ok()
+        .filter(|markup| *markup >= 0.0)
+        .ok_or_else(|| AppError::Validation(format!("'{}' is not a valid markup.", text)))
+}
+
+/// One invoice line per expense, raised by `markup` percent.
+pub fn items(expenses: &[Expense], markup: f64) -> Vec<Item> {
+    expenses
+        .iter()
+        .map(|expense| {
+            let title = if expense.category.is_empty() {
+                expense.vendor.clone()
+            } else {
+                format!("{}: {}", expense.category, expense.vendor)
+            };
+            let price = (expense.amount * (100.0 + markup)).round() / 100.0;
+            Item::new(&title, Some(price), Some(1), Some(expense.date.clone()))
+        })
+        .collect()
+}
+
+/// Add the client's billable expenses to their latest draft invoice for the
+/// same project and currency, or to a new draft, and mark them billed.
+pub fn bill(
+    db: &Database,
+    client_id: u32,
+    markup: f64,
+    config: &ExpenseConfig,
+    today: NaiveDate,
+) -> Result<String, AppError> {
+    let expenses = db.get_billable_expenses(client_id)?;
+    if expenses.is_empty() {
+        return Err(AppError::Validation(
+            "This client has no billable expenses.".to_string(),
+        ));
+    }
+
+    let projects = db.get_projects()?;
+
+    let mut groups: Vec<(Option<u32>, &str)> = Vec::new();
+    for expense in &expenses {
+        let key = (expense.project_id, expense.currency.as_str());
+        if !groups.contains(&key) {
+            groups.push(key);
+        }
+    }
+    let mut billed = Vec::new();
+    for (project_id, currency) in groups {
+        let group: Vec<Expense> = expenses
+            .iter()
+            .filter(|expense| expense.project_id == project_id && expense.currency == currency)
+            .cloned()
+            .collect();
+        let mut invoice = match db.latest_draft(client_id, currency, project_id)? {
+            Some(invoice) => invoice,
+            None => Invoice {
+                code: generate_unique_id(&db.get_invoice_codes()?),
+                client: group[0].client.clone().ok_or_else(|| {
+                    AppError::Validation(format!("Expense {} has no client to bill.", group[0].id))
+                })?,
+                currency: currency.to_string(),
+                status: "draft".to_string(),
+                date: today.to_string(),
+                due_date: (today + Days::new(config.due_days)).to_string(),
+                ..Invoice::default()
+            },
+        };
+        invoice.project = projects
+            .iter()
+            .find(|project| Some(project.id) == project_id)
+            .cloned();
+        for mut item in items(&group, markup) {
+            invoice.total += item.price.unwrap_or(0.0);
+            item.id = invoice.items.len() as u32 + 1;
+            invoice.items.push(item);
+        }
+        let ids: Vec<u32> = group.iter().map(|expense| expense.id).collect();
+        db.bill_expenses(&invoice, &ids).map_err(|e| match e {
+            rusqlite::Error::StatementChangedRows(_) => AppError::Validation(
+                "Some of these expenses were billed in the meantime.".to_string(),
+            ),
+            e => e.into(),
+        })?;
+        billed.push(format!(
+            "{} expense(s) to draft invoice {}",
+            group.len(),
+            invoice.code
+        ));
+    }
+    Ok(format!("Billed {}", billed.join(", ")))
+}
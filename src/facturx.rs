@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use lopdf::{Document, Object, Stream, dictionary};
+use serde::Deserialize;
+use strum::{Display, EnumString};
+use tera::{Context, Tera};
+
+use crate::config::IssuerConfig;
+use crate::einvoice::EInvoice;
+use crate::error::AppError;
+use crate::models::{Invoice, ValidationErrors, is_country_code};
+
+/// Name the XML must have inside the PDF.
+pub const FILE_NAME: &str = "factur-x.xml";
+
+/// sRGB output intent required by PDF/A; Chrome prints in RGB.
+const SRGB_PROFILE: &[u8] = include_bytes!("../assets/sRGB-v4.icc");
+
+/// How much of the invoice the embedded XML carries.
+#[derive(Clone, Copy, PartialEq, Deserialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Profile {
+    /// Header totals only; in Germany not a valid invoice on its own.
+    Minimum,
+    /// Adds line items, VAT breakdown and payment details.
+    Basic,
+    /// The full EN 16931 core invoice.
+    En16931,
+}
+
+impl Profile {
+    /// The guideline identifier (BT-24).
+    pub fn guideline(self) -> &'static str {
+        match self {
+            Self::Minimum => "urn:factur-x.eu:1p0:minimum",
+            Self::Basic => "urn:cen.eu:en16931:2017#compliant#urn:factur-x.eu:1p0:basic",
+            Self::En16931 => "urn:cen.eu:en16931:2017",
+        }
+    }
+
+    /// Value of `fx:ConformanceLevel` in the XMP metadata.
+    pub fn conformance_level(self) -> &'static str {
+        match self {
+            Self::Minimum => "MINIMUM",
+            Self::Basic => "BASIC",
+            Self::En16931 => "EN 16931",
+        }
+    }
+
+    /// How the XML relates to the printed invoice: below BASIC it can't
+    /// replace it, so it is only supporting data.
+    fn relationship(self) -> &'static str {
+        match self {
+            Self::Minimum => "Data",
+            Self::Basic | Self::En16931 => "Alternative",
+        }
+    }
+}
+
+/// Render the CII XML for `invoice` at `profile`, with its issuer snapshot
+/// once issued.
+pub fn render(
+    invoice: &Invoice,
+    issuer: &IssuerConfig,
+    profile: Profile,
+) -> Result<String, AppError> {
+    let issuer = invoice.issuer.as_ref().unwrap_or(issuer);
+    let mut errors = ValidationErrors::default();
+    if profile != Profile::Minimum && !is_country_code(&invoice.client.country) {
+        errors.add("client.country", "Client needs a two-letter country code");
+    }
+    let cii = EInvoice::new(invoice, issuer, errors)?;
+
+    let tera = Tera::new("templates/**/*")?;
+    let mut ctx = Context::new();
+    ctx.insert("guideline", profile.guideline());
+    ctx.insert("basic", &(profile != Profile::Minimum));
+    ctx.insert("en16931", &(profile == Profile::En16931));
+    ctx.insert("invoice", &cii);
+    Ok(tera.render("facturx_invoice.xml", &ctx)?)
+}
+
+/// Turn a PDF printed by Chrome into a PDF/A-3 with `xml` attached as
+/// `factur-x.xml`, adding the XMP metadata and output intent the standard
+/// asks for, dated `date`. The file ID is left to `pdf::normalize`.
+pub fn embed(
+    pdf: &[u8],
+    xml: &str,
+    profile: Profile,
+    title: &str,
+    date: DateTime<Utc>,
+) -> Result<Vec<u8>, AppError> {
+    let mut doc = Document::load_mem(pdf).map_err(pdf_error)?;
+
+    let tera = Tera::new("templates/**/*")?;
+    let mut ctx = Context::new();
+    ctx.insert("title", title);
+    ctx.insert("date", &date.to_rfc3339());
+    ctx.insert("conformance_level", profile.conformance_level());
+    ctx.insert("file_name", FILE_NAME);
+    let metadata = tera.render("facturx_metadata.xml", &ctx)?;
+
+    let pdf_date = Object::string_literal(pdf_date(&date));
+    let file = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Subtype" => "text/xml",
+            "Params" => dictionary! {
+                "Size" => xml.len() as i64,
+                "ModDate" => pdf_date.clone(),
+            },
+        },
+        xml.as_bytes().to_vec(),
+    ));
+    let file_spec = doc.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(FILE_NAME),
+        "UF" => Object::string_literal(FILE_NAME),
+        "Desc" => Object::string_literal("Factur-X invoice"),
+        "AFRelationship" => profile.relationship(),
+        "EF" => dictionary! { "F" => file, "UF" => file },
+    });
+    let metadata = doc.add_object(Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        metadata.into_bytes(),
+    ));
+    let icc = doc.add_object(Stream::new(dictionary! { "N" => 3 }, SRGB_PROFILE.to_vec()));
+    let info = doc.add_object(dictionary! {
+        "Title" => Object::string_literal(title),
+        "Creator" => Object::string_literal("invoice-rs"),
+        "Producer" => Object::string_literal("invoice-rs"),
+        "CreationDate" => pdf_date.clone(),
+        "ModDate" => pdf_date,
+    });
+
+    let catalog = doc.catalog_mut().map_err(pdf_error)?;
+    let embedded = dictionary! {
+        "Names" => vec![Object::string_literal(FILE_NAME), file_spec.into()],
+    };
+    match catalog.get_mut(b"Names").and_then(Object::as_dict_mut) {
+        Ok(names) => names.set("EmbeddedFiles", embedded),
+        Err(_) => catalog.set("Names", dictionary! { "EmbeddedFiles" => embedded }),
+    }
+    catalog.set("AF", vec![file_spec.into()]);
+    catalog.set("Metadata", metadata);
+    catalog.set(
+        "OutputIntents",
+        vec![
+            dictionary! {
+                "Type" => "OutputIntent",
+                "S" => "GTS_PDFA1",
+                "OutputConditionIdentifier" => Object::string_literal("sRGB IEC61966-2.1"),
+                "Info" => Object::string_literal("sRGB IEC61966-2.1"),
+                "DestOutputProfile" => icc,
+            }
+            .into(),
+        ],
+    );
+
+    doc.trailer.set("Info", info);
+    doc.version = "1.7".to_string();
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out)?;
+    Ok(out)
+}
+
+/// A date in the PDF format, e.g. `D:20261019143000Z`.
+pub fn pdf_date(date: &DateTime<Utc>) -> String {
+    date.format("D:%Y%m%d%H%M%SZ").to_string()
+}
+
+pub fn pdf_error(e: lopdf::Error) -> AppError {
+    AppError::Pdf(e.to_string())
+}
@@ -0,0 +1,476 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use strum::{Display, EnumString};
+
+use crate::app::Mode;
+use crate::ui::layout::SelectedTab;
+use crate::ui::modal::ModalType;
+
+/// Named actions that keys can be bound to.
+#[derive(Clone, Copy, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum KeyAction {
+    Quit,
+    Help,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    PageDown,
+    PageUp,
+    NextTab,
+    PreviousTab,
+    New,
+    NewClient,
+    EditClient,
+    NewProject,
+    NewTimeEntry,
+    NewExpense,
+    Edit,
+    ExportPdf,
+    ExportReportPdf,
+    ExportCsv,
+    ExportUbl,
+    SendEmail,
+    ApproveReminder,
+    SkipReminder,
+    AcceptMatch,
+    RejectMatch,
+    Import,
+    ImportStatement,
+    ReportRange,
+    Delete,
+    DeleteTimeEntry,
+    DeleteExpense,
+    Void,
+    CreditNote,
+    RecordPayment,
+    ToggleTimer,
+    BillTime,
+    BillExpenses,
+    History,
+    SortColumn,
+    ReverseSort,
+    NextField,
+    PreviousField,
+    Backspace,
+    DeleteForward,
+    DeleteWord,
+    CursorLeft,
+    CursorRight,
+    CursorHome,
+    CursorEnd,
+    Save,
+    Submit,
+    Cancel,
+}
+
+impl KeyAction {
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::Help => "Show this help",
+            Self::Down => "Move down",
+            Self::Up => "Move up",
+            Self::Top => "Go to first invoice",
+            Self::Bottom => "Go to last invoice",
+            Self::PageDown => "Half page down",
+            Self::PageUp => "Half page up",
+            Self::NextTab => "Next tab",
+            Self::PreviousTab => "Previous tab",
+            Self::New => "New invoice",
+            Self::NewClient => "New client",
+            Self::EditClient => "Edit the selected client",
+            Self::NewProject => "New project for the selected client",
+            Self::NewTimeEntry => "New time entry",
+            Self::NewExpense => "New expense",
+            Self::Edit => "Edit invoice",
+            Self::ExportPdf => "Export invoice pdf",
+            Self::ExportReportPdf => "Export report pdf",
+            Self::ExportCsv => "Export report, invoices or clients as csv",
+            Self::ExportUbl => "Export invoice as UBL / Peppol xml",
+            Self::SendEmail => "Email invoice to the client",
+            Self::ApproveReminder => "Send the selected reminder",
+            Self::SkipReminder => "Skip the selected reminder",
+            Self::AcceptMatch => "Accept the selected bank match",
+            Self::RejectMatch => "Reject the selected bank match",
+            Self::Import => "Import clients or invoices",
+            Self::ImportStatement => "Import a bank statement",
+            Self::ReportRange => "Set report date range",
+            Self::Delete => "Delete invoice",
+            Self::DeleteTimeEntry => "Delete time entry",
+            Self::DeleteExpense => "Delete expense",
+            Self::Void => "Void draft invoice",
+            Self::CreditNote => "Create a credit note for the issued invoice",
+            Self::RecordPayment => "Record a payment",
+            Self::ToggleTimer => "Start or stop the timer",
+            Self::BillTime => "Bill the client's unbilled time on a draft invoice",
+            Self::BillExpenses => "Bill the client's unbilled expenses on a draft invoice",
+            Self::History => "Show the invoice's change history",
+            Self::SortColumn => "Sort by next column",
+            Self::ReverseSort => "Reverse sort direction",
+            Self::NextField => "Next field",
+            Self::PreviousField => "Previous field",
+            Self::Backspace => "Delete character",
+            Self::DeleteForward => "Delete character under cursor",
+            Self::DeleteWord => "Delete previous word",
+            Self::CursorLeft => "Move cursor left",
+            Self::CursorRight => "Move cursor right",
+            Self::CursorHome => "Move cursor to start",
+            Self::CursorEnd => "Move cursor to end",
+            Self::Save => "Save",
+            Self::Submit => "Confirm",
+            Self::Cancel => "Cancel",
+        }
+    }
+
+    /// The tab an action is limited to. Actions for different tabs can share
+    /// a key; the one for the current tab wins over an unscoped binding.
+    pub fn tab(self) -> Option<SelectedTab> {
+        match self {
+            // These act on the selected invoice, which other tabs don't show.
+            Self::New
+            | Self::Edit
+            | Self::ExportPdf
+            | Self::ExportUbl
+            | Self::SendEmail
+            | Self::Delete
+            | Self::Void
+            | Self::CreditNote
+            | Self::RecordPayment
+            | Self::History => Some(SelectedTab::Invoices),
+            Self::NewClient | Self::EditClient | Self::NewProject => Some(SelectedTab::Clients),
+            Self::ExportReportPdf => Some(SelectedTab::Reports),
+            Self::ApproveReminder | Self::SkipReminder => Some(SelectedTab::Reminders),
+            Self::AcceptMatch | Self::RejectMatch | Self::ImportStatement => {
+                Some(SelectedTab::Bank)
+            }
+            Self::NewTimeEntry | Self::DeleteTimeEntry => Some(SelectedTab::Time),
+            Self::NewExpense | Self::DeleteExpense | Self::BillExpenses => {
+                Some(SelectedTab::Expenses)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Which set of bindings applies, derived from the app `Mode`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum KeyContext {
+    Normal,
+    Editing,
+    Confirm,
+    Prompt,
+    Alert,
+    Wizard,
+}
+
+impl From<&Mode> for KeyContext {
+    fn from(mode: &Mode) -> Self {
+        match mode {
+            Mode::Normal => Self::Normal,
+            Mode::Editing => Self::Editing,
+            Mode::Wizard => Self::Wizard,
+            Mode::Modal(ModalType::Alert) => Self::Alert,
+            Mode::Modal(ModalType::Confirm) => Self::Confirm,
+            Mode::Modal(ModalType::Prompt) => Self::Prompt,
+        }
+    }
+}
+
+/// A key plus modifiers, written like `j`, `G`, `ctrl-d` or `enter`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        // Shift is already reflected in the character itself.
+        let relevant = KeyModifiers::CONTROL | KeyModifiers::ALT;
+        self.code == key.code && self.modifiers == key.modifiers & relevant
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            if let Some(key) = rest.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = key;
+            } else if let Some(key) = rest.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = key;
+            } else {
+                break;
+            }
+        }
+
+        let code = match rest {
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" => KeyCode::Delete,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match rest.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n) => KeyCode::F(n),
+                        None => return Err(format!("unknown key '{}'", s)),
+                    },
+                }
+            }
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift-tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "◄"),
+            KeyCode::Right => write!(f, "►"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// Bindings as written in the `[keys.<context>]` tables of the config file,
+/// mapping a key to an action name.
+pub type KeymapConfig = HashMap<String, HashMap<String, String>>;
+
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<(KeyBinding, KeyAction)>>,
+}
+
+impl Keymap {
+    /// Default bindings overridden by `config`. Unknown keys or actions are
+    /// returned as errors and otherwise ignored.
+    pub fn new(config: &KeymapConfig) -> (Self, Vec<String>) {
+        let mut keymap = Self::default();
+        let mut errors = Vec::new();
+        for (context, keys) in config {
+            let Ok(context) = context.parse::<KeyContext>() else {
+                errors.push(format!("unknown key context '{}'", context));
+                continue;
+            };
+            for (key, action) in keys {
+                match (key.parse::<KeyBinding>(), action.parse::<KeyAction>()) {
+                    (Ok(key), Ok(action)) => keymap.bind(context, key, action),
+                    (Err(e), _) => errors.push(e),
+                    (_, Err(_)) => errors.push(format!("unknown action '{}'", action)),
+                }
+            }
+        }
+        (keymap, errors)
+    }
+
+    fn bind(&mut self, context: KeyContext, key: KeyBinding, action: KeyAction) {
+        let bindings = self.bindings.entry(context).or_default();
+        bindings
+            .retain(|(bound, bound_action)| *bound != key || bound_action.tab() != action.tab());
+        bindings.push((key, action));
+    }
+
+    /// The action bound to `key`, preferring one scoped to `tab`.
+    pub fn action(
+        &self,
+        context: KeyContext,
+        tab: SelectedTab,
+        key: &KeyEvent,
+    ) -> Option<KeyAction> {
+        let matching = || {
+            self.bindings
+                .get(&context)
+                .into_iter()
+                .flatten()
+                .filter(|(binding, _)| binding.matches(key))
+                .map(|(_, action)| *action)
+        };
+        matching()
+            .find(|action| action.tab() == Some(tab))
+            .or_else(|| matching().find(|action| action.tab().is_none()))
+    }
+
+    /// Bindings for `context` grouped by action, in the order they were defined.
+    pub fn help(&self, context: KeyContext) -> Vec<(KeyAction, Vec<KeyBinding>)> {
+        let mut help: Vec<(KeyAction, Vec<KeyBinding>)> = Vec::new();
+        for (key, action) in self.bindings.get(&context).into_iter().flatten() {
+            match help.iter_mut().find(|(a, _)| a == action) {
+                Some((_, keys)) => keys.push(*key),
+                None => help.push((*action, vec![*key])),
+            }
+        }
+        help
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use KeyAction::*;
+
+        let defaults: [(KeyContext, &[(&str, KeyAction)]); 5] = [
+            (
+                KeyContext::Normal,
+                &[
+                    ("q", Quit),
+                    ("esc", Quit),
+                    ("h", Help),
+                    ("?", Help),
+                    ("j", Down),
+                    ("down", Down),
+                    ("k", Up),
+                    ("up", Up),
+                    ("g", Top),
+                    ("home", Top),
+                    ("G", Bottom),
+                    ("end", Bottom),
+                    ("ctrl-d", PageDown),
+                    ("pagedown", PageDown),
+                    ("ctrl-u", PageUp),
+                    ("pageup", PageUp),
+                    ("l", NextTab),
+                    ("right", NextTab),
+                    ("left", PreviousTab),
+                    ("n", New),
+                    ("n", NewClient),
+                    ("P", NewProject),
+                    ("n", NewTimeEntry),
+                    ("n", NewExpense),
+                    ("e", Edit),
+                    ("e", EditClient),
+                    ("p", ExportPdf),
+                    ("p", ExportReportPdf),
+                    ("c", ExportCsv),
+                    ("x", ExportUbl),
+                    ("m", SendEmail),
+                    ("a", ApproveReminder),
+                    ("a", AcceptMatch),
+                    ("i", Import),
+                    ("i", ImportStatement),
+                    ("t", ReportRange),
+                    ("d", Delete),
+                    ("d", SkipReminder),
+                    ("d", RejectMatch),
+                    ("d", DeleteTimeEntry),
+                    ("d", DeleteExpense),
+                    ("v", Void),
+                    ("N", CreditNote),
+                    ("r", RecordPayment),
+                    ("space", ToggleTimer),
+                    ("b", BillTime),
+                    ("b", BillExpenses),
+                    ("H", History),
+                    ("s", SortColumn),
+                    ("S", ReverseSort),
+                ],
+            ),
+            (
+                KeyContext::Editing,
+                &[
+                    ("f1", Help),
+                    ("tab", NextField),
+                    ("backtab", PreviousField),
+                    ("backspace", Backspace),
+                    ("delete", DeleteForward),
+                    ("ctrl-w", DeleteWord),
+                    ("alt-backspace", DeleteWord),
+                    ("left", CursorLeft),
+                    ("right", CursorRight),
+                    ("home", CursorHome),
+                    ("ctrl-a", CursorHome),
+                    ("end", CursorEnd),
+                    ("ctrl-e", CursorEnd),
+                    ("enter", Save),
+                    ("esc", Cancel),
+                ],
+            ),
+            (
+                KeyContext::Confirm,
+                &[
+                    ("tab", NextField),
+                    ("backtab", PreviousField),
+                    ("left", PreviousField),
+                    ("right", NextField),
+                    ("enter", Submit),
+                    ("esc", Cancel),
+                ],
+            ),
+            (
+                KeyContext::Wizard,
+                &[
+                    ("f1", Help),
+                    ("tab", NextField),
+                    ("down", NextField),
+                    ("backtab", PreviousField),
+                    ("up", PreviousField),
+                    ("left", CursorLeft),
+                    ("right", CursorRight),
+                    ("backspace", Backspace),
+                    ("delete", DeleteForward),
+                    ("ctrl-w", DeleteWord),
+                    ("home", CursorHome),
+                    ("end", CursorEnd),
+                    ("enter", Submit),
+                    ("esc", Cancel),
+                ],
+            ),
+            (
+                KeyContext::Prompt,
+                &[("backspace", Backspace), ("enter", Submit), ("esc", Cancel)],
+            ),
+        ];
+
+        let mut keymap = Self {
+            bindings: HashMap::new(),
+        };
+        for (context, bindings) in defaults {
+            for (key, action) in bindings {
+                let key = key.parse().expect("default key bindings are valid");
+                keymap.bind(context, key, *action);
+            }
+        }
+        keymap
+    }
+}
@@ -0,0 +1,383 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use strum::{Display, EnumString};
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Invoice, Payment};
+use crate::report::DateRange;
+
+/// Account names for the journal, from the `[ledger]` table of the config
+/// file. Beancount needs them to start with `Assets`, `Liabilities`,
+/// `Equity`, `Income` or `Expenses`.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct LedgerConfig {
+    pub receivable: String,
+    pub revenue: String,
+    pub discount: String,
+    pub tax: String,
+    pub bank: String,
+    /// Receivable account per client name.
+    pub clients: BTreeMap<String, String>,
+    /// Revenue account per item title, e.g. to book by category.
+    pub products: BTreeMap<String, String>,
+    /// Tax account per rate, keyed like `"19"` or `"7.5"`.
+    pub tax_rates: BTreeMap<String, String>,
+}
+
+impl Default for LedgerConfig {
+    fn default() -> Self {
+        Self {
+            receivable: "Assets:Receivable".to_string(),
+            revenue: "Income:Sales".to_string(),
+            discount: "Income:Discounts".to_string(),
+            tax: "Liabilities:Tax".to_string(),
+            bank: "Assets:Bank".to_string(),
+            clients: BTreeMap::new(),
+            products: BTreeMap::new(),
+            tax_rates: BTreeMap::new(),
+        }
+    }
+}
+
+impl LedgerConfig {
+    fn receivable(&self, client: &str) -> &str {
+        self.clients.get(client).unwrap_or(&self.receivable)
+    }
+
+    fn revenue(&self, product: &str) -> &str {
+        self.products.get(product).unwrap_or(&self.revenue)
+    }
+
+    fn tax(&self, rate: i64) -> &str {
+        self.tax_rates.get(&format_rate(rate)).unwrap_or(&self.tax)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum LedgerFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl LedgerFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Ledger => "ledger",
+            Self::Hledger => "journal",
+            Self::Beancount => "beancount",
+        }
+    }
+}
+
+/// One journal entry. Amounts are in cents and add up to zero.
+pub struct Entry {
+    pub date: String,
+    pub code: String,
+    pub payee: String,
+    pub narration: String,
+    pub currency: String,
+    pub postings: Vec<(String, i64)>,
+    // Invoices before payments on the same day.
+    order: u8,
+    id: u32,
+}
+
+/// Journal entries for the invoices issued and payments received in
+/// `range`, in date order. When more than one currency occurs, every
+/// account gets the currency as a last part, so no account mixes them.
+pub fn journal(
+    db: &Database,
+    config: &LedgerConfig,
+    range: DateRange,
+) -> Result<Vec<Entry>, AppError> {
+    let (from, to) = (range.from.to_string(), range.to.to_string());
+    let invoices = db.get_booked_invoices(&from, &to)?;
+
+    let mut entries: Vec<Entry> = invoices
+        .iter()
+        .map(|inv| invoice_entry(inv, config))
+        .collect();
+    // Payments may settle invoices from before the range.
+    let mut by_id: HashMap<u32, Option<Invoice>> = invoices
+        .into_iter()
+        .map(|inv| (inv.id, Some(inv)))
+        .collect();
+    for payment in db.get_payments_between(&from, &to)? {
+        let inv = match by_id.get(&payment.invoice_id) {
+            Some(inv) => inv,
+            None => {
+                let inv = db.get_invoice(payment.invoice_id)?;
+                by_id.entry(payment.invoice_id).or_insert(inv)
+            }
+        };
+        if let Some(inv) = inv {
+            entries.push(payment_entry(&payment, inv, config));
+        }
+    }
+
+    let mut currencies: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry.currency.as_str())
+        .collect();
+    currencies.sort();
+    currencies.dedup();
+    if currencies.len() > 1 {
+        for entry in &mut entries {
+            for (account, _) in &mut entry.postings {
+                *account = format!("{}:{}", account, entry.currency);
+            }
+        }
+    }
+    entries
+        .sort_by(|a, b| (&a.date, a.order, &a.code, a.id).cmp(&(&b.date, b.order, &b.code, b.id)));
+    Ok(entries)
+}
+
+/// Receivable against revenue per product, discount and tax per rate.
+/// Invoices with a negative total are credit notes and post the reverse.
+fn invoice_entry(inv: &Invoice, config: &LedgerConfig) -> Entry {
+    let total = cents(inv.total);
+    let discount = cents(inv.discount);
+    let tax = cents(inv.tax);
+
+    // Lines without their own rate use the rate of the invoice as a whole.
+    let invoice_rate = if total - discount != 0 {
+        (tax as f64 * 10_000.0 / (total - discount) as f64).round() as i64
+    } else {
+        0
+    };
+    let mut revenue: BTreeMap<&str, i64> = BTreeMap::new();
+    // Line amounts per rate, in hundredths of a percent.
+    let mut by_rate: BTreeMap<i64, i64> = BTreeMap::new();
+    let mut lines = 0;
+    for item in &inv.items {
+        let amount = cents(item.amount());
+        let rate = item
+            .tax_rate
+            .map_or(invoice_rate, |rate| (rate * 100.0).round() as i64);
+        *revenue.entry(config.revenue(&item.title)).or_default() += amount;
+        *by_rate.entry(rate).or_default() += amount;
+        lines += amount;
+    }
+    // Book any difference between the items and the total as plain revenue.
+    if lines != total {
+        *revenue.entry(&config.revenue).or_default() += total - lines;
+    }
+
+    let mut postings = vec![(
+        config.receivable(&inv.client.name).to_string(),
+        total - discount + tax,
+    )];
+    postings.extend(
+        revenue
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .map(|(account, amount)| (account.to_string(), -amount)),
+    );
+    if discount != 0 {
+        postings.push((config.discount.clone(), discount));
+    }
+    let mut taxes: BTreeMap<&str, i64> = BTreeMap::new();
+    for (rate, amount) in split_tax(tax, &by_rate, invoice_rate) {
+        *taxes.entry(config.tax(rate)).or_default() += amount;
+    }
+    postings.extend(
+        taxes
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .map(|(account, amount)| (account.to_string(), -amount)),
+    );
+
+    let kind = if total - discount + tax < 0 {
+        "Credit note"
+    } else {
+        "Invoice"
+    };
+    Entry {
+        date: inv.date.clone(),
+        code: inv.code.clone(),
+        payee: inv.client.name.clone(),
+        narration: format!("{} {}", kind, inv.code),
+        currency: inv.currency.clone(),
+        postings,
+        order: 0,
+        id: inv.id,
+    }
+}
+
+/// Share `tax` out over the rates in proportion to the tax each rate's
+/// lines would carry, so the parts add up to the invoice's tax exactly.
+fn split_tax(tax: i64, by_rate: &BTreeMap<i64, i64>, invoice_rate: i64) -> Vec<(i64, i64)> {
+    let weights: Vec<(i64, i128)> = by_rate
+        .iter()
+        .map(|(rate, amount)| (*rate, i128::from(*rate) * i128::from(*amount)))
+        .filter(|(_, weight)| *weight != 0)
+        .collect();
+    let total: i128 = weights.iter().map(|(_, weight)| weight).sum();
+    if total == 0 {
+        return vec![(invoice_rate, tax)];
+    }
+    let mut shares: Vec<(i64, i64)> = weights
+        .iter()
+        .map(|(rate, weight)| (*rate, (i128::from(tax) * weight / total) as i64))
+        .collect();
+    let rest = tax - shares.iter().map(|(_, share)| share).sum::<i64>();
+    if let Some(largest) = shares.iter_mut().max_by_key(|(_, share)| share.abs()) {
+        largest.1 += rest;
+    }
+    shares
+}
+
+fn payment_entry(payment: &Payment, inv: &Invoice, config: &LedgerConfig) -> Entry {
+    let amount = cents(payment.amount);
+    let mut narration = format!("Payment {}", inv.code);
+    if !payment.reference.is_empty() {
+        narration.push_str(&format!(" ({})", payment.reference));
+    }
+    Entry {
+        date: payment.date.clone(),
+        code: inv.code.clone(),
+        payee: inv.client.name.clone(),
+        narration,
+        currency: inv.currency.clone(),
+        postings: vec![
+            (config.bank.clone(), amount),
+            (config.receivable(&inv.client.name).to_string(), -amount),
+        ],
+        order: 1,
+        id: payment.id,
+    }
+}
+
+/// The journal as ledger-cli, hledger or Beancount text.
+pub fn render(entries: &[Entry], format: LedgerFormat, range: DateRange) -> String {
+    let mut out = String::new();
+    if format == LedgerFormat::Beancount {
+        let mut accounts: Vec<String> = entries
+            .iter()
+            .flat_map(|entry| &entry.postings)
+            .map(|(account, _)| beancount_account(account))
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+        for account in &accounts {
+            let _ = writeln!(out, "{} open {}", range.from, account);
+        }
+    }
+
+    for entry in entries {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        match format {
+            LedgerFormat::Ledger => {
+                let _ = writeln!(
+                    out,
+                    "{} * ({}) {}",
+                    entry.date.replace('-', "/"),
+                    entry.code,
+                    entry.payee
+                );
+                let _ = writeln!(out, "    ; {}", entry.narration);
+            }
+            LedgerFormat::Hledger => {
+                let _ = writeln!(
+                    out,
+                    "{} * ({}) {} | {}",
+                    entry.date,
+                    entry.code,
+                    entry.payee.replace('|', "/"),
+                    entry.narration
+                );
+            }
+            LedgerFormat::Beancount => {
+                let _ = writeln!(
+                    out,
+                    "{} * {} {}",
+                    entry.date,
+                    quote(&entry.payee),
+                    quote(&entry.narration)
+                );
+                let _ = writeln!(out, "    invoice: {}", quote(&entry.code));
+            }
+        }
+        for (account, amount) in &entry.postings {
+            let account = match format {
+                LedgerFormat::Beancount => beancount_account(account),
+                _ => account.clone(),
+            };
+            let _ = writeln!(
+                out,
+                "    {:<40}  {:>12} {}",
+                account,
+                money(*amount),
+                entry.currency
+            );
+        }
+    }
+    out
+}
+
+/// Write the journal for `range` to `journal_<from>_<to>.<ext>`.
+pub fn export(
+    db: &Database,
+    config: &LedgerConfig,
+    format: LedgerFormat,
+    range: DateRange,
+) -> Result<String, AppError> {
+    let entries = journal(db, config, range)?;
+    let path = format!("journal_{}_{}.{}", range.from, range.to, format.extension());
+    fs::write(&path, render(&entries, format, range))?;
+    Ok(path)
+}
+
+fn cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+fn money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+/// `1900` as `19`, `750` as `7.5`.
+fn format_rate(rate: i64) -> String {
+    let percent = format!("{}.{:02}", rate / 100, (rate % 100).abs());
+    percent
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Beancount only allows letters, digits and dashes in account names, each
+/// part starting with a capital letter or digit.
+fn beancount_account(account: &str) -> String {
+    account
+        .split(':')
+        .map(|part| {
+            let cleaned: String = part
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                .collect();
+            let cleaned = cleaned.trim_matches('-');
+            let mut chars = cleaned.chars();
+            match chars.next() {
+                Some(first) if first.is_alphanumeric() => {
+                    first.to_uppercase().chain(chars).collect()
+                }
+                _ => "X".to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,29 @@
+pub mod api;
+pub mod app;
+pub mod audit;
+pub mod backup;
+pub mod bank;
+pub mod cli;
+pub mod config;
+pub mod credit_note;
+pub mod csv;
+pub mod db;
+pub mod dunning;
+pub mod einvoice;
+pub mod error;
+pub mod expense;
+pub mod facturx;
+pub mod keymap;
+pub mod ledger;
+pub mod mail;
+pub mod models;
+pub mod pdf;
+pub mod project;
+pub mod report;
+pub mod smtp;
+pub mod timesheet;
+pub mod transfer;
+pub mod ubl;
+pub mod ui;
+pub mod utils;
+pub mod webhook;
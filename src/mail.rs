@@ -0,0 +1,293 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Local;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+
+use crate::config::IssuerConfig;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Invoice, SentEmail, is_email};
+use crate::pdf::{self, PdfConfig};
+use crate::smtp;
+
+/// Overrides `password` so it needn't be written to the config file.
+const PASSWORD_VAR: &str = "INVOICE_RS_SMTP_PASSWORD";
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// Unencrypted; only for local test servers.
+    None,
+    /// Upgrade a plain connection, usually on port 587.
+    StartTls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+}
+
+/// Outgoing mail server and sender, from the `[mail]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: Security,
+    pub username: String,
+    password: String,
+    /// Sender address; the issuer name is used as the display name.
+    pub from: String,
+    /// Write `.eml` files to `outbox` instead of sending.
+    pub dry_run: bool,
+    pub outbox: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 587,
+            security: Security::StartTls,
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            dry_run: false,
+            outbox: "outbox".to_string(),
+        }
+    }
+}
+
+impl MailConfig {
+    pub fn password(&self) -> String {
+        std::env::var(PASSWORD_VAR).unwrap_or_else(|_| self.password.clone())
+    }
+}
+
+/// A message with one attachment, ready to be written out as MIME.
+pub struct Email {
+    pub from: String,
+    pub from_name: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub attachment_name: String,
+    pub attachment: Vec<u8>,
+}
+
+impl Email {
+    /// Fill the subject and body from the `<template>_subject.txt` and
+    /// `<template>_body.txt` templates. `ctx` may carry extra values.
+    pub fn compose(
+        invoice: &Invoice,
+        issuer: &IssuerConfig,
+        config: &MailConfig,
+        template: &str,
+        mut ctx: Context,
+        attachment_name: &str,
+        attachment: Vec<u8>,
+    ) -> Result<Self, AppError> {
+        let tera = Tera::new("templates/**/*")?;
+        ctx.insert("invoice", invoice);
+        ctx.insert("issuer", issuer);
+        ctx.insert(
+            "net_total",
+            &format!("{:.2}", invoice.calculate_net_total()),
+        );
+        ctx.insert("paid", &format!("{:.2}", invoice.paid));
+        ctx.insert("balance", &format!("{:.2}", invoice.balance()));
+        let subject = tera.render(&format!("{}_subject.txt", template), &ctx)?;
+        let body = tera.render(&format!("{}_body.txt", template), &ctx)?;
+        Ok(Self {
+            from: config.from.clone(),
+            from_name: issuer.name.clone(),
+            to: invoice.client.email.clone(),
+            // Line breaks would start new headers.
+            subject: subject.split_whitespace().collect::<Vec<_>>().join(" "),
+            body,
+            attachment_name: attachment_name.to_string(),
+            attachment,
+        })
+    }
+
+    /// The message as MIME text with CRLF line endings.
+    pub fn to_mime(&self) -> String {
+        let boundary = format!("invoice-rs-{}", uuid::Uuid::new_v4().simple());
+        let domain = self
+            .from
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        let from = if self.from_name.is_empty() {
+            self.from.clone()
+        } else {
+            format!("{} <{}>", encode_phrase(&self.from_name), self.from)
+        };
+
+        let mut mime = String::new();
+        for (name, value) in [
+            ("From", from.as_str()),
+            ("To", self.to.as_str()),
+            ("Subject", &encode_word(&self.subject)),
+            ("Date", &Local::now().to_rfc2822()),
+            (
+                "Message-ID",
+                &format!("<{}@{}>", uuid::Uuid::new_v4(), domain),
+            ),
+            ("MIME-Version", "1.0"),
+            (
+                "Content-Type",
+                &format!("multipart/mixed; boundary=\"{}\"", boundary),
+            ),
+        ] {
+            mime.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        mime.push_str(&format!(
+            "\r\n--{b}\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             {body}\
+             --{b}\r\n\
+             Content-Type: application/pdf; name=\"{name}\"\r\n\
+             Content-Disposition: attachment; filename=\"{name}\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             {attachment}\
+             --{b}--\r\n",
+            b = boundary,
+            body = base64_lines(self.body.replace('\n', "\r\n").as_bytes()),
+            name = self.attachment_name,
+            attachment = base64_lines(&self.attachment),
+        ));
+        mime
+    }
+}
+
+/// Base64 in lines of 76 characters, as MIME requires.
+fn base64_lines(data: &[u8]) -> String {
+    let encoded = STANDARD.encode(data);
+    let mut out = String::with_capacity(encoded.len() + encoded.len() / 76 * 2 + 2);
+    for chunk in encoded.as_bytes().chunks(76) {
+        out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// RFC 2047 encoded word for header text that isn't plain ASCII.
+fn encode_word(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?utf-8?B?{}?=", STANDARD.encode(text))
+    }
+}
+
+/// A display name, quoted when ASCII and encoded otherwise.
+fn encode_phrase(name: &str) -> String {
+    if name.is_ascii() {
+        format!("\"{}\"", name.replace(['\\', '"'], ""))
+    } else {
+        encode_word(name)
+    }
+}
+
+/// Email the invoice PDF to its client and move a draft invoice to sent. In
+/// dry-run mode the status is left alone.
+pub fn send_invoice(
+    db: &Database,
+    invoice: &Invoice,
+    config: &MailConfig,
+    issuer: &IssuerConfig,
+    pdf_config: &PdfConfig,
+) -> Result<String, AppError> {
+    check(invoice, config)?;
+    let pdf = pdf::invoice_pdf(db, invoice, issuer, pdf_config)?;
+    let hash = pdf::hash(&pdf);
+    let email = Email::compose(
+        invoice,
+        issuer,
+        config,
+        "email",
+        Context::new(),
+        &format!("invoice_{}.pdf", invoice.code),
+        pdf.clone(),
+    )?;
+    let message = deliver(db, invoice, config, &email)?;
+    if !config.dry_run {
+        db.mark_sent(invoice.id)?;
+        db.set_issued_pdf(invoice.id, &hash, &pdf)?;
+    }
+    Ok(message)
+}
+
+/// Make sure the message can be addressed before anything is rendered.
+pub fn check(invoice: &Invoice, config: &MailConfig) -> Result<(), AppError> {
+    if !is_email(&invoice.client.email) {
+        return Err(AppError::Validation(format!(
+            "{} has no valid email address.",
+            invoice.client.name
+        )));
+    }
+    if !config.dry_run && !is_email(&config.from) {
+        return Err(AppError::Config(
+            "set a sender address with 'from' in the [mail] table".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Send `email` about `invoice` and log the attempt. In dry-run mode the
+/// message is written to the outbox as an `.eml` file instead.
+pub fn deliver(
+    db: &Database,
+    invoice: &Invoice,
+    config: &MailConfig,
+    email: &Email,
+) -> Result<String, AppError> {
+    let mime = email.to_mime();
+    let mut log = SentEmail {
+        id: 0,
+        invoice_id: invoice.id,
+        sent_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        recipient: email.to.clone(),
+        subject: email.subject.clone(),
+        status: String::new(),
+        detail: String::new(),
+    };
+
+    let result = if config.dry_run {
+        write_eml(config, invoice, &mime)
+    } else {
+        smtp::send(config, &email.from, &email.to, &mime).map(|_| String::new())
+    };
+    match result {
+        Ok(path) if config.dry_run => {
+            log.status = "dry_run".to_string();
+            log.detail = path.clone();
+            db.log_email(&log)?;
+            Ok(format!("Wrote {}", path))
+        }
+        Ok(_) => {
+            log.status = "sent".to_string();
+            db.log_email(&log)?;
+            Ok(format!("Sent '{}' to {}", email.subject, email.to))
+        }
+        Err(e) => {
+            log.status = "failed".to_string();
+            log.detail = e.to_string();
+            db.log_email(&log)?;
+            Err(e)
+        }
+    }
+}
+
+fn write_eml(config: &MailConfig, invoice: &Invoice, mime: &str) -> Result<String, AppError> {
+    fs::create_dir_all(&config.outbox)?;
+    let name = format!(
+        "{}-invoice_{}.eml",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        invoice.code
+    );
+    let path = Path::new(&config.outbox).join(name);
+    fs::write(&path, mime)?;
+    Ok(path.display().to_string())
+}
@@ -1,27 +1,31 @@
 use color_eyre::Result;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyEventKind},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::io;
 
-mod app;
-mod db;
-mod models;
-mod pdf;
-mod ui;
-mod utils;
-
-use app::{App, Mode};
-use ui::splash::SplashScreen;
+use invoice_rs::app::App;
+use invoice_rs::cli;
+use invoice_rs::ui::{self, splash::SplashScreen};
 
 fn main() -> Result<()> {
+    // Subcommands run without the TUI.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     enable_raw_mode()?;
     color_eyre::install()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     let mut app = App::new();
@@ -30,7 +34,7 @@ fn main() -> Result<()> {
     run_app(&mut terminal, &mut app, &mut splash)?;
 
     disable_raw_mode()?;
-    execute!(io::stdout(), LeaveAlternateScreen)?;
+    execute!(io::stdout(), DisableBracketedPaste, LeaveAlternateScreen)?;
     Ok(())
 }
 
@@ -48,59 +52,19 @@ fn run_app(
             }
         })?;
 
+        app.tick();
+
         if event::poll(std::time::Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                match app.mode {
-                    Mode::Normal => match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => break,
-                        KeyCode::Down => app.next(),
-                        KeyCode::Up => app.previous(),
-                        KeyCode::Right => app.next_tab(),
-                        KeyCode::Left => app.previous_tab(),
-                        KeyCode::Char('n') => app.start_new(),
-                        KeyCode::Char('e') => app.start_edit(),
-                        KeyCode::Char('p') => app.export_pdf(),
-                        _ => {}
-                    },
-                    Mode::Editing => {
-                        let form = app.form.as_mut().unwrap();
-                        match key.code {
-                            KeyCode::Esc => app.cancel_form(),
-                            KeyCode::Enter => app.open_save_modal(),
-                            KeyCode::Tab => form.next_field(),
-                            KeyCode::Backspace => form.backspace(),
-                            KeyCode::Char(c) => form.update_field(c),
-                            _ => {}
-                        }
-                    }
-                    Mode::Modal(modal_type) => match modal_type {
-                        ui::modal::ModalType::Alert => match key.code {
-                            _ => app.close_modals(),
-                        },
-                        ui::modal::ModalType::Confirm => match key.code {
-                            KeyCode::Tab => {
-                                if let Some(modal) = app.modal.as_mut() {
-                                    modal.next_field();
-                                }
-                            }
-                            KeyCode::Esc => app.close_modals(),
-                            KeyCode::Enter => {
-                                if let Some(mut modal) = app.modal.take() {
-                                    modal.press_field(
-                                        Box::new(|app: &mut App| {
-                                            app.close_modals();
-                                        }),
-                                        app,
-                                    );
-                                    app.modal = Some(modal);
-                                }
-                            }
-                            _ => {}
-                        },
-                    },
-                }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                Event::Paste(text) => app.paste(&text),
+                _ => {}
             }
         }
+
+        if app.should_quit {
+            break;
+        }
     }
     Ok(())
 }
@@ -1,12 +1,18 @@
+use crate::config::IssuerConfig;
+use chrono::{Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use strum::{Display, EnumIter, EnumString, FromRepr};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Item {
+    #[serde(default)]
     pub id: u32,
     pub title: String,
     pub price: Option<f64>,
     pub quantity: Option<u32>,
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
     pub remark: Option<String>,
 }
 
@@ -22,9 +28,20 @@ impl Item {
             title: title.to_string(),
             price,
             quantity,
+            tax_rate: None,
             remark,
         }
     }
+
+    /// Price times quantity; a missing price or quantity counts as zero.
+    pub fn amount(&self) -> f64 {
+        self.price.unwrap_or(0.0) * f64::from(self.quantity.unwrap_or(0))
+    }
+}
+
+/// Sum of the item amounts, the total of an invoice without its own.
+pub fn items_total(items: &[Item]) -> f64 {
+    items.iter().map(Item::amount).sum()
 }
 
 impl fmt::Display for Item {
@@ -52,37 +69,34 @@ pub struct Invoice {
     pub tax: f64,
     pub status: String,
     pub date: String,
+    #[serde(default)]
+    pub due_date: String,
+    #[serde(default)]
+    pub paid: f64,
+    #[serde(default)]
+    pub void_reason: String,
+    #[serde(default)]
+    pub project: Option<Project>,
+    /// When the invoice left draft; from then on its content is frozen.
+    #[serde(default)]
+    pub issued_at: String,
+    /// The issuer as it was at issue time.
+    #[serde(default)]
+    pub issuer: Option<IssuerConfig>,
+    /// Hex SHA-256 of the PDF first rendered after issuing.
+    #[serde(default)]
+    pub pdf_hash: String,
+    /// Code of the invoice this one credits, for credit notes.
+    #[serde(default)]
+    pub credit_note_for: String,
+    /// Net amount of the issued credit notes against this invoice.
+    #[serde(default)]
+    pub credited: f64,
     pub items: Vec<Item>,
 }
 
-impl Invoice {
-    pub fn new(
-        id: u32,
-        code: &str,
-        client: &Client,
-        currency: &str,
-        total: f64,
-        discount: f64,
-        tax: f64,
-        status: &str,
-        date: &str,
-        items: Vec<Item>,
-    ) -> Self {
-        Self {
-            id,
-            code: code.to_string(),
-            client: client.to_owned(),
-            currency: currency.to_string(),
-            total,
-            discount,
-            tax,
-            status: status.to_string(),
-            date: date.to_string(),
-            items,
-        }
-    }
-
-    pub fn default() -> Self {
+impl Default for Invoice {
+    fn default() -> Self {
         Self {
             id: 0,
             code: "empty_invoice".to_string(),
@@ -93,22 +107,250 @@ impl Invoice {
             tax: 0.0,
             status: String::new(),
             date: String::new(),
+            due_date: String::new(),
+            paid: 0.0,
+            void_reason: String::new(),
+            project: None,
+            issued_at: String::new(),
+            issuer: None,
+            pdf_hash: String::new(),
+            credit_note_for: String::new(),
+            credited: 0.0,
             items: Vec::new(),
         }
     }
+}
 
+impl Invoice {
     pub fn calculate_net_total(&self) -> f64 {
         self.total - self.discount + self.tax
     }
+
+    pub fn balance(&self) -> f64 {
+        self.calculate_net_total() - self.paid - self.credited
+    }
+
+    pub fn is_issued(&self) -> bool {
+        !self.issued_at.is_empty()
+    }
+
+    pub fn is_credit_note(&self) -> bool {
+        !self.credit_note_for.is_empty()
+    }
+
+    /// The same invoice with every amount's sign flipped.
+    pub fn negated(&self) -> Self {
+        let mut invoice = self.clone();
+        invoice.total = -self.total;
+        invoice.discount = -self.discount;
+        invoice.tax = -self.tax;
+        invoice.paid = -self.paid;
+        for item in &mut invoice.items {
+            item.price = item.price.map(|price| -price);
+        }
+        invoice
+    }
+
+    /// A draft credit note `code` cancelling this invoice in full.
+    pub fn credit_note(&self, code: &str, date: &str) -> Self {
+        let mut note = self.negated();
+        note.id = 0;
+        note.code = code.to_string();
+        note.status = "draft".to_string();
+        note.date = date.to_string();
+        note.due_date = date.to_string();
+        note.paid = 0.0;
+        note.void_reason = String::new();
+        note.issued_at = String::new();
+        note.issuer = None;
+        note.pdf_hash = String::new();
+        note.credit_note_for = self.code.clone();
+        note.credited = 0.0;
+        note
+    }
+
+    /// Check the invoice before it is stored. `code_taken` reports whether
+    /// another invoice already uses a code.
+    pub fn validate(&self, code_taken: impl Fn(&str) -> bool) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        if self.code.trim().is_empty() {
+            errors.add("code", "Code is required");
+        } else if code_taken(&self.code) {
+            errors.add("code", "Another invoice already uses this code");
+        }
+        if self.client.email.trim().is_empty() {
+            errors.add("client", "Client is required");
+        }
+        if self
+            .project
+            .as_ref()
+            .is_some_and(|project| project.client_id != self.client.id)
+        {
+            errors.add("project", "Project belongs to another client");
+        }
+        if !is_currency_code(&self.currency) {
+            errors.add("currency", "Unknown currency code");
+        }
+        // Credit notes carry their amounts negated.
+        let sign = if self.is_credit_note() { -1.0 } else { 1.0 };
+        for (field, amount) in [
+            ("total", self.total),
+            ("discount", self.discount),
+            ("tax", self.tax),
+            ("paid", self.paid),
+        ] {
+            if !amount.is_finite() || amount * sign < 0.0 {
+                errors.add(field, sign_rule(sign));
+            }
+        }
+        if self.discount * sign > self.total * sign {
+            errors.add("discount", "Discount can't exceed the total");
+        }
+
+        let date = check_date(&mut errors, "date", &self.date, true);
+        let due_date = check_date(&mut errors, "due_date", &self.due_date, false);
+        if let (Some(date), Some(due_date)) = (date, due_date)
+            && due_date < date
+        {
+            errors.add("due_date", "Due date is before the invoice date");
+        }
+
+        for item in &self.items {
+            if item.title.trim().is_empty() {
+                errors.add("items", "Every item needs a title");
+            }
+            if item
+                .price
+                .is_some_and(|price| !price.is_finite() || price * sign < 0.0)
+            {
+                errors.add(
+                    "items",
+                    format!(
+                        "Price of '{}' must be {}",
+                        item.title,
+                        sign_rule(sign).to_lowercase()
+                    ),
+                );
+            }
+        }
+
+        errors.into_result()
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Default, Clone, Copy, PartialEq, Display, EnumString, FromRepr, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum SortColumn {
+    Code,
+    #[default]
+    Date,
+    DueDate,
+    Client,
+    NetTotal,
+    Balance,
+    Status,
+}
+
+impl SortColumn {
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Code => "Code",
+            Self::Date => "Date",
+            Self::DueDate => "Due",
+            Self::Client => "Client",
+            Self::NetTotal => "Net Total",
+            Self::Balance => "Balance",
+            Self::Status => "Status",
+        }
+    }
+
+    /// Get the next column, wrapping around to the first one.
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or(Self::Code)
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Asc => Self::Desc,
+            Self::Desc => Self::Asc,
+        }
+    }
+
+    pub fn arrow(self) -> &'static str {
+        match self {
+            Self::Asc => "▲",
+            Self::Desc => "▼",
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+pub struct InvoiceSort {
+    pub column: SortColumn,
+    pub direction: SortDirection,
+}
+
+impl InvoiceSort {
+    /// The same column in the opposite direction, used to page backwards.
+    pub fn reversed(self) -> Self {
+        Self {
+            column: self.column,
+            direction: self.direction.toggle(),
+        }
+    }
+}
+
+impl fmt::Display for InvoiceSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.column, self.direction)
+    }
+}
+
+impl std::str::FromStr for InvoiceSort {
+    type Err = strum::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, direction) = s.split_once(':').unwrap_or((s, "desc"));
+        Ok(Self {
+            column: column.parse()?,
+            direction: direction.parse()?,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Client {
+    /// Assigned by the database; may be left out when creating a client.
+    #[serde(default)]
     pub id: u32,
     pub name: String,
+    #[serde(default)]
     pub phone: String,
     pub email: String,
+    #[serde(default)]
     pub address: String,
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`.
+    #[serde(default)]
+    pub country: String,
+    #[serde(default)]
+    pub vat_id: String,
+    /// Electronic address as `scheme:id`, e.g. `0088:5790000435951`.
+    #[serde(default)]
+    pub endpoint: String,
+    /// Reference the client asks to be quoted on invoices.
+    #[serde(default)]
+    pub reference: String,
 }
 
 impl Client {
@@ -119,16 +361,432 @@ impl Client {
             phone: phone.to_string(),
             email: email.to_string(),
             address: address.to_string(),
+            country: String::new(),
+            vat_id: String::new(),
+            endpoint: String::new(),
+            reference: String::new(),
         }
     }
 
-    pub fn default() -> Self {
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        if self.name.trim().is_empty() {
+            errors.add("name", "Name is required");
+        }
+        if self.email.trim().is_empty() {
+            errors.add("email", "Email is required");
+        } else if !is_email(&self.email) {
+            errors.add("email", "Not a valid email address");
+        }
+        if !self.phone.trim().is_empty() && !is_phone(&self.phone) {
+            errors.add("phone", "Not a valid phone number");
+        }
+        if !self.country.is_empty() && !is_country_code(&self.country) {
+            errors.add("country", "Use a two-letter country code, e.g. DE");
+        }
+        if !self.endpoint.is_empty() && !is_endpoint(&self.endpoint) {
+            errors.add("endpoint", "Use scheme:id, e.g. 0088:5790000435951");
+        }
+
+        errors.into_result()
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
         Self {
             id: 0,
             name: "empty_client".to_string(),
             phone: String::new(),
             email: String::new(),
             address: String::new(),
+            country: String::new(),
+            vat_id: String::new(),
+            endpoint: String::new(),
+            reference: String::new(),
+        }
+    }
+}
+
+/// An engagement for a client, with its own purchase order number, budget
+/// and hourly rate. `status` is `active` or `closed`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: u32,
+    pub client_id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub po_number: String,
+    #[serde(default)]
+    pub budget: f64,
+    #[serde(default)]
+    pub rate: f64,
+    #[serde(default = "active")]
+    pub status: String,
+}
+
+fn active() -> String {
+    "active".to_string()
+}
+
+impl Project {
+    pub fn is_active(&self) -> bool {
+        self.status == "active"
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub id: u32,
+    pub invoice_id: u32,
+    pub amount: f64,
+    pub date: String,
+    #[serde(default)]
+    pub reference: String,
+}
+
+/// One attempt at emailing an invoice: `status` is `sent`, `dry_run` or
+/// `failed`, and `detail` holds the .eml path or the error.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SentEmail {
+    pub id: u32,
+    pub invoice_id: u32,
+    pub sent_at: String,
+    pub recipient: String,
+    pub subject: String,
+    pub status: String,
+    pub detail: String,
+}
+
+/// A dunning level that was sent, or skipped, for an invoice. `level`
+/// counts from 1; `fees` is what was added to the invoice with it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: u32,
+    pub invoice_id: u32,
+    pub level: u32,
+    pub name: String,
+    pub date: String,
+    pub fees: f64,
+    pub status: String,
+}
+
+/// A credit from an imported bank statement. `bank_id` is the bank's own
+/// reference, used to skip transactions imported before.
+#[derive(Clone, Default)]
+pub struct BankTransaction {
+    pub id: u32,
+    pub bank_id: String,
+    pub date: String,
+    pub amount: f64,
+    pub currency: String,
+    /// The payer, as the bank reports them.
+    pub name: String,
+    pub reference: String,
+}
+
+/// Time worked for a client. A running timer has `started_at` set and no
+/// minutes yet; `invoice_id` is set once the time has been billed.
+#[derive(Clone)]
+pub struct TimeEntry {
+    pub id: u32,
+    pub client: Client,
+    /// Project name, which may or may not be one of the client's projects.
+    pub project: String,
+    pub project_id: Option<u32>,
+    pub description: String,
+    pub date: String,
+    pub minutes: u32,
+    /// Hourly rate.
+    pub rate: f64,
+    pub started_at: Option<String>,
+    pub invoice_id: Option<u32>,
+}
+
+impl TimeEntry {
+    pub fn hours(&self) -> f64 {
+        f64::from(self.minutes) / 60.0
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.hours() * self.rate
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+}
+
+/// Money spent on behalf of the business. Billable expenses belong to a
+/// client and are passed on to them; `invoice_id` is set once they have been.
+#[derive(Clone)]
+pub struct Expense {
+    pub id: u32,
+    pub date: String,
+    pub vendor: String,
+    pub amount: f64,
+    pub currency: String,
+    pub category: String,
+    /// Path to the scanned receipt, empty when there is none.
+    pub receipt: String,
+    pub client: Option<Client>,
+    pub project_id: Option<u32>,
+    pub billable: bool,
+    pub invoice_id: Option<u32>,
+}
+
+impl Expense {
+    pub fn is_billed(&self) -> bool {
+        self.invoice_id.is_some()
+    }
+}
+
+/// Something that happened to an invoice, sent to the configured webhooks.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString)]
+pub enum EventKind {
+    #[strum(serialize = "invoice.created")]
+    InvoiceCreated,
+    #[strum(serialize = "invoice.updated")]
+    InvoiceUpdated,
+    #[strum(serialize = "invoice.sent")]
+    InvoiceSent,
+    #[strum(serialize = "payment.recorded")]
+    PaymentRecorded,
+    #[strum(serialize = "invoice.overdue")]
+    InvoiceOverdue,
+}
+
+/// The kinds of record the audit log follows.
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditEntity {
+    Invoice,
+    Client,
+    Payment,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Status,
+}
+
+/// One change in the append-only audit log. `changes` is a JSON object
+/// mapping each changed field to its `[before, after]` values, and `hash`
+/// covers the entry and the `prev_hash` of the one before it.
+#[derive(Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: u32,
+    pub at: String,
+    pub actor: String,
+    pub entity: String,
+    pub entity_id: u32,
+    /// The invoice the change belongs to, if any.
+    pub invoice_id: Option<u32>,
+    pub action: String,
+    pub changes: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// An event in the outbox. `payload` is the JSON `data` object sent with it.
+#[derive(Clone)]
+pub struct WebhookEvent {
+    pub id: u32,
+    pub kind: String,
+    pub invoice_id: u32,
+    pub payload: String,
+    pub created_at: String,
+}
+
+/// One endpoint's delivery of an event: `status` is `pending`, `delivered`
+/// or `failed`, `next_attempt_at` is in Unix seconds.
+#[derive(Clone)]
+pub struct WebhookDelivery {
+    pub id: u32,
+    pub event: WebhookEvent,
+    pub url: String,
+    pub attempts: u32,
+    pub status: String,
+    pub response_code: Option<u16>,
+    pub error: String,
+    pub next_attempt_at: i64,
+    pub updated_at: String,
+}
+
+#[derive(Default, Clone)]
+pub struct InvoiceFilter {
+    pub status: Option<String>,
+    pub client_id: Option<u32>,
+    pub search: Option<String>,
+}
+
+/// Amounts invoiced and collected in one calendar month.
+#[derive(Debug, Clone, Default)]
+pub struct MonthlyTotals {
+    /// `YYYY-MM`.
+    pub month: String,
+    pub invoiced: f64,
+    pub collected: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientRevenue {
+    pub name: String,
+    pub revenue: f64,
+}
+
+/// Figures shown on the dashboard tab.
+#[derive(Debug, Clone, Default)]
+pub struct DashboardStats {
+    /// Oldest month first.
+    pub months: Vec<MonthlyTotals>,
+    pub receivables: f64,
+    pub overdue: f64,
+    pub overdue_count: usize,
+    pub top_clients: Vec<ClientRevenue>,
+    /// `None` until an invoice has been paid.
+    pub avg_days_to_pay: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// Per-field problems found by `Invoice::validate` or `Client::validate`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    pub fn add(&mut self, field: &'static str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field,
+            message: message.into(),
+        });
+    }
+
+    /// The first message for `field`, if any.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|error| error.field == field)
+            .map(|error| error.message.as_str())
+    }
+
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self
+            .0
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+const MIN_YEAR: i32 = 2000;
+const MAX_YEAR: i32 = 2100;
+
+// ISO 4217 codes in current use.
+const CURRENCY_CODES: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+pub fn is_currency_code(code: &str) -> bool {
+    CURRENCY_CODES.contains(&code)
+}
+
+pub fn is_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+pub fn is_phone(phone: &str) -> bool {
+    let digits = phone.chars().filter(char::is_ascii_digit).count();
+    (5..=15).contains(&digits)
+        && phone
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-().".contains(c))
+}
+
+pub fn is_country_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// An electronic address such as `0088:5790000435951`: a four digit scheme
+/// from the EAS code list and an identifier.
+pub fn is_endpoint(endpoint: &str) -> bool {
+    endpoint.split_once(':').is_some_and(|(scheme, id)| {
+        scheme.len() == 4 && scheme.chars().all(|c| c.is_ascii_digit()) && !id.trim().is_empty()
+    })
+}
+
+fn sign_rule(sign: f64) -> &'static str {
+    if sign < 0.0 {
+        "Must be zero or less"
+    } else {
+        "Must be zero or more"
+    }
+}
+
+/// Parse a `YYYY-MM-DD` date and check that it falls in a sensible range.
+fn check_date(
+    errors: &mut ValidationErrors,
+    field: &'static str,
+    value: &str,
+    required: bool,
+) -> Option<NaiveDate> {
+    if value.trim().is_empty() {
+        if required {
+            errors.add(field, "Date is required");
+        }
+        return None;
+    }
+    match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        Ok(date) if (MIN_YEAR..=MAX_YEAR).contains(&date.year()) => Some(date),
+        Ok(_) => {
+            errors.add(
+                field,
+                format!("Date must be between {} and {}", MIN_YEAR, MAX_YEAR),
+            );
+            None
+        }
+        Err(_) => {
+            errors.add(field, "Use the YYYY-MM-DD format");
+            None
         }
     }
 }
@@ -1,49 +1,222 @@
-use std::error::Error;
 use std::fs;
+use std::io::Write;
 
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use headless_chrome::Browser;
+use lopdf::{Document, Object, StringFormat, dictionary};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tera::{Context, Tera};
 
+use crate::config::IssuerConfig;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::facturx::{self, Profile, pdf_date, pdf_error};
 use crate::models::Invoice;
+use crate::report::Report;
 
-pub fn generate_invoice_pdf(invoice: &Invoice) -> Result<(), Box<dyn Error>> {
-    let browser = Browser::default()?;
+/// Turns rendered HTML into PDF bytes.
+pub type Printer = fn(&str) -> Result<Vec<u8>, AppError>;
 
-    let tab = browser.new_tab()?;
+/// PDF output settings, from the `[pdf]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct PdfConfig {
+    /// Embed Factur-X XML at this profile, making the PDF a PDF/A-3.
+    pub facturx: Option<Profile>,
+    /// Headless Chrome unless replaced, e.g. in tests.
+    #[serde(skip)]
+    pub printer: Printer,
+}
 
-    let html_path = generate_invoice_html(&invoice)?;
+impl Default for PdfConfig {
+    fn default() -> Self {
+        Self {
+            facturx: None,
+            printer: print_html,
+        }
+    }
+}
 
-    // Navigate to the local HTML file
-    let file_path = format!(
-        "file://{}/{}",
-        std::env::current_dir()?.display(),
-        html_path
-    );
-    tab.navigate_to(&file_path)?;
+/// Render the invoice, write it to `invoice_<code>.pdf` and return the path.
+pub fn generate_invoice_pdf(
+    db: &Database,
+    invoice: &Invoice,
+    issuer: &IssuerConfig,
+    config: &PdfConfig,
+) -> Result<String, AppError> {
+    let pdf = invoice_pdf(db, invoice, issuer, config)?;
+    let pdf_path = format!("invoice_{}.pdf", invoice.code);
+    fs::write(&pdf_path, pdf)?;
+    Ok(pdf_path)
+}
+
+/// Render the invoice to PDF bytes. With a Factur-X profile the XML is
+/// built and checked first, then embedded in the printed PDF.
+///
+/// The first PDF rendered for an issued invoice is kept, with its hash,
+/// and that same document is returned from then on; it isn't printed again.
+pub fn invoice_pdf(
+    db: &Database,
+    invoice: &Invoice,
+    issuer: &IssuerConfig,
+    config: &PdfConfig,
+) -> Result<Vec<u8>, AppError> {
+    if let Some(pdf) = issued_pdf(db, invoice)? {
+        return Ok(pdf);
+    }
+    let pdf = render_invoice_pdf(invoice, issuer, config)?;
+    keep_issued(db, invoice, pdf)
+}
 
-    // Take pdf of the entire browser window
-    let pdf = tab.wait_until_navigated()?.print_to_pdf(None)?;
-    // Save the pdf to disc
-    std::fs::write(format!("invoice_{}.pdf", invoice.code), pdf)?;
+/// The PDF kept for an issued invoice, checked against its stored hash.
+pub fn issued_pdf(db: &Database, invoice: &Invoice) -> Result<Option<Vec<u8>>, AppError> {
+    if !invoice.is_issued() {
+        return Ok(None);
+    }
+    match db.get_issued_pdf(invoice.id)? {
+        Some(pdf) if hash(&pdf) != invoice.pdf_hash => Err(AppError::Pdf(format!(
+            "the PDF kept for invoice {} doesn't match its SHA-256 {}",
+            invoice.code, invoice.pdf_hash
+        ))),
+        pdf => Ok(pdf),
+    }
+}
 
-    fs::remove_file(&html_path)?;
+/// Keep `pdf` as the document of an issued invoice that has none yet, and
+/// return the one kept. Invoices issued before PDFs were kept only have a
+/// hash, which a fresh render has to match.
+pub fn keep_issued(db: &Database, invoice: &Invoice, pdf: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    if !invoice.is_issued() {
+        return Ok(pdf);
+    }
+    let hash = hash(&pdf);
+    if !invoice.pdf_hash.is_empty() && hash != invoice.pdf_hash {
+        return Err(AppError::Pdf(format!(
+            "invoice {} no longer renders to the PDF it was issued with \
+             (SHA-256 {}); has a template changed?",
+            invoice.code, invoice.pdf_hash
+        )));
+    }
+    db.set_issued_pdf(invoice.id, &hash, &pdf)?;
+    // Another render may have been kept first.
+    Ok(db.get_issued_pdf(invoice.id)?.unwrap_or(pdf))
+}
 
-    Ok(())
+/// Print the invoice without touching the database; `invoice_pdf` does
+/// the same and also keeps the result for an issued one.
+pub fn render_invoice_pdf(
+    invoice: &Invoice,
+    issuer: &IssuerConfig,
+    config: &PdfConfig,
+) -> Result<Vec<u8>, AppError> {
+    // Payments come after the invoice and aren't part of its document.
+    let invoice = &Invoice {
+        paid: 0.0,
+        ..invoice.clone()
+    };
+    let date = NaiveDate::parse_from_str(&invoice.date, "%Y-%m-%d")
+        .unwrap_or_default()
+        .and_time(NaiveTime::MIN)
+        .and_utc();
+    let xml = config
+        .facturx
+        .map(|profile| facturx::render(invoice, issuer, profile))
+        .transpose()?;
+    let mut pdf = (config.printer)(&render_invoice(invoice)?)?;
+    if let (Some(profile), Some(xml)) = (config.facturx, xml) {
+        let title = format!("Invoice {}", invoice.code);
+        pdf = facturx::embed(&pdf, &xml, profile, &title, date)?;
+    }
+    normalize(&pdf, date)
 }
 
-fn render_invoice(invoice: &Invoice) -> tera::Result<String> {
+/// Pin what changes between two prints of the same document, the dates in
+/// its info dictionary and its file ID, so that it comes out byte for byte
+/// the same. The ID is derived from the rest of the file.
+pub fn normalize(pdf: &[u8], date: DateTime<Utc>) -> Result<Vec<u8>, AppError> {
+    let mut doc = Document::load_mem(pdf).map_err(pdf_error)?;
+    let date = Object::string_literal(pdf_date(&date));
+    let info = doc.trailer.get(b"Info").and_then(Object::as_reference).ok();
+    match info.and_then(|id| doc.get_dictionary_mut(id).ok()) {
+        Some(info) => {
+            info.set("CreationDate", date.clone());
+            info.set("ModDate", date);
+        }
+        None => {
+            let info = doc.add_object(dictionary! {
+                "CreationDate" => date.clone(),
+                "ModDate" => date,
+            });
+            doc.trailer.set("Info", info);
+        }
+    }
+    doc.trailer.remove(b"ID");
+    let mut out = Vec::new();
+    doc.save_to(&mut out)?;
+
+    let id = Object::String(
+        Sha256::digest(&out)[..16].to_vec(),
+        StringFormat::Hexadecimal,
+    );
+    doc.trailer.set("ID", vec![id.clone(), id]);
+    out.clear();
+    doc.save_to(&mut out)?;
+    Ok(out)
+}
+
+/// Hex SHA-256 of a PDF, as stored for issued invoices.
+pub fn hash(pdf: &[u8]) -> String {
+    Sha256::digest(pdf)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Render a report through the `report.html` template and return the PDF path.
+pub fn generate_report_pdf(report: &Report) -> Result<String, AppError> {
     let tera = Tera::new("templates/**/*")?;
     let mut ctx = Context::new();
-    ctx.insert("invoice", invoice);
-    tera.render("invoice.html", &ctx)
+    ctx.insert("report", report);
+    let html = tera.render("report.html", &ctx)?;
+    write_pdf(&html, &report.file_stem())
 }
 
-pub fn generate_invoice_html(invoice: &Invoice) -> Result<String, Box<dyn std::error::Error>> {
-    let html = render_invoice(&invoice)?;
+/// Print rendered `html` to `<stem>.pdf` and return the path.
+pub fn write_pdf(html: &str, stem: &str) -> Result<String, AppError> {
+    let pdf_path = format!("{stem}.pdf");
+    fs::write(&pdf_path, print_html(html)?)?;
+    Ok(pdf_path)
+}
+
+/// Print `html` through headless Chrome, from a temporary file that is
+/// removed afterwards.
+pub fn print_html(html: &str) -> Result<Vec<u8>, AppError> {
+    let mut file = tempfile::Builder::new()
+        .prefix("invoice-rs-")
+        .suffix(".html")
+        .tempfile()?;
+    file.write_all(html.as_bytes())?;
+    file.flush()?;
+
+    let browser = Browser::default().map_err(|e| AppError::Pdf(e.to_string()))?;
 
-    let html_path = format!("invoice_{}.html", invoice.code);
+    let tab = browser
+        .new_tab()
+        .map_err(|e| AppError::Pdf(e.to_string()))?;
 
-    fs::write(&html_path, &html)?;
+    // Navigate to the local HTML file
+    let file_path = format!("file://{}", file.path().display());
+    tab.navigate_to(&file_path)
+        .and_then(|tab| tab.wait_until_navigated())
+        // Take pdf of the entire browser window
+        .and_then(|tab| tab.print_to_pdf(None))
+        .map_err(|e| AppError::Pdf(e.to_string()))
+}
 
-    Ok(html_path)
+fn render_invoice(invoice: &Invoice) -> tera::Result<String> {
+    let tera = Tera::new("templates/**/*")?;
+    let mut ctx = Context::new();
+    ctx.insert("invoice", invoice);
+    tera.render("invoice.html", &ctx)
 }
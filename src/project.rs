@@ -0,0 +1,123 @@
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Client, Project, ValidationErrors};
+
+/// A project with what it has used so far, in total and per month.
+pub struct ProjectBudget {
+    pub project: Project,
+    pub used: f64,
+    /// `(YYYY-MM, amount)` pairs, oldest first.
+    pub by_month: Vec<(String, f64)>,
+}
+
+impl ProjectBudget {
+    pub fn remaining(&self) -> f64 {
+        self.project.budget - self.used
+    }
+
+    /// Share of the budget used, from 0 up; 0 for projects without a budget.
+    pub fn burn(&self) -> f64 {
+        if self.project.budget > 0.0 {
+            self.used / self.project.budget
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Every project with its usage.
+pub fn budgets(db: &Database) -> Result<Vec<ProjectBudget>, AppError> {
+    let usage = db.project_usage()?;
+    Ok(db
+        .get_projects()?
+        .into_iter()
+        .map(|project| {
+            let by_month: Vec<(String, f64)> = usage
+                .iter()
+                .filter(|(id, _, _)| *id == project.id)
+                .map(|(_, month, amount)| (month.clone(), *amount))
+                .collect();
+            ProjectBudget {
+                used: by_month.iter().map(|(_, amount)| amount).sum(),
+                project,
+                by_month,
+            }
+        })
+        .collect())
+}
+
+/// Names of the fields `validate_project` takes and reports problems for.
+pub const PROJECT_FIELDS: [&str; 4] = ["name", "po_number", "budget", "rate"];
+
+/// Build a project for `client` from `name[, PO number, budget, rate]`.
+pub fn entry(fields: &[&str], client: &Client) -> Result<Project, AppError> {
+    if !(1..=PROJECT_FIELDS.len()).contains(&fields.len()) {
+        return Err(AppError::Validation(
+            "Give a name and optionally PO number; budget; rate.".to_string(),
+        ));
+    }
+    let mut values = [""; 4];
+    values[..fields.len()].copy_from_slice(fields);
+    Ok(validate_project(values, client)?)
+}
+
+/// Build a project for `client` from the values of `PROJECT_FIELDS`, with a
+/// problem per field. Only the name is required.
+pub fn validate_project(values: [&str; 4], client: &Client) -> Result<Project, ValidationErrors> {
+    let [name, po_number, budget, rate] = values.map(str::trim);
+    let mut errors = ValidationErrors::default();
+
+    if name.is_empty() {
+        errors.add("name", "Give the project a name.");
+    }
+    let mut amount = |field: &'static str, text: &str| match text {
+        "" => 0.0,
+        text => match text.parse::<f64>() {
+            Ok(amount) if amount >= 0.0 => amount,
+            _ => {
+                errors.add(field, format!("'{}' is not a valid {}.", text, field));
+                0.0
+            }
+        },
+    };
+    let budget = amount("budget", budget);
+    let rate = amount("rate", rate);
+
+    errors.into_result()?;
+    Ok(Project {
+        id: 0,
+        client_id: client.id,
+        name: name.to_string(),
+        po_number: po_number.to_string(),
+        budget,
+        rate,
+        status: "active".to_string(),
+    })
+}
+
+/// The client's active project named `name`, ignoring case.
+pub fn find_project<'a>(
+    projects: &'a [Project],
+    client_id: u32,
+    name: &str,
+) -> Option<&'a Project> {
+    let name = name.trim().to_lowercase();
+    projects.iter().find(|project| {
+        project.client_id == client_id && project.is_active() && project.name.to_lowercase() == name
+    })
+}
+
+/// Store a new project, reporting a duplicate name as a validation error.
+pub fn add(db: &Database, project: &Project) -> Result<u32, AppError> {
+    db.add_project(project).map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(error, _)
+            if error.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            AppError::Validation(format!(
+                "This client already has a project '{}'.",
+                project.name
+            ))
+        }
+        e => e.into(),
+    })
+}
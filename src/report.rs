@@ -0,0 +1,210 @@
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rusqlite::types::Value;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use strum::{Display, EnumIter, EnumString, FromRepr};
+
+use crate::csv;
+use crate::db::Database;
+use crate::error::AppError;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Display, EnumString, FromRepr, EnumIter)]
+#[strum(serialize_all = "snake_case")]
+pub enum ReportKind {
+    #[default]
+    SalesByPeriod,
+    SalesByClient,
+    SalesByItem,
+    TaxByRate,
+    ReceivablesAging,
+    PaymentsJournal,
+    ProfitByPeriod,
+}
+
+impl ReportKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::SalesByPeriod => "Sales by Period",
+            Self::SalesByClient => "Sales by Client",
+            Self::SalesByItem => "Sales by Item",
+            Self::TaxByRate => "Tax Collected by Rate",
+            Self::ReceivablesAging => "Receivables Aging",
+            Self::PaymentsJournal => "Payments Received",
+            Self::ProfitByPeriod => "Profit by Period",
+        }
+    }
+
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            Self::SalesByPeriod => &["Month", "Invoices", "Subtotal", "Tax", "Total"],
+            Self::SalesByClient => &["Client", "Invoices", "Subtotal", "Tax", "Total", "Paid"],
+            Self::SalesByItem => &["Item", "Quantity", "Amount"],
+            Self::TaxByRate => &["Rate", "Invoices", "Taxable", "Tax"],
+            Self::ReceivablesAging => &[
+                "Client", "Current", "1-30", "31-60", "61-90", "90+", "Total",
+            ],
+            Self::PaymentsJournal => &["Date", "Invoice", "Client", "Reference", "Amount"],
+            Self::ProfitByPeriod => &["Month", "Revenue", "Expenses", "Profit"],
+        }
+    }
+
+    /// Cycle forward through the reports, wrapping at the end.
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or(Self::SalesByPeriod)
+    }
+
+    pub fn previous(self) -> Self {
+        match (self as usize).checked_sub(1) {
+            Some(index) => Self::from_repr(index).unwrap_or(self),
+            None => Self::ProfitByPeriod,
+        }
+    }
+}
+
+/// Inclusive range of dates a report covers, written `2025-01-01..2025-03-31`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl Default for DateRange {
+    /// The current quarter.
+    fn default() -> Self {
+        let today = Local::now().date_naive();
+        let first_month = (today.month0() / 3) * 3 + 1;
+        let from = NaiveDate::from_ymd_opt(today.year(), first_month, 1).unwrap_or(today);
+        let to = from
+            .checked_add_months(Months::new(3))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(today);
+        Self { from, to }
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.from, self.to)
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            AppError::Validation(format!(
+                "'{}' is not a range like 2025-01-01..2025-03-31",
+                s
+            ))
+        };
+        let (from, to) = s.trim().split_once("..").ok_or_else(invalid)?;
+        let from = NaiveDate::parse_from_str(from.trim(), "%Y-%m-%d").map_err(|_| invalid())?;
+        let to = NaiveDate::parse_from_str(to.trim(), "%Y-%m-%d").map_err(|_| invalid())?;
+        if from > to {
+            return Err(AppError::Validation(
+                "The report range ends before it starts.".to_string(),
+            ));
+        }
+        Ok(Self { from, to })
+    }
+}
+
+/// A generated report, formatted for display and export.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    #[serde(serialize_with = "serialize_kind")]
+    pub kind: ReportKind,
+    pub title: &'static str,
+    pub range: DateRange,
+    pub columns: &'static [&'static str],
+    pub rows: Vec<Vec<String>>,
+    /// Sums of the numeric columns, blank elsewhere.
+    pub totals: Vec<String>,
+    pub generated: String,
+}
+
+fn serialize_kind<S: serde::Serializer>(kind: &ReportKind, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(kind)
+}
+
+impl Report {
+    pub fn generate(db: &Database, kind: ReportKind, range: DateRange) -> Result<Self, AppError> {
+        let values = db.report(kind, &range.from.to_string(), &range.to.to_string())?;
+        let columns = kind.columns();
+
+        let mut sums: Vec<Option<f64>> = vec![None; columns.len()];
+        let mut integer = vec![true; columns.len()];
+        for row in &values {
+            for (index, value) in row.iter().enumerate() {
+                let number = match value {
+                    Value::Integer(n) => *n as f64,
+                    Value::Real(n) => {
+                        integer[index] = false;
+                        *n
+                    }
+                    _ => continue,
+                };
+                *sums[index].get_or_insert(0.0) += number;
+            }
+        }
+        let totals = sums
+            .iter()
+            .zip(&integer)
+            .enumerate()
+            .map(|(index, (sum, integer))| match sum {
+                _ if index == 0 => "Total".to_string(),
+                Some(sum) if *integer => format!("{sum:.0}"),
+                Some(sum) => format!("{sum:.2}"),
+                None => String::new(),
+            })
+            .collect();
+
+        Ok(Self {
+            kind,
+            title: kind.title(),
+            range,
+            columns,
+            rows: values
+                .iter()
+                .map(|row| row.iter().map(format_value).collect())
+                .collect(),
+            totals,
+            generated: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        })
+    }
+
+    /// File name without extension used for exports.
+    pub fn file_stem(&self) -> String {
+        format!("report_{}_{}_{}", self.kind, self.range.from, self.range.to)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        csv::write_record(&mut out, self.columns);
+        for row in &self.rows {
+            csv::write_record(&mut out, row);
+        }
+        csv::write_record(&mut out, &self.totals);
+        out
+    }
+
+    /// Write the report as CSV and return the file path.
+    pub fn export_csv(&self) -> Result<String, AppError> {
+        let path = format!("{}.csv", self.file_stem());
+        fs::write(&path, self.to_csv())?;
+        Ok(path)
+    }
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(n) => n.to_string(),
+        Value::Real(n) => format!("{n:.2}"),
+        Value::Text(s) => s.clone(),
+        Value::Blob(_) => String::new(),
+    }
+}
@@ -0,0 +1,224 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::mail::{MailConfig, Security};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Plain TCP, or TCP wrapped in TLS from the start or after STARTTLS.
+enum Connection {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Session {
+    conn: BufReader<Connection>,
+}
+
+impl Session {
+    /// Read a reply, which may span several `250-` lines, and check its code
+    /// is in the same class as `expected`. Returns the text of each line.
+    fn reply(&mut self, expected: u16) -> Result<Vec<String>, AppError> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.conn.read_line(&mut line).map_err(mail_error)? == 0 {
+                return Err(AppError::Mail("connection closed by server".to_string()));
+            }
+            let line = line.trim_end();
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| AppError::Mail(format!("unexpected reply '{}'", line)))?;
+            lines.push(line.get(4..).unwrap_or("").to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                if code / 100 != expected / 100 {
+                    return Err(AppError::Mail(format!(
+                        "server said {} {}",
+                        code,
+                        lines.join(" ")
+                    )));
+                }
+                return Ok(lines);
+            }
+        }
+    }
+
+    fn command(&mut self, command: &str, expected: u16) -> Result<Vec<String>, AppError> {
+        let conn = self.conn.get_mut();
+        conn.write_all(command.as_bytes()).map_err(mail_error)?;
+        conn.write_all(b"\r\n").map_err(mail_error)?;
+        conn.flush().map_err(mail_error)?;
+        self.reply(expected)
+    }
+
+    /// Send EHLO and return the extensions the server offers.
+    fn ehlo(&mut self, name: &str) -> Result<Vec<String>, AppError> {
+        let mut lines = self.command(&format!("EHLO {}", name), 250)?;
+        lines.remove(0);
+        Ok(lines)
+    }
+
+    fn start_tls(self, host: &str) -> Result<Self, AppError> {
+        match self.conn.into_inner() {
+            Connection::Plain(stream) => Ok(Self {
+                conn: BufReader::new(Connection::Tls(Box::new(tls(host, stream)?))),
+            }),
+            Connection::Tls(_) => Err(AppError::Mail("already using TLS".to_string())),
+        }
+    }
+
+    /// Log in with PLAIN or LOGIN, both of which send the password as is,
+    /// so only over TLS.
+    fn login(
+        &mut self,
+        extensions: &[String],
+        username: &str,
+        password: &str,
+    ) -> Result<(), AppError> {
+        if !matches!(self.conn.get_ref(), Connection::Tls(_)) {
+            return Err(AppError::Mail(
+                "won't send the password unencrypted; set security to \"tls\" or \"starttls\""
+                    .to_string(),
+            ));
+        }
+        let mechanisms = extensions
+            .iter()
+            .find_map(|line| line.strip_prefix("AUTH "))
+            .unwrap_or("")
+            .to_ascii_uppercase();
+        if mechanisms.split_whitespace().any(|m| m == "PLAIN") {
+            let token = STANDARD.encode(format!("\0{}\0{}", username, password));
+            self.command(&format!("AUTH PLAIN {}", token), 235)?;
+        } else if mechanisms.split_whitespace().any(|m| m == "LOGIN") {
+            self.command("AUTH LOGIN", 334)?;
+            self.command(&STANDARD.encode(username), 334)?;
+            self.command(&STANDARD.encode(password), 235)?;
+        } else {
+            return Err(AppError::Mail(
+                "server offers no supported login (PLAIN or LOGIN)".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Send the message body after DATA, escaping lines that start with a dot.
+    fn data(&mut self, message: &str) -> Result<(), AppError> {
+        self.command("DATA", 354)?;
+        let mut body = String::with_capacity(message.len() + 5);
+        for line in message.split("\r\n") {
+            if line.starts_with('.') {
+                body.push('.');
+            }
+            body.push_str(line);
+            body.push_str("\r\n");
+        }
+        body.push_str(".\r\n");
+        let conn = self.conn.get_mut();
+        conn.write_all(body.as_bytes()).map_err(mail_error)?;
+        conn.flush().map_err(mail_error)?;
+        self.reply(250)?;
+        Ok(())
+    }
+}
+
+/// Deliver `message`, a complete MIME message with CRLF line endings, from
+/// `from` to `to` through the configured server.
+pub fn send(config: &MailConfig, from: &str, to: &str, message: &str) -> Result<(), AppError> {
+    let stream = TcpStream::connect((config.host.as_str(), config.port)).map_err(|e| {
+        AppError::Mail(format!(
+            "can't reach {}:{}: {}",
+            config.host, config.port, e
+        ))
+    })?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(mail_error)?;
+    stream
+        .set_write_timeout(Some(TIMEOUT))
+        .map_err(mail_error)?;
+    let conn = match config.security {
+        Security::Tls => Connection::Tls(Box::new(tls(&config.host, stream)?)),
+        Security::StartTls | Security::None => Connection::Plain(stream),
+    };
+    let mut session = Session {
+        conn: BufReader::new(conn),
+    };
+
+    // Introduce ourselves with the sender's domain.
+    let name = from
+        .rsplit_once('@')
+        .map_or("localhost", |(_, domain)| domain);
+    session.reply(220)?;
+    let mut extensions = session.ehlo(name)?;
+    if config.security == Security::StartTls {
+        if !extensions
+            .iter()
+            .any(|line| line.eq_ignore_ascii_case("STARTTLS"))
+        {
+            return Err(AppError::Mail("server doesn't offer STARTTLS".to_string()));
+        }
+        session.command("STARTTLS", 220)?;
+        session = session.start_tls(&config.host)?;
+        extensions = session.ehlo(name)?;
+    }
+    if !config.username.is_empty() {
+        session.login(&extensions, &config.username, &config.password())?;
+    }
+    session.command(&format!("MAIL FROM:<{}>", from), 250)?;
+    session.command(&format!("RCPT TO:<{}>", to), 250)?;
+    session.data(message)?;
+    // The message is accepted; a failed goodbye doesn't matter.
+    let _ = session.command("QUIT", 221);
+    Ok(())
+}
+
+fn tls(
+    host: &str,
+    stream: TcpStream,
+) -> Result<StreamOwned<ClientConnection, TcpStream>, AppError> {
+    let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(mail_error)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string()).map_err(mail_error)?;
+    let conn = ClientConnection::new(Arc::new(config), name).map_err(mail_error)?;
+    Ok(StreamOwned::new(conn, stream))
+}
+
+fn mail_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Mail(e.to_string())
+}
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Days, Local, NaiveDate};
+use serde::Deserialize;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Client, Invoice, Item, Project, TimeEntry, ValidationErrors};
+use crate::project::find_project;
+use crate::utils::generate_unique_id;
+
+/// Time tracking settings, from the `[time]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct TimeConfig {
+    /// Hourly rate for entries that don't give their own.
+    pub rate: f64,
+    /// Currency of invoices billed from time.
+    pub currency: String,
+    /// Days until an invoice billed from time is due.
+    pub due_days: u64,
+}
+
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            currency: "USD".to_string(),
+            due_days: 30,
+        }
+    }
+}
+
+/// Names of the fields `validate_entry` takes and reports problems for.
+pub const ENTRY_FIELDS: [&str; 5] = ["client", "project", "description", "duration", "rate"];
+
+/// Build an entry from `client, project, description, duration[, rate]`,
+/// or from `client, project, description[, rate]` for a timer starting now.
+pub fn entry(
+    fields: &[&str],
+    clients: &[Client],
+    projects: &[Project],
+    config: &TimeConfig,
+    timer: bool,
+) -> Result<TimeEntry, AppError> {
+    let (client, project, description, rest) = match fields {
+        [client, project, description, rest @ ..] => (client, project, description, rest),
+        _ => return Err(AppError::Validation(entry_usage(timer))),
+    };
+    let (duration, rate) = match (timer, rest) {
+        (true, []) => ("", ""),
+        (true, [rate]) => ("", *rate),
+        (false, [duration]) => (*duration, ""),
+        (false, [duration, rate]) => (*duration, *rate),
+        _ => return Err(AppError::Validation(entry_usage(timer))),
+    };
+    let values = [*client, *project, *description, duration, rate];
+    Ok(validate_entry(values, clients, projects, config, timer)?)
+}
+
+/// Build an entry from the values of `ENTRY_FIELDS`, with a problem per
+/// field. The client is given by id or name. A project named like one of
+/// the client's projects is linked to it and supplies the rate when none is
+/// given. A timer starts now and ignores the duration.
+pub fn validate_entry(
+    values: [&str; 5],
+    clients: &[Client],
+    projects: &[Project],
+    config: &TimeConfig,
+    timer: bool,
+) -> Result<TimeEntry, ValidationErrors> {
+    let [client, project, description, duration, rate] = values.map(str::trim);
+    let mut errors = ValidationErrors::default();
+
+    let client = find_client(clients, client)
+        .map_err(|e| errors.add("client", e.to_string()))
+        .ok();
+    let linked = client.and_then(|client| find_project(projects, client.id, project));
+    let minutes = match parse_duration(duration) {
+        _ if timer => 0,
+        Some(minutes) => minutes,
+        None => {
+            errors.add(
+                "duration",
+                format!(
+                    "'{}' is not a duration; use e.g. 1:30, 1h30m, 90m or 1.5",
+                    duration
+                ),
+            );
+            0
+        }
+    };
+    let rate = match rate {
+        "" => linked
+            .map(|project| project.rate)
+            .filter(|rate| *rate > 0.0)
+            .unwrap_or(config.rate),
+        rate => match rate.parse::<f64>() {
+            Ok(rate) if rate >= 0.0 => rate,
+            _ => {
+                errors.add("rate", format!("'{}' is not a valid rate.", rate));
+                0.0
+            }
+        },
+    };
+
+    let Some(client) = client.filter(|_| errors.0.is_empty()) else {
+        return Err(errors);
+    };
+    let now = Local::now();
+    Ok(TimeEntry {
+        id: 0,
+        client: client.clone(),
+        project: linked.map_or(project.to_string(), |project| project.name.clone()),
+        project_id: linked.map(|project| project.id),
+        description: description.to_string(),
+        date: now.date_naive().to_string(),
+        minutes,
+        rate,
+        started_at: timer.then(|| now.to_rfc3339()),
+        invoice_id: None,
+    })
+}
+
+fn entry_usage(timer: bool) -> String {
+    if timer {
+        "Give client; project; description and optionally a rate.".to_string()
+    } else {
+        "Give client; project; description; duration and optionally a rate.".to_string()
+    }
+}
+
+/// The client with id `query`, or whose name is or uniquely contains it.
+pub fn find_client<'a>(clients: &'a [Client], query: &str) -> Result<&'a Client, AppError> {
+    let query = query.trim();
+    let lower = query.to_lowercase();
+    if let Some(client) = clients
+        .iter()
+        .find(|client| client.id.to_string() == query || client.name.to_lowercase() == lower)
+    {
+        return Ok(client);
+    }
+    let found: Vec<&Client> = clients
+        .iter()
+        .filter(|client| client.name.to_lowercase().contains(&lower))
+        .collect();
+    match found.as_slice() {
+        [client] => Ok(client),
+        [] => Err(AppError::Validation(format!("No client '{}'.", query))),
+        _ => Err(AppError::Validation(format!(
+            "'{}' matches more than one client.",
+            query
+        ))),
+    }
+}
+
+/// Minutes in `1:30`, `1h30m`, `1h 30`, `90m` or decimal hours like `1.5`.
+pub fn parse_duration(text: &str) -> Option<u32> {
+    let text = text.trim().to_lowercase();
+    let minutes = if let Some((hours, minutes)) = text.split_once(':') {
+        hours.parse::<u32>().ok()? * 60 + minutes.parse::<u32>().ok()?
+    } else if let Some((hours, minutes)) = text.split_once('h') {
+        let minutes = minutes.trim().trim_end_matches('m').trim();
+        let minutes = if minutes.is_empty() {
+            0
+        } else {
+            minutes.parse::<u32>().ok()?
+        };
+        hours.trim().parse::<u32>().ok()? * 60 + minutes
+    } else if let Some(minutes) = text.strip_suffix('m') {
+        minutes.trim().parse::<u32>().ok()?
+    } else {
+        let hours = text.parse::<f64>().ok().filter(|hours| hours.is_finite())?;
+        (hours * 60.0).round() as u32
+    };
+    (minutes > 0).then_some(minutes)
+}
+
+/// `90` as `1:30`.
+pub fn format_minutes(minutes: u32) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Minutes a running timer has been going at `now`, at least one.
+pub fn elapsed(entry: &TimeEntry, now: DateTime<Local>) -> u32 {
+    let Some(started) = entry
+        .started_at
+        .as_deref()
+        .and_then(|started| DateTime::parse_from_rfc3339(started).ok())
+    else {
+        return entry.minutes;
+    };
+    let seconds = (now.fixed_offset() - started).num_seconds().max(0);
+    ((seconds + 59) / 60).max(1) as u32
+}
+
+/// Start a timer for `entry`. Only one runs at a time.
+pub fn start(db: &Database, entry: &TimeEntry) -> Result<String, AppError> {
+    if let Some(running) = db.running_timer()? {
+        return Err(AppError::Validation(format!(
+            "A timer is already running for {} ({}). Stop it first.",
+            running.client.name, running.project
+        )));
+    }
+    db.add_time_entry(entry)?;
+    Ok(format!(
+        "Timer started for {} ({})",
+        entry.client.name, entry.project
+    ))
+}
+
+/// Stop the running timer, recording the time since it started.
+pub fn stop(db: &Database, now: DateTime<Local>) -> Result<String, AppError> {
+    let entry = db
+        .running_timer()?
+        .ok_or_else(|| AppError::Validation("No timer is running.".to_string()))?;
+    let minutes = elapsed(&entry, now);
+    db.stop_timer(entry.id, minutes)?;
+    Ok(format!(
+        "Recorded {} h for {} ({})",
+        format_minutes(minutes),
+        entry.client.name,
+        entry.project
+    ))
+}
+
+/// One invoice line per project and rate, in the order the work was done,
+/// with the descriptions as its remark.
+pub fn items(entries: &[TimeEntry]) -> Vec<Item> {
+    let mut groups: Vec<(&str, f64, u32, Vec<&str>)> = Vec::new();
+    for entry in entries {
+        let group = match groups
+            .iter_mut()
+            .position(|(project, rate, _, _)| *project == entry.project && *rate == entry.rate)
+        {
+            Some(index) => &mut groups[index],
+            None => {
+                groups.push((&entry.project, entry.rate, 0, Vec::new()));
+                groups.last_mut().unwrap()
+            }
+        };
+        group.2 += entry.minutes;
+        if !entry.description.is_empty() && !group.3.contains(&entry.description.as_str()) {
+            group.3.push(&entry.description);
+        }
+    }
+
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(index, (project, rate, minutes, descriptions))| {
+            let project = if project.is_empty() { "Time" } else { project };
+            let hours = f64::from(minutes) / 60.0;
+            let amount = (hours * rate * 100.0).round() / 100.0;
+            let mut item = Item::new(
+                &format!(
+                    "{} ({} h at {:.2}/h)",
+                    project,
+                    format_minutes(minutes),
+                    rate
+                ),
+                Some(amount),
+                Some(1),
+                (!descriptions.is_empty()).then(|| descriptions.join("; ")),
+            );
+            item.id = index as u32 + 1;
+            item
+        })
+        .collect()
+}
+
+/// Put the client's unbilled time on new draft invoices, one per project,
+/// and mark it billed.
+pub fn bill(
+    db: &Database,
+    client_id: u32,
+    config: &TimeConfig,
+    today: NaiveDate,
+) -> Result<String, AppError> {
+    let entries = db.get_unbilled_time(client_id)?;
+    let Some(first) = entries.first() else {
+        return Err(AppError::Validation(
+            "This client has no unbilled time.".to_string(),
+        ));
+    };
+    let projects = db.get_projects()?;
+
+    let mut project_ids: Vec<Option<u32>> = Vec::new();
+    for entry in &entries {
+        if !project_ids.contains(&entry.project_id) {
+            project_ids.push(entry.project_id);
+        }
+    }
+    let mut billed = Vec::new();
+    for project_id in project_ids {
+        let group: Vec<TimeEntry> = entries
+            .iter()
+            .filter(|entry| entry.project_id == project_id)
+            .cloned()
+            .collect();
+        let items = items(&group);
+        let total = items.iter().map(|item| item.price.unwrap_or(0.0)).sum();
+        let code = generate_unique_id(&db.get_invoice_codes()?);
+        let invoice = Invoice {
+            code,
+            client: first.client.clone(),
+            currency: config.currency.clone(),
+            total,
+            status: "draft".to_string(),
+            date: today.to_string(),
+            due_date: (today + Days::new(config.due_days)).to_string(),
+            project: projects
+                .iter()
+                .find(|project| Some(project.id) == project_id)
+                .cloned(),
+            items,
+            ..Invoice::default()
+        };
+        let ids: Vec<u32> = group.iter().map(|entry| entry.id).collect();
+        db.bill_time(&invoice, &ids).map_err(|e| match e {
+            rusqlite::Error::StatementChangedRows(_) => AppError::Validation(
+                "Some of this time was billed in the meantime; nothing was changed.".to_string(),
+            ),
+            e => e.into(),
+        })?;
+        let minutes: u32 = group.iter().map(|entry| entry.minutes).sum();
+        billed.push(format!(
+            "{} h to draft invoice {}",
+            format_minutes(minutes),
+            invoice.code
+        ));
+    }
+    Ok(format!(
+        "Billed {} for {}",
+        billed.join(", "),
+        first.client.name
+    ))
+}
@@ -0,0 +1,576 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+
+use strum::{Display, EnumString};
+
+use crate::csv;
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::{Client, Invoice, InvoiceFilter, InvoiceSort, Item, items_total};
+
+#[derive(Debug, Clone, Copy, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ImportKind {
+    Clients,
+    Invoices,
+}
+
+const CLIENT_FIELDS: &[&str] = &[
+    "name",
+    "email",
+    "phone",
+    "address",
+    "country",
+    "vat_id",
+    "endpoint",
+    "reference",
+];
+
+/// Invoices are imported one line item per row; rows sharing a code make up
+/// one invoice, whose other fields are taken from its first row.
+const INVOICE_FIELDS: &[&str] = &[
+    "code",
+    "client_email",
+    "currency",
+    "total",
+    "discount",
+    "tax",
+    "status",
+    "date",
+    "due_date",
+    "paid",
+    "credit_note_for",
+    "item_title",
+    "item_quantity",
+    "item_price",
+    "item_tax_rate",
+    "item_remark",
+];
+
+// Other header names recognised when guessing a mapping.
+const ALIASES: &[(&str, &str)] = &[
+    ("client", "client_email"),
+    ("email", "client_email"),
+    ("item", "item_title"),
+    ("quantity", "item_quantity"),
+    ("qty", "item_quantity"),
+    ("price", "item_price"),
+    ("tax_rate", "item_tax_rate"),
+    ("remark", "item_remark"),
+];
+
+impl ImportKind {
+    pub fn fields(self) -> &'static [&'static str] {
+        match self {
+            Self::Clients => CLIENT_FIELDS,
+            Self::Invoices => INVOICE_FIELDS,
+        }
+    }
+
+    /// Fields that must be mapped to a column.
+    pub fn required(self) -> &'static [&'static str] {
+        match self {
+            Self::Clients => &["name", "email"],
+            Self::Invoices => &["code", "client_email", "date"],
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Self::Clients => Self::Invoices,
+            Self::Invoices => Self::Clients,
+        }
+    }
+}
+
+/// A parsed CSV file: its header row and data rows.
+pub struct CsvFile {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl CsvFile {
+    pub fn read(path: &str) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path)?;
+        let mut records = csv::parse(&text)
+            .map_err(|e| AppError::Validation(format!("{}: {}", path, e)))?
+            .into_iter();
+        let headers = records
+            .next()
+            .ok_or_else(|| AppError::Validation(format!("{} is empty", path)))?;
+        Ok(Self {
+            headers: headers.iter().map(|h| h.trim().to_string()).collect(),
+            rows: records.collect(),
+        })
+    }
+}
+
+/// The field each CSV column is imported into, if any.
+#[derive(Clone)]
+pub struct Mapping {
+    pub kind: ImportKind,
+    pub columns: Vec<Option<&'static str>>,
+}
+
+impl Mapping {
+    /// Map columns whose header names a field, ignoring case, spaces and
+    /// punctuation.
+    pub fn guess(kind: ImportKind, headers: &[String]) -> Self {
+        let mut columns: Vec<Option<&'static str>> = Vec::new();
+        for header in headers {
+            let name = normalize(header);
+            let field = kind
+                .fields()
+                .iter()
+                .copied()
+                .find(|f| normalize(f) == name)
+                .or_else(|| {
+                    ALIASES
+                        .iter()
+                        .find(|(alias, field)| {
+                            normalize(alias) == name && kind.fields().contains(field)
+                        })
+                        .map(|(_, field)| *field)
+                });
+            // A field is only mapped once, to the first matching column.
+            columns.push(field.filter(|field| !columns.contains(&Some(*field))));
+        }
+        Self { kind, columns }
+    }
+
+    /// Apply `header=field` overrides; an empty field leaves the column out.
+    pub fn apply(&mut self, headers: &[String], overrides: &[String]) -> Result<(), AppError> {
+        for entry in overrides {
+            let (header, field) = entry.split_once('=').ok_or_else(|| {
+                AppError::Validation(format!("'{}' is not a column=field mapping", entry))
+            })?;
+            let column = headers
+                .iter()
+                .position(|h| h.eq_ignore_ascii_case(header.trim()))
+                .ok_or_else(|| AppError::Validation(format!("No column named '{}'", header)))?;
+            let field = match field.trim() {
+                "" => None,
+                name => Some(
+                    self.kind
+                        .fields()
+                        .iter()
+                        .copied()
+                        .find(|f| *f == name)
+                        .ok_or_else(|| {
+                            AppError::Validation(format!("Unknown {} field '{}'", self.kind, name))
+                        })?,
+                ),
+            };
+            self.set(column, field);
+        }
+        Ok(())
+    }
+
+    /// Map `column` to `field`, unmapping any other column that had it.
+    pub fn set(&mut self, column: usize, field: Option<&'static str>) {
+        if field.is_some() {
+            for mapped in self.columns.iter_mut() {
+                if *mapped == field {
+                    *mapped = None;
+                }
+            }
+        }
+        if let Some(mapped) = self.columns.get_mut(column) {
+            *mapped = field;
+        }
+    }
+
+    /// Step `column` to the next (or previous) field, with "unmapped" between
+    /// the last and the first.
+    pub fn cycle(&mut self, column: usize, forward: bool) {
+        let fields = self.kind.fields();
+        let Some(current) = self.columns.get(column) else {
+            return;
+        };
+        let index = current.and_then(|field| fields.iter().position(|f| *f == field));
+        let next = match (index, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(fields.len() - 1),
+            (Some(i), true) if i + 1 < fields.len() => Some(i + 1),
+            (Some(i), false) if i > 0 => Some(i - 1),
+            _ => None,
+        };
+        self.set(column, next.map(|i| fields[i]));
+    }
+
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.kind
+            .required()
+            .iter()
+            .copied()
+            .filter(|field| !self.columns.contains(&Some(*field)))
+            .collect()
+    }
+
+    fn value<'a>(&self, row: &'a [String], field: &str) -> &'a str {
+        self.columns
+            .iter()
+            .position(|mapped| *mapped == Some(field))
+            .and_then(|column| row.get(column))
+            .map_or("", |value| value.trim())
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// A problem with one row, numbered from the header as row 1.
+pub struct RowIssue {
+    pub row: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+pub enum Records {
+    Clients(Vec<Client>),
+    Invoices(Vec<Invoice>),
+}
+
+/// Result of a dry run: the records that would be imported and the rows
+/// that would not.
+pub struct ImportPreview {
+    pub records: Records,
+    pub errors: Vec<RowIssue>,
+    pub duplicates: Vec<RowIssue>,
+}
+
+impl ImportPreview {
+    pub fn count(&self) -> usize {
+        match &self.records {
+            Records::Clients(clients) => clients.len(),
+            Records::Invoices(invoices) => invoices.len(),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let noun = match self.records {
+            Records::Clients(_) => "clients",
+            Records::Invoices(_) => "invoices",
+        };
+        format!(
+            "{} {} to import, {} duplicates skipped, {} errors",
+            self.count(),
+            noun,
+            self.duplicates.len(),
+            self.errors.len()
+        )
+    }
+}
+
+/// Validate every row of `file` against the database without writing anything.
+pub fn preview(
+    db: &Database,
+    file: &CsvFile,
+    mapping: &Mapping,
+) -> Result<ImportPreview, AppError> {
+    let missing = mapping.missing();
+    if !missing.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Map a column to: {}",
+            missing.join(", ")
+        )));
+    }
+    match mapping.kind {
+        ImportKind::Clients => preview_clients(db, file, mapping),
+        ImportKind::Invoices => preview_invoices(db, file, mapping),
+    }
+}
+
+// The header is row 1, so data row `index` is row `index + 2`.
+fn row_number(index: usize) -> usize {
+    index + 2
+}
+
+fn preview_clients(
+    db: &Database,
+    file: &CsvFile,
+    mapping: &Mapping,
+) -> Result<ImportPreview, AppError> {
+    let mut emails: HashSet<String> = db
+        .get_all_clients()?
+        .into_iter()
+        .map(|client| client.email.to_lowercase())
+        .collect();
+    let mut clients = Vec::new();
+    let mut errors = Vec::new();
+    let mut duplicates = Vec::new();
+
+    for (index, row) in file.rows.iter().enumerate() {
+        let client = Client {
+            country: mapping.value(row, "country").to_uppercase(),
+            vat_id: mapping.value(row, "vat_id").to_string(),
+            endpoint: mapping.value(row, "endpoint").to_string(),
+            reference: mapping.value(row, "reference").to_string(),
+            ..Client::new(
+                0,
+                mapping.value(row, "name"),
+                mapping.value(row, "phone"),
+                mapping.value(row, "email"),
+                mapping.value(row, "address"),
+            )
+        };
+        if let Err(e) = client.validate() {
+            errors.push(RowIssue {
+                row: row_number(index),
+                message: e.to_string(),
+            });
+            continue;
+        }
+        if !emails.insert(client.email.to_lowercase()) {
+            duplicates.push(RowIssue {
+                row: row_number(index),
+                message: format!("a client with email {} already exists", client.email),
+            });
+            continue;
+        }
+        clients.push(client);
+    }
+
+    Ok(ImportPreview {
+        records: Records::Clients(clients),
+        errors,
+        duplicates,
+    })
+}
+
+fn preview_invoices(
+    db: &Database,
+    file: &CsvFile,
+    mapping: &Mapping,
+) -> Result<ImportPreview, AppError> {
+    let clients = db.get_all_clients()?;
+    let existing = db.get_invoice_codes()?;
+    let mut errors = Vec::new();
+    let mut duplicates = Vec::new();
+
+    // Group rows by code, keeping the order invoices first appear in.
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, row) in file.rows.iter().enumerate() {
+        let code = mapping.value(row, "code");
+        match groups.iter_mut().find(|(c, _)| c == code) {
+            Some((_, rows)) => rows.push(index),
+            None => groups.push((code.to_string(), vec![index])),
+        }
+    }
+
+    let codes: HashSet<&str> = groups.iter().map(|(code, _)| code.as_str()).collect();
+    let mut invoices = Vec::new();
+    for (code, rows) in &groups {
+        let first = row_number(rows[0]);
+        if existing.contains(code) {
+            duplicates.push(RowIssue {
+                row: first,
+                message: format!("invoice {} already exists", code),
+            });
+            continue;
+        }
+        // A credit note's invoice may come later in the file.
+        let credited = |invoice: &Invoice| {
+            let code = invoice.credit_note_for.as_str();
+            code.is_empty() || existing.contains(code) || codes.contains(code)
+        };
+        match parse_invoice(file, mapping, &clients, rows) {
+            Ok(invoice) if !credited(&invoice) => errors.push(RowIssue {
+                row: first,
+                message: format!("credits unknown invoice {}", invoice.credit_note_for),
+            }),
+            Ok(invoice) => invoices.push(invoice),
+            Err(message) => errors.push(RowIssue {
+                row: first,
+                message,
+            }),
+        }
+    }
+
+    Ok(ImportPreview {
+        records: Records::Invoices(invoices),
+        errors,
+        duplicates,
+    })
+}
+
+fn parse_invoice(
+    file: &CsvFile,
+    mapping: &Mapping,
+    clients: &[Client],
+    rows: &[usize],
+) -> Result<Invoice, String> {
+    let head = &file.rows[rows[0]];
+    let value = |field| mapping.value(head, field);
+
+    let email = value("client_email");
+    let client = clients
+        .iter()
+        .find(|client| client.email.eq_ignore_ascii_case(email))
+        .ok_or_else(|| format!("no client with email '{}'", email))?;
+
+    let mut items = Vec::new();
+    for &index in rows {
+        let row = &file.rows[index];
+        let title = mapping.value(row, "item_title");
+        if title.is_empty() {
+            continue;
+        }
+        let mut item = Item::new(
+            title,
+            optional_number(mapping.value(row, "item_price"), "item_price")?,
+            optional_number(mapping.value(row, "item_quantity"), "item_quantity")?,
+            Some(mapping.value(row, "item_remark").to_string()).filter(|r| !r.is_empty()),
+        );
+        item.id = items.len() as u32 + 1;
+        item.tax_rate = optional_number(mapping.value(row, "item_tax_rate"), "item_tax_rate")?;
+        items.push(item);
+    }
+
+    // Without a total column the invoice is the sum of its items.
+    let total = match optional_number(value("total"), "total")? {
+        Some(total) => total,
+        None => items_total(&items),
+    };
+    let invoice = Invoice {
+        code: value("code").to_string(),
+        client: client.clone(),
+        currency: Some(value("currency"))
+            .filter(|c| !c.is_empty())
+            .unwrap_or("USD")
+            .to_string(),
+        total,
+        discount: optional_number(value("discount"), "discount")?.unwrap_or(0.0),
+        tax: optional_number(value("tax"), "tax")?.unwrap_or(0.0),
+        status: Some(value("status"))
+            .filter(|s| !s.is_empty())
+            .unwrap_or("draft")
+            .to_string(),
+        date: value("date").to_string(),
+        due_date: value("due_date").to_string(),
+        paid: optional_number(value("paid"), "paid")?.unwrap_or(0.0),
+        credit_note_for: value("credit_note_for").to_string(),
+        items,
+        ..Invoice::default()
+    };
+    // Duplicate codes are reported separately.
+    invoice.validate(|_| false).map_err(|e| e.to_string())?;
+    Ok(invoice)
+}
+
+fn optional_number<T: std::str::FromStr>(value: &str, field: &str) -> Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("{}: '{}' is not a number", field, value))
+}
+
+/// Write the previewed records in one transaction. Refuses when any row
+/// had errors, so a file is imported completely or not at all.
+pub fn commit(db: &Database, preview: &ImportPreview) -> Result<usize, AppError> {
+    if !preview.errors.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Nothing imported: {} rows have errors.",
+            preview.errors.len()
+        )));
+    }
+    let count = match &preview.records {
+        Records::Clients(clients) => db.import_clients(clients)?,
+        Records::Invoices(invoices) => db.import_invoices(invoices)?,
+    };
+    Ok(count)
+}
+
+pub fn export_clients(db: &Database) -> Result<String, AppError> {
+    let mut out = String::new();
+    csv::write_record(&mut out, CLIENT_FIELDS);
+    for client in db.get_all_clients()? {
+        csv::write_record(
+            &mut out,
+            &[
+                &client.name,
+                &client.email,
+                &client.phone,
+                &client.address,
+                &client.country,
+                &client.vat_id,
+                &client.endpoint,
+                &client.reference,
+            ],
+        );
+    }
+    Ok(out)
+}
+
+/// Export the invoices matching `filter` in the same layout `preview` reads,
+/// one row per line item.
+pub fn export_invoices(
+    db: &Database,
+    filter: &InvoiceFilter,
+    sort: InvoiceSort,
+) -> Result<String, AppError> {
+    let mut out = String::new();
+    csv::write_record(&mut out, INVOICE_FIELDS);
+    for invoice in db.list_invoices(filter, sort, i64::MAX as usize, None)? {
+        let head = [
+            invoice.code.clone(),
+            invoice.client.email.clone(),
+            invoice.currency.clone(),
+            invoice.total.to_string(),
+            invoice.discount.to_string(),
+            invoice.tax.to_string(),
+            invoice.status.clone(),
+            invoice.date.clone(),
+            invoice.due_date.clone(),
+            invoice.paid.to_string(),
+            invoice.credit_note_for.clone(),
+        ];
+        let items = db.get_items(invoice.id)?;
+        if items.is_empty() {
+            let mut record = head.to_vec();
+            record.resize(INVOICE_FIELDS.len(), String::new());
+            csv::write_record(&mut out, &record);
+        }
+        for item in items {
+            let mut record = head.to_vec();
+            record.extend([
+                item.title,
+                item.quantity.map(|q| q.to_string()).unwrap_or_default(),
+                item.price.map(|p| p.to_string()).unwrap_or_default(),
+                item.tax_rate.map(|t| t.to_string()).unwrap_or_default(),
+                item.remark.unwrap_or_default(),
+            ]);
+            csv::write_record(&mut out, &record);
+        }
+    }
+    Ok(out)
+}
+
+/// Export clients or the filtered invoices to `path`.
+pub fn export_to(
+    db: &Database,
+    kind: ImportKind,
+    filter: &InvoiceFilter,
+    sort: InvoiceSort,
+    path: &str,
+) -> Result<(), AppError> {
+    let text = match kind {
+        ImportKind::Clients => export_clients(db)?,
+        ImportKind::Invoices => export_invoices(db, filter, sort)?,
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use tera::{Context, Tera};
+
+use crate::config::IssuerConfig;
+use crate::einvoice::{EInvoice, REQUIRED};
+use crate::error::AppError;
+use crate::models::{Invoice, ValidationErrors, is_country_code, is_endpoint};
+
+pub const CUSTOMIZATION_ID: &str =
+    "urn:cen.eu:en16931:2017#compliant#urn:fdc:peppol.eu:2017:poacc:billing:3.0";
+pub const PROFILE_ID: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+/// Peppol rules on top of EN 16931: both parties need an electronic address
+/// and the buyer a country, and payment goes to the issuer's account.
+fn check_peppol(invoice: &Invoice, issuer: &IssuerConfig) -> ValidationErrors {
+    let mut errors = ValidationErrors::default();
+    if issuer.iban.trim().is_empty() {
+        errors.add("issuer.iban", REQUIRED);
+    }
+    if !is_endpoint(&issuer.endpoint) {
+        errors.add("issuer.endpoint", "Use scheme:id, e.g. 0088:5790000435951");
+    }
+    if !is_country_code(&invoice.client.country) {
+        errors.add("client.country", "Client needs a two-letter country code");
+    }
+    if !is_endpoint(&invoice.client.endpoint) {
+        errors.add("client.endpoint", "Client needs an electronic address");
+    }
+    errors
+}
+
+/// Render the UBL 2.1 XML for `invoice`, with its issuer snapshot once issued.
+/// Credit notes become a UBL CreditNote document referring to the invoice
+/// they credit.
+pub fn render(invoice: &Invoice, issuer: &IssuerConfig) -> Result<String, AppError> {
+    let issuer = invoice.issuer.as_ref().unwrap_or(issuer);
+    let mut ubl = EInvoice::new(invoice, issuer, check_peppol(invoice, issuer))?;
+    // BT-10 is mandatory in Peppol; fall back to the invoice code.
+    if ubl.buyer_reference.is_empty() {
+        ubl.buyer_reference = ubl.id.clone();
+    }
+    let tera = Tera::new("templates/**/*")?;
+    let mut ctx = Context::new();
+    ctx.insert("customization_id", CUSTOMIZATION_ID);
+    ctx.insert("profile_id", PROFILE_ID);
+    ctx.insert(
+        "document",
+        if invoice.is_credit_note() {
+            "CreditNote"
+        } else {
+            "Invoice"
+        },
+    );
+    ctx.insert("invoice", &ubl);
+    Ok(tera.render("ubl_invoice.xml", &ctx)?)
+}
+/// Validate and write `invoice_<code>.xml`, returning the path.
+pub fn export_ubl(invoice: &Invoice, issuer: &IssuerConfig) -> Result<String, AppError> {
+    let xml = render(invoice, issuer)?;
+    let path = format!("invoice_{}.xml", invoice.code);
+    fs::write(&path, xml)?;
+    Ok(path)
+}
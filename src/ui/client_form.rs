@@ -1,107 +1,150 @@
 use crate::models::Client;
-use chrono::prelude::*;
+use crate::ui::input::InputField;
 use ratatui::{
     Frame,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Style},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear},
 };
-use std::fmt;
+
+const FIELD_COUNT: usize = 8;
 
 #[derive(Clone)]
 pub struct ClientForm {
-    pub name: String,
-    pub email: String,
-    pub phone: String,
-    pub address: String,
+    /// Id of the client being edited, `None` for a new one.
+    pub id: Option<u32>,
+    pub name: InputField,
+    pub email: InputField,
+    pub phone: InputField,
+    pub address: InputField,
+    pub country: InputField,
+    pub vat_id: InputField,
+    pub endpoint: InputField,
+    pub reference: InputField,
     pub field_index: usize,
 }
 
+impl Default for ClientForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ClientForm {
-    pub fn new(clients: Vec<Client>) -> Self {
-        let now = Local::now();
-        let today = now.date_naive();
+    pub fn new() -> Self {
         Self {
-            name: String::new(),
-            email: String::new(),
-            phone: String::new(),
-            address: String::new(),
-            field_index: 0,
+            id: None,
+            ..Self::from_client(&Client::new(0, "", "", "", ""))
         }
     }
 
     pub fn from_client(client: &Client) -> Self {
-        Self {
-            name: client.name.clone(),
-            email: client.email.clone(),
-            phone: client.phone.clone(),
-            address: client.address.clone(),
+        let mut form = Self {
+            id: Some(client.id),
+            name: InputField::new("Name", &client.name),
+            email: InputField::new("Email", &client.email),
+            phone: InputField::new("Phone", &client.phone),
+            address: InputField::new("Address", &client.address),
+            country: InputField::new("Country", &client.country),
+            vat_id: InputField::new("VAT ID", &client.vat_id),
+            endpoint: InputField::new("Endpoint", &client.endpoint),
+            reference: InputField::new("Reference", &client.reference),
             field_index: 0,
-        }
+        };
+        form.name.focus();
+        form
     }
 
-    pub fn next_field(&mut self) {
-        self.field_index = (self.field_index + 1) % 4;
+    pub fn fields(&self) -> [&InputField; FIELD_COUNT] {
+        [
+            &self.name,
+            &self.email,
+            &self.phone,
+            &self.address,
+            &self.country,
+            &self.vat_id,
+            &self.endpoint,
+            &self.reference,
+        ]
     }
 
-    pub fn update_field(&mut self, c: char) {
-        match self.field_index {
-            0 => self.name.push(c),
-            1 => self.email.push(c),
-            2 => self.phone.push(c),
-            3 => self.address.push(c),
-            _ => {}
+    fn field_mut(&mut self, index: usize) -> &mut InputField {
+        match index {
+            0 => &mut self.name,
+            1 => &mut self.email,
+            2 => &mut self.phone,
+            3 => &mut self.address,
+            4 => &mut self.country,
+            5 => &mut self.vat_id,
+            6 => &mut self.endpoint,
+            _ => &mut self.reference,
         }
     }
 
-    pub fn backspace(&mut self) {
-        match self.field_index {
-            0 => {
-                self.name.pop();
-            }
-            1 => {
-                self.email.pop();
-            }
-            2 => {
-                self.phone.pop();
-            }
-            3 => {
-                self.address.pop();
-            }
-            _ => {}
-        };
+    /// The field that currently has focus.
+    pub fn focused_mut(&mut self) -> &mut InputField {
+        self.field_mut(self.field_index)
     }
 
-    pub fn to_client(&self, id: u32) -> Option<Client> {
-        Some(Client::new(
-            id,
-            &self.name,
-            &self.email,
-            &self.phone,
-            &self.address,
-        ))
+    fn focus_field(&mut self, index: usize) {
+        self.focused_mut().unfocus();
+        self.field_index = index;
+        self.focused_mut().focus();
+    }
+
+    pub fn next_field(&mut self) {
+        self.focus_field((self.field_index + 1) % FIELD_COUNT);
     }
-}
 
-impl fmt::Display for ClientForm {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Name: {}\nEmail: {}\nPhone: {}\nAddress: {}",
-            self.name, self.email, self.phone, self.address
-        )
+    pub fn previous_field(&mut self) {
+        self.focus_field((self.field_index + FIELD_COUNT - 1) % FIELD_COUNT);
+    }
+
+    /// Validate the form, showing errors next to their fields.
+    /// `email_taken` reports whether another client already uses an email.
+    pub fn validate(&mut self, email_taken: impl Fn(&str) -> bool) -> bool {
+        let client = self.to_client(self.id.unwrap_or(0));
+        let mut errors = client.validate().err().unwrap_or_default();
+        if errors.get("email").is_none() && email_taken(&client.email) {
+            errors.add("email", "Already used by another client");
+        }
+        self.name.error = errors.get("name").map(str::to_string);
+        self.email.error = errors.get("email").map(str::to_string);
+        self.phone.error = errors.get("phone").map(str::to_string);
+        self.address.error = errors.get("address").map(str::to_string);
+        self.country.error = errors.get("country").map(str::to_string);
+        self.endpoint.error = errors.get("endpoint").map(str::to_string);
+        errors.0.is_empty()
+    }
+
+    pub fn to_client(&self, id: u32) -> Client {
+        Client {
+            country: self.country.value().trim().to_uppercase(),
+            vat_id: self.vat_id.value().trim().to_string(),
+            endpoint: self.endpoint.value().trim().to_string(),
+            reference: self.reference.value().trim().to_string(),
+            ..Client::new(
+                id,
+                self.name.value().trim(),
+                self.phone.value().trim(),
+                self.email.value().trim(),
+                self.address.value().trim(),
+            )
+        }
     }
 }
 
 pub fn draw_form(frame: &mut Frame, form: &ClientForm) {
     let size = frame.area();
-    let popup_area = centered_rect(70, 60, size);
+    let popup_area = centered_rect(70, 80, size);
 
     frame.render_widget(Clear, popup_area);
 
     let title = format!(
-        "Editing Field {} of 4 (Tab to switch, Enter to save, Esc to cancel)",
-        form.field_index + 1
+        "{} Client, Field {} of {} (Tab/Shift-Tab to switch, Enter to save, Esc to cancel)",
+        if form.id.is_some() { "Editing" } else { "New" },
+        form.field_index + 1,
+        FIELD_COUNT
     );
 
     let block = Block::default()
@@ -109,10 +152,22 @@ pub fn draw_form(frame: &mut Frame, form: &ClientForm) {
         .title(title)
         .style(Style::default().fg(Color::White).bg(Color::Black));
 
-    let text = format!("{}", form);
-    let paragraph = Paragraph::new(text).alignment(Alignment::Left).block(block);
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
 
-    frame.render_widget(paragraph, popup_area);
+    let fields = form.fields();
+    let constraints = fields
+        .iter()
+        .map(|field| Constraint::Length(field.height()))
+        .chain([Constraint::Min(0)]);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+
+    for (field, area) in fields.iter().zip(rows.iter()) {
+        frame.render_widget(*field, *area);
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -0,0 +1,220 @@
+use crate::models::{Client, ValidationErrors};
+use crate::ui::input::InputField;
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// What an `EntryForm` adds once saved.
+#[derive(Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    TimeEntry,
+    Timer,
+    Expense,
+    /// Client id.
+    Project(u32),
+}
+
+/// Form for a record made from a few text fields, such as a time entry.
+/// The record's own module checks the values and names the field each
+/// problem belongs to, so the form only shows them.
+#[derive(Clone)]
+pub struct EntryForm {
+    pub kind: EntryKind,
+    pub title: String,
+    /// Field names as the record's module reports problems for them.
+    keys: Vec<&'static str>,
+    pub fields: Vec<InputField>,
+    pub field_index: usize,
+    /// Problems that don't belong to one of the fields.
+    pub form_error: Option<String>,
+}
+
+impl EntryForm {
+    /// A form with a field per `(key, label)`, the first one focused.
+    pub fn new(kind: EntryKind, title: &str, fields: &[(&'static str, &str)]) -> Self {
+        let mut form = Self {
+            kind,
+            title: title.to_string(),
+            keys: fields.iter().map(|(key, _)| *key).collect(),
+            fields: fields
+                .iter()
+                .map(|(_, label)| InputField::new(label, ""))
+                .collect(),
+            field_index: 0,
+            form_error: None,
+        };
+        form.focused_mut().focus();
+        form
+    }
+
+    pub fn time_entry() -> Self {
+        Self::new(
+            EntryKind::TimeEntry,
+            "New Time Entry",
+            &[
+                ("client", "Client"),
+                ("project", "Project"),
+                ("description", "Description"),
+                ("duration", "Duration"),
+                ("rate", "Rate"),
+            ],
+        )
+    }
+
+    pub fn timer() -> Self {
+        Self::new(
+            EntryKind::Timer,
+            "Start Timer",
+            &[
+                ("client", "Client"),
+                ("project", "Project"),
+                ("description", "Description"),
+                ("rate", "Rate"),
+            ],
+        )
+    }
+
+    pub fn expense() -> Self {
+        Self::new(
+            EntryKind::Expense,
+            "New Expense",
+            &[
+                ("vendor", "Vendor"),
+                ("amount", "Amount"),
+                ("category", "Category"),
+                ("client", "Client"),
+                ("project", "Project"),
+                ("receipt", "Receipt"),
+                ("date", "Date"),
+            ],
+        )
+    }
+
+    pub fn project(client: &Client) -> Self {
+        Self::new(
+            EntryKind::Project(client.id),
+            &format!("New Project for {}", client.name),
+            &[
+                ("name", "Name"),
+                ("po_number", "PO Number"),
+                ("budget", "Budget"),
+                ("rate", "Rate"),
+            ],
+        )
+    }
+
+    /// The value of the field named `key`, or an empty one if the form
+    /// doesn't have it.
+    pub fn value(&self, key: &str) -> &str {
+        self.keys
+            .iter()
+            .position(|k| *k == key)
+            .map_or("", |index| self.fields[index].value())
+    }
+
+    /// Show `errors` next to their fields, and below the fields when they
+    /// belong to none of them.
+    pub fn show_errors(&mut self, errors: &ValidationErrors) {
+        for (key, field) in self.keys.iter().zip(self.fields.iter_mut()) {
+            field.error = errors.get(key).map(str::to_string);
+        }
+        let other: Vec<&str> = errors
+            .0
+            .iter()
+            .filter(|error| !self.keys.contains(&error.field))
+            .map(|error| error.message.as_str())
+            .collect();
+        self.form_error = (!other.is_empty()).then(|| other.join(" "));
+    }
+
+    /// The field that currently has focus.
+    pub fn focused_mut(&mut self) -> &mut InputField {
+        &mut self.fields[self.field_index]
+    }
+
+    fn focus_field(&mut self, index: usize) {
+        self.focused_mut().unfocus();
+        self.field_index = index;
+        self.focused_mut().focus();
+    }
+
+    pub fn next_field(&mut self) {
+        self.focus_field((self.field_index + 1) % self.fields.len());
+    }
+
+    pub fn previous_field(&mut self) {
+        let count = self.fields.len();
+        self.focus_field((self.field_index + count - 1) % count);
+    }
+}
+
+pub fn draw_form(frame: &mut Frame, form: &EntryForm) {
+    let size = frame.area();
+    let popup_area = centered_rect(70, 80, size);
+
+    frame.render_widget(Clear, popup_area);
+
+    let title = format!(
+        "{}, Field {} of {} (Tab/Shift-Tab to switch, Enter to save, Esc to cancel)",
+        form.title,
+        form.field_index + 1,
+        form.fields.len()
+    );
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let constraints = form
+        .fields
+        .iter()
+        .map(|field| Constraint::Length(field.height()))
+        .chain([Constraint::Min(0)]);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+
+    for (field, area) in form.fields.iter().zip(rows.iter()) {
+        frame.render_widget(field, *area);
+    }
+
+    if let Some(error) = &form.form_error {
+        let paragraph = Paragraph::new(Line::styled(
+            error.as_str(),
+            Style::default().fg(Color::Red),
+        ))
+        .alignment(Alignment::Left);
+        frame.render_widget(paragraph, rows[form.fields.len()]);
+    }
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    let vertical = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1]);
+
+    vertical[1]
+}
@@ -0,0 +1,67 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+use crate::keymap::{KeyContext, Keymap};
+
+/// Overlay listing the bindings active in `context`, generated from the keymap.
+pub fn draw_help(frame: &mut Frame, keymap: &Keymap, context: KeyContext) {
+    let size = frame.area();
+    let popup_area = centered_rect(60, 70, size);
+
+    frame.render_widget(Clear, popup_area);
+
+    let lines: Vec<Line> = keymap
+        .help(context)
+        .into_iter()
+        .map(|(action, keys)| {
+            let keys = keys
+                .iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>20}  ", keys),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(action.description()),
+            ])
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Help: {} keys (any key to close)", context))
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    let vertical = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1]);
+
+    vertical[1]
+}
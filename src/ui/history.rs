@@ -0,0 +1,74 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+};
+
+use crate::audit;
+use crate::models::AuditEntry;
+
+/// Overlay with an invoice's audit entries, oldest first, each followed by
+/// its changed fields.
+pub fn draw_history(frame: &mut Frame, title: &str, entries: &[AuditEntry]) {
+    let size = frame.area();
+    let popup_area = centered_rect(80, 80, size);
+
+    frame.render_widget(Clear, popup_area);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in entries {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}  {} {}", entry.at, entry.action, entry.entity),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("  by {}", entry.actor)),
+        ]));
+        lines.extend(
+            audit::describe(entry)
+                .into_iter()
+                .map(|change| Line::raw(format!("    {}", change))),
+        );
+    }
+    if lines.is_empty() {
+        lines.push(Line::raw("No changes recorded yet."));
+    }
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{} (any key to close)", title))
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false }),
+        popup_area,
+    );
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    let vertical = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1]);
+
+    vertical[1]
+}
@@ -0,0 +1,270 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::error::AppError;
+use crate::transfer::{CsvFile, ImportKind, ImportPreview, Mapping};
+use crate::ui::input::InputField;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WizardStep {
+    File,
+    Mapping,
+    Preview,
+}
+
+/// Steps through picking a CSV file, mapping its columns and checking a dry
+/// run before anything is imported.
+pub struct ImportWizard {
+    pub step: WizardStep,
+    pub kind: ImportKind,
+    pub path: InputField,
+    pub file: Option<CsvFile>,
+    pub mapping: Option<Mapping>,
+    /// Selected column on the mapping step, scroll offset on the preview.
+    pub selected: usize,
+    pub preview: Option<ImportPreview>,
+}
+
+impl ImportWizard {
+    pub fn new(kind: ImportKind) -> Self {
+        let mut path = InputField::new("File", "");
+        path.focus();
+        Self {
+            step: WizardStep::File,
+            kind,
+            path,
+            file: None,
+            mapping: None,
+            selected: 0,
+            preview: None,
+        }
+    }
+
+    pub fn next_row(&mut self) {
+        match self.step {
+            WizardStep::File => self.kind = self.kind.toggle(),
+            WizardStep::Mapping => {
+                let columns = self.file.as_ref().map_or(0, |file| file.headers.len());
+                if self.selected + 1 < columns {
+                    self.selected += 1;
+                }
+            }
+            WizardStep::Preview => self.selected += 1,
+        }
+    }
+
+    pub fn previous_row(&mut self) {
+        match self.step {
+            WizardStep::File => self.kind = self.kind.toggle(),
+            WizardStep::Mapping | WizardStep::Preview => {
+                self.selected = self.selected.saturating_sub(1)
+            }
+        }
+    }
+
+    /// Change the field the selected column is imported into.
+    pub fn cycle_field(&mut self, forward: bool) {
+        if let Some(mapping) = self.mapping.as_mut() {
+            mapping.cycle(self.selected, forward);
+        }
+    }
+
+    /// Read the chosen file and guess its column mapping.
+    pub fn load_file(&mut self) -> Result<(), AppError> {
+        let file = CsvFile::read(self.path.value().trim())?;
+        self.mapping = Some(Mapping::guess(self.kind, &file.headers));
+        self.file = Some(file);
+        self.selected = 0;
+        self.step = WizardStep::Mapping;
+        Ok(())
+    }
+
+    pub fn show_preview(&mut self, preview: ImportPreview) {
+        self.preview = Some(preview);
+        self.selected = 0;
+        self.step = WizardStep::Preview;
+    }
+
+    /// Go back a step. Returns false on the first step, where the wizard closes.
+    pub fn back(&mut self) -> bool {
+        self.selected = 0;
+        match self.step {
+            WizardStep::File => return false,
+            WizardStep::Mapping => self.step = WizardStep::File,
+            WizardStep::Preview => self.step = WizardStep::Mapping,
+        }
+        true
+    }
+}
+
+pub fn draw_wizard(frame: &mut Frame, wizard: &ImportWizard) {
+    let size = frame.area();
+    let popup_area = centered_rect(70, 70, size);
+
+    frame.render_widget(Clear, popup_area);
+
+    let (number, name, hint) = match wizard.step {
+        WizardStep::File => (
+            1,
+            "Choose a file",
+            "Tab to switch type, Enter to read the file",
+        ),
+        WizardStep::Mapping => (2, "Map columns", "◄ ► to change field, Enter for a dry run"),
+        WizardStep::Preview => (3, "Dry run", "Enter to import, Esc to go back"),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(
+            "Import {}: step {} of 3, {} ({})",
+            wizard.kind, number, name, hint
+        ))
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    match wizard.step {
+        WizardStep::File => draw_file_step(frame, wizard, inner),
+        WizardStep::Mapping => draw_mapping_step(frame, wizard, inner),
+        WizardStep::Preview => draw_preview_step(frame, wizard, inner),
+    }
+}
+
+fn draw_file_step(frame: &mut Frame, wizard: &ImportWizard, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(wizard.path.height()),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    let kinds = [ImportKind::Clients, ImportKind::Invoices]
+        .map(|kind| {
+            if kind == wizard.kind {
+                format!("[{}]", kind)
+            } else {
+                format!(" {} ", kind)
+            }
+        })
+        .join(" ");
+    frame.render_widget(Paragraph::new(format!("Import: {}", kinds)), rows[0]);
+    frame.render_widget(&wizard.path, rows[1]);
+
+    let columns = wizard.kind.fields().join(", ");
+    frame.render_widget(
+        Paragraph::new(format!("Recognised columns: {}", columns))
+            .style(Style::default().fg(Color::DarkGray))
+            .wrap(Wrap { trim: true }),
+        rows[2],
+    );
+}
+
+fn draw_mapping_step(frame: &mut Frame, wizard: &ImportWizard, area: Rect) {
+    let (Some(file), Some(mapping)) = (&wizard.file, &wizard.mapping) else {
+        return;
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(area);
+
+    let items: Vec<ListItem> = file
+        .headers
+        .iter()
+        .zip(&mapping.columns)
+        .enumerate()
+        .map(|(index, (header, field))| {
+            let sample = file
+                .rows
+                .first()
+                .and_then(|row| row.get(index))
+                .map_or("", String::as_str);
+            ListItem::new(format!(
+                "{:<20} -> {:<14} e.g. {}",
+                header,
+                field.unwrap_or("(ignored)"),
+                sample
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(wizard.selected));
+    let list = List::new(items)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("▶ ");
+    frame.render_stateful_widget(list, rows[0], &mut state);
+
+    let missing = mapping.missing();
+    let status = if missing.is_empty() {
+        Line::raw(format!("{} rows", file.rows.len()))
+    } else {
+        Line::styled(
+            format!("Required: {}", missing.join(", ")),
+            Style::default().fg(Color::Red),
+        )
+    };
+    frame.render_widget(Paragraph::new(status), rows[1]);
+}
+
+fn draw_preview_step(frame: &mut Frame, wizard: &ImportWizard, area: Rect) {
+    let Some(preview) = &wizard.preview else {
+        return;
+    };
+    let mut lines = vec![Line::styled(
+        preview.summary(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if !preview.errors.is_empty() {
+        lines.push(Line::styled(
+            "Fix the errors below and import again; nothing is imported while rows have errors.",
+            Style::default().fg(Color::Red),
+        ));
+    }
+    lines.extend(
+        preview
+            .errors
+            .iter()
+            .map(|issue| Line::styled(issue.to_string(), Style::default().fg(Color::Red))),
+    );
+    lines.extend(preview.duplicates.iter().map(|issue| {
+        Line::styled(
+            format!("skip {}", issue),
+            Style::default().fg(Color::Yellow),
+        )
+    }));
+    let scroll = wizard.selected.min(lines.len().saturating_sub(1)) as u16;
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    let vertical = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1]);
+
+    vertical[1]
+}
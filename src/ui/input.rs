@@ -0,0 +1,219 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::Widget,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const LABEL_WIDTH: usize = 14;
+
+/// Single-line text input with a grapheme-aware cursor.
+#[derive(Clone)]
+pub struct InputField {
+    pub label: String,
+    value: String,
+    /// Cursor position counted in graphemes.
+    cursor: usize,
+    pub error: Option<String>,
+    pub is_focused: bool,
+}
+
+impl InputField {
+    pub fn new(label: &str, value: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            value: value.to_string(),
+            cursor: value.graphemes(true).count(),
+            error: None,
+            is_focused: false,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.to_string();
+        self.cursor = self.len();
+    }
+
+    pub fn focus(&mut self) {
+        self.is_focused = true;
+    }
+
+    pub fn unfocus(&mut self) {
+        self.is_focused = false;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let at = self.byte_offset(self.cursor);
+        self.value.insert(at, c);
+        self.cursor = self.value[..at + c.len_utf8()].graphemes(true).count();
+        self.error = None;
+    }
+
+    /// Insert pasted text at the cursor, flattening newlines to spaces.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .chars()
+            .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        let at = self.byte_offset(self.cursor);
+        self.value.insert_str(at, &text);
+        self.cursor = self.value[..at + text.len()].graphemes(true).count();
+        self.error = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            let start = self.byte_offset(self.cursor - 1);
+            let end = self.byte_offset(self.cursor);
+            self.value.replace_range(start..end, "");
+            self.cursor -= 1;
+            self.error = None;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.len() {
+            let start = self.byte_offset(self.cursor);
+            let end = self.byte_offset(self.cursor + 1);
+            self.value.replace_range(start..end, "");
+            self.error = None;
+        }
+    }
+
+    /// Delete back to the start of the previous word.
+    pub fn delete_word(&mut self) {
+        let graphemes: Vec<&str> = self.value.graphemes(true).collect();
+        let mut start = self.cursor;
+        while start > 0 && graphemes[start - 1].trim().is_empty() {
+            start -= 1;
+        }
+        while start > 0 && !graphemes[start - 1].trim().is_empty() {
+            start -= 1;
+        }
+        let from = self.byte_offset(start);
+        let to = self.byte_offset(self.cursor);
+        self.value.replace_range(from..to, "");
+        self.cursor = start;
+        self.error = None;
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.len();
+    }
+
+    /// Rows needed to draw the field and its error message.
+    pub fn height(&self) -> u16 {
+        if self.error.is_some() { 2 } else { 1 }
+    }
+
+    fn len(&self) -> usize {
+        self.value.graphemes(true).count()
+    }
+
+    fn byte_offset(&self, grapheme: usize) -> usize {
+        self.value
+            .grapheme_indices(true)
+            .nth(grapheme)
+            .map(|(i, _)| i)
+            .unwrap_or(self.value.len())
+    }
+}
+
+impl Widget for &InputField {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+
+        let label_style = if self.is_focused {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        let label = if self.label.is_empty() {
+            String::new()
+        } else {
+            format!("{:>width$}: ", self.label, width = LABEL_WIDTH)
+        };
+        let (x, _) = buf.set_stringn(area.x, area.y, &label, area.width as usize, label_style);
+
+        let value_width = area.right().saturating_sub(x) as usize;
+        if value_width == 0 {
+            return;
+        }
+        let value_style = if self.is_focused {
+            Style::default().fg(Color::White).bg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        for col in x..area.right() {
+            if let Some(cell) = buf.cell_mut((col, area.y)) {
+                cell.set_style(value_style);
+            }
+        }
+
+        // Scroll horizontally so the cursor stays visible.
+        let graphemes: Vec<&str> = self.value.graphemes(true).collect();
+        let mut start = 0;
+        while start < self.cursor
+            && graphemes[start..self.cursor].concat().width() + 1 > value_width
+        {
+            start += 1;
+        }
+
+        let mut col = x;
+        for (i, grapheme) in graphemes.iter().enumerate().skip(start) {
+            let width = grapheme.width() as u16;
+            if col + width > area.right() {
+                break;
+            }
+            let style = if self.is_focused && i == self.cursor {
+                value_style.add_modifier(Modifier::REVERSED)
+            } else {
+                value_style
+            };
+            buf.set_string(col, area.y, grapheme, style);
+            col += width.max(1);
+        }
+        if self.is_focused && self.cursor == graphemes.len() && col < area.right() {
+            buf.set_string(
+                col,
+                area.y,
+                " ",
+                value_style.add_modifier(Modifier::REVERSED),
+            );
+        }
+
+        if let (Some(error), true) = (&self.error, area.height > 1) {
+            let indent = " ".repeat(label.len());
+            buf.set_stringn(
+                area.x,
+                area.y + 1,
+                format!("{}{}", indent, error),
+                area.width as usize,
+                Style::default().fg(Color::Red),
+            );
+        }
+    }
+}
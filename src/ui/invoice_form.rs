@@ -1,160 +1,250 @@
-use crate::models::{Client, Invoice, Item};
+use crate::models::{Client, Invoice, Item, Project};
+use crate::ui::input::InputField;
 use crate::utils::generate_unique_id;
-use chrono::prelude::*;
+use chrono::{Days, prelude::*};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Style},
+    text::Line,
     widgets::{Block, Borders, Clear, Paragraph},
 };
 use std::collections::HashSet;
-use std::fmt;
+
+const FIELD_COUNT: usize = 10;
 
 #[derive(Clone)]
 pub struct InvoiceForm {
-    pub code: String,
-    pub client_email: String,
-    pub total: String,
-    pub currency: String,
-    pub discount: String,
-    pub tax: String,
-    pub status: String,
-    pub date: String,
+    /// Id of the invoice being edited, `None` for a new one.
+    pub id: Option<u32>,
+    pub code: InputField,
+    pub client_email: InputField,
+    /// Name of one of the client's projects, or empty.
+    pub project: InputField,
+    pub currency: InputField,
+    pub total: InputField,
+    pub discount: InputField,
+    pub tax: InputField,
+    pub status: InputField,
+    pub date: InputField,
+    pub due_date: InputField,
+    pub paid: f64,
+    /// Code of the invoice a credit note credits, kept as loaded.
+    pub credit_note_for: String,
     pub items: Vec<Item>,
+    /// Validation message that doesn't belong to a single field.
+    pub form_error: Option<String>,
     pub field_index: usize,
     pub clients: Vec<Client>,
+    pub projects: Vec<Project>,
 }
 
 impl InvoiceForm {
-    pub fn new(clients: Vec<Client>) -> Self {
+    pub fn new(clients: Vec<Client>, projects: Vec<Project>) -> Self {
         let existing_ids = HashSet::from(["278532".to_string()]);
         let now = Local::now();
         let today = now.date_naive();
-        Self {
-            code: format!("INV-{}", generate_unique_id(&existing_ids)),
-            client_email: String::new(),
-            currency: String::from("USD"),
-            total: String::new(),
-            discount: String::from("0"),
-            tax: String::from("0"),
-            status: String::from("draft"),
-            date: String::from(today.to_string()),
+        let code = format!("INV-{}", generate_unique_id(&existing_ids));
+        let mut form = Self {
+            id: None,
+            code: InputField::new("Code", &code),
+            client_email: InputField::new("Client Email", ""),
+            project: InputField::new("Project", ""),
+            currency: InputField::new("Currency", "USD"),
+            total: InputField::new("Total", ""),
+            discount: InputField::new("Discount", "0"),
+            tax: InputField::new("Tax", "0"),
+            status: InputField::new("Status", "draft"),
+            date: InputField::new("Date", &today.to_string()),
+            due_date: InputField::new("Due Date", &(today + Days::new(30)).to_string()),
+            paid: 0.0,
+            credit_note_for: String::new(),
             items: Vec::new(),
+            form_error: None,
             field_index: 0,
             clients,
-        }
+            projects,
+        };
+        form.code.focus();
+        form
     }
 
-    pub fn from_invoice(inv: &Invoice, clients: Vec<Client>) -> Self {
-        Self {
-            code: inv.code.clone(),
-            client_email: inv.client.email.to_string(),
-            currency: inv.currency.clone(),
-            total: format!("{:.2}", inv.total),
-            discount: format!("{:.2}", inv.discount),
-            tax: format!("{:.2}", inv.tax),
-            status: inv.status.clone(),
-            date: inv.date.clone(),
+    pub fn from_invoice(inv: &Invoice, clients: Vec<Client>, projects: Vec<Project>) -> Self {
+        let mut form = Self {
+            id: Some(inv.id),
+            code: InputField::new("Code", &inv.code),
+            client_email: InputField::new("Client Email", &inv.client.email),
+            project: InputField::new(
+                "Project",
+                inv.project.as_ref().map_or("", |project| &project.name),
+            ),
+            currency: InputField::new("Currency", &inv.currency),
+            total: InputField::new("Total", &format!("{:.2}", inv.total)),
+            discount: InputField::new("Discount", &format!("{:.2}", inv.discount)),
+            tax: InputField::new("Tax", &format!("{:.2}", inv.tax)),
+            status: InputField::new("Status", &inv.status),
+            date: InputField::new("Date", &inv.date),
+            due_date: InputField::new("Due Date", &inv.due_date),
+            paid: inv.paid,
+            credit_note_for: inv.credit_note_for.clone(),
             items: inv.items.clone(),
+            form_error: None,
             field_index: 0,
             clients,
+            projects,
+        };
+        form.code.focus();
+        form
+    }
+
+    pub fn fields(&self) -> [&InputField; FIELD_COUNT] {
+        [
+            &self.code,
+            &self.client_email,
+            &self.project,
+            &self.currency,
+            &self.total,
+            &self.discount,
+            &self.tax,
+            &self.status,
+            &self.date,
+            &self.due_date,
+        ]
+    }
+
+    fn field_mut(&mut self, index: usize) -> &mut InputField {
+        match index {
+            0 => &mut self.code,
+            1 => &mut self.client_email,
+            2 => &mut self.project,
+            3 => &mut self.currency,
+            4 => &mut self.total,
+            5 => &mut self.discount,
+            6 => &mut self.tax,
+            7 => &mut self.status,
+            8 => &mut self.date,
+            _ => &mut self.due_date,
         }
     }
 
+    /// The field that currently has focus.
+    pub fn focused_mut(&mut self) -> &mut InputField {
+        self.field_mut(self.field_index)
+    }
+
+    fn focus_field(&mut self, index: usize) {
+        self.focused_mut().unfocus();
+        self.field_index = index;
+        self.focused_mut().focus();
+    }
+
     pub fn next_field(&mut self) {
-        self.field_index = (self.field_index + 1) % 8;
+        self.focus_field((self.field_index + 1) % FIELD_COUNT);
     }
 
-    pub fn update_field(&mut self, c: char) {
-        match self.field_index {
-            0 => self.code.push(c),
-            1 => self.client_email.push(c),
-            2 => self.currency.push(c),
-            3 => self.total.push(c),
-            4 => self.discount.push(c),
-            5 => self.tax.push(c),
-            6 => self.status.push(c),
-            7 => self.date.push(c),
-            8 => {}
-            _ => {}
-        }
+    pub fn previous_field(&mut self) {
+        self.focus_field((self.field_index + FIELD_COUNT - 1) % FIELD_COUNT);
     }
 
-    pub fn backspace(&mut self) {
-        match self.field_index {
-            0 => {
-                self.code.pop();
-            }
-            1 => {
-                self.client_email.pop();
-            }
-            2 => {
-                self.currency.pop();
-            }
-            3 => {
-                self.total.pop();
-            }
-            4 => {
-                self.discount.pop();
-            }
-            5 => {
-                self.tax.pop();
-            }
-            6 => {
-                self.status.pop();
+    /// Validate the form, showing errors next to their fields. Returns
+    /// whether the form can be saved.
+    pub fn validate(&mut self, code_taken: impl Fn(&str) -> bool) -> bool {
+        for index in 0..FIELD_COUNT {
+            self.field_mut(index).error = None;
+        }
+        self.form_error = None;
+
+        for field in [&mut self.total, &mut self.discount, &mut self.tax] {
+            if field.value().trim().parse::<f64>().is_err() {
+                field.error = Some("Enter a number".to_string());
             }
-            7 => {
-                self.date.pop();
+        }
+        let email = self.client_email.value().trim();
+        match self.clients.iter().find(|c| c.email == email) {
+            None => self.client_email.error = Some("No client with this email".to_string()),
+            Some(client) => {
+                if self.project(client.id).is_err() {
+                    self.project.error = Some("Not one of this client's projects".to_string());
+                }
             }
-            8 => {}
-            _ => {}
+        }
+        if self.fields().iter().any(|field| field.error.is_some()) {
+            return false;
+        }
+
+        let Some(invoice) = self.to_invoice(self.id.unwrap_or(0)) else {
+            return false;
+        };
+        let Err(errors) = invoice.validate(code_taken) else {
+            return true;
         };
+        for error in &errors.0 {
+            match self.field_by_name(error.field) {
+                Some(field) if field.error.is_none() => field.error = Some(error.message.clone()),
+                Some(_) => {}
+                None => {
+                    self.form_error.get_or_insert(error.message.clone());
+                }
+            }
+        }
+        false
     }
 
-    pub fn to_invoice(&self, id: u32) -> Option<Invoice> {
-        let total = self.total.parse::<f64>().ok()?;
-        let discount = self.discount.parse::<f64>().ok()?;
-        let tax = self.tax.parse::<f64>().ok()?;
-        let client_opt = self.clients.iter().find(|c| c.email == self.client_email);
-
-        if let Some(client) = client_opt {
-            Some(Invoice::new(
-                id,
-                &self.code,
-                client,
-                &self.currency,
-                total,
-                discount,
-                tax,
-                &self.status,
-                &self.date,
-                self.items.clone(),
-            ))
-        } else {
-            None
+    fn field_by_name(&mut self, name: &str) -> Option<&mut InputField> {
+        match name {
+            "code" => Some(&mut self.code),
+            "client" => Some(&mut self.client_email),
+            "project" => Some(&mut self.project),
+            "currency" => Some(&mut self.currency),
+            "total" => Some(&mut self.total),
+            "discount" => Some(&mut self.discount),
+            "tax" => Some(&mut self.tax),
+            "status" => Some(&mut self.status),
+            "date" => Some(&mut self.date),
+            "due_date" => Some(&mut self.due_date),
+            _ => None,
         }
     }
-}
 
-impl fmt::Display for InvoiceForm {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Code: {}\nClient Email: {}\nCurrency: {}\nTotal: {}\nDiscount: {}\nTax: {}\nStatus: {}\nDate: {}\nItems:\n{}",
-            self.code,
-            self.client_email,
-            self.currency,
-            self.total,
-            self.discount,
-            self.tax,
-            self.status,
-            self.date,
-            self.items
-                .iter()
-                .map(|item| format!("{}", item))
-                .collect::<Vec<String>>()
-                .join("\n")
-        )
+    /// The client's project named in the project field, `Ok(None)` when
+    /// it's empty and `Err` when the client has no such project.
+    fn project(&self, client_id: u32) -> Result<Option<&Project>, ()> {
+        let name = self.project.value().trim().to_lowercase();
+        if name.is_empty() {
+            return Ok(None);
+        }
+        self.projects
+            .iter()
+            .find(|project| project.client_id == client_id && project.name.to_lowercase() == name)
+            .map(Some)
+            .ok_or(())
+    }
+
+    pub fn to_invoice(&self, id: u32) -> Option<Invoice> {
+        let total = self.total.value().trim().parse::<f64>().ok()?;
+        let discount = self.discount.value().trim().parse::<f64>().ok()?;
+        let tax = self.tax.value().trim().parse::<f64>().ok()?;
+        let email = self.client_email.value().trim();
+        let client = self.clients.iter().find(|c| c.email == email)?;
+        let project = self.project(client.id).ok()?.cloned();
+
+        Some(Invoice {
+            id,
+            code: self.code.value().to_string(),
+            client: client.clone(),
+            currency: self.currency.value().to_string(),
+            total,
+            discount,
+            tax,
+            status: self.status.value().to_string(),
+            date: self.date.value().to_string(),
+            due_date: self.due_date.value().to_string(),
+            paid: self.paid,
+            project,
+            credit_note_for: self.credit_note_for.clone(),
+            items: self.items.clone(),
+            ..Invoice::default()
+        })
     }
 }
 
@@ -165,8 +255,9 @@ pub fn draw_form(frame: &mut Frame, form: &InvoiceForm) {
     frame.render_widget(Clear, popup_area);
 
     let title = format!(
-        "Editing Field {} of 8 (Tab to switch, Enter to save, Esc to cancel)",
-        form.field_index + 1
+        "Editing Field {} of {} (Tab/Shift-Tab to switch, Enter to save, Esc to cancel)",
+        form.field_index + 1,
+        FIELD_COUNT
     );
 
     let block = Block::default()
@@ -174,10 +265,35 @@ pub fn draw_form(frame: &mut Frame, form: &InvoiceForm) {
         .title(title)
         .style(Style::default().fg(Color::White).bg(Color::Black));
 
-    let text = format!("{}", form);
-    let paragraph = Paragraph::new(text).alignment(Alignment::Left).block(block);
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let fields = form.fields();
+    let constraints = fields
+        .iter()
+        .map(|field| Constraint::Length(field.height()))
+        .chain([Constraint::Min(0)]);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+
+    for (field, area) in fields.iter().zip(rows.iter()) {
+        frame.render_widget(*field, *area);
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    if let Some(error) = &form.form_error {
+        lines.push(Line::styled(
+            error.as_str(),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    lines.push(Line::raw("Items:"));
+    lines.extend(form.items.iter().map(|item| Line::raw(item.to_string())));
+    let paragraph = Paragraph::new(lines).alignment(Alignment::Left);
 
-    frame.render_widget(paragraph, popup_area);
+    frame.render_widget(paragraph, rows[FIELD_COUNT]);
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
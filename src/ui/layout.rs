@@ -1,20 +1,38 @@
 use crate::app::{App, Mode};
+use crate::keymap::KeyContext;
+use crate::models::SortColumn;
+use crate::project::ProjectBudget;
+use crate::report::ReportKind;
+use crate::timesheet;
+use crate::ui::client_form;
+use crate::ui::entry_form;
+use crate::ui::help::draw_help;
+use crate::ui::history::draw_history;
+use crate::ui::import_wizard::draw_wizard;
 use crate::ui::invoice_form::draw_form;
 use crate::ui::modal::draw_modal;
+use chrono::Local;
 use ratatui::style::palette::tailwind;
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Cell, List, ListItem, ListState, Paragraph, Row,
+        Sparkline, Table, TableState, Tabs,
+    },
 };
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 
-#[derive(Default, Clone, Copy, Display, FromRepr, EnumIter)]
+#[derive(Default, Clone, Copy, PartialEq, Display, FromRepr, EnumIter)]
 pub enum SelectedTab {
     #[default]
-    #[strum(to_string = "Invoices")]
-    TabInvoices,
-    #[strum(to_string = "Clients")]
-    TabClients,
+    Invoices,
+    Clients,
+    Dashboard,
+    Reports,
+    Reminders,
+    Bank,
+    Time,
+    Expenses,
 }
 
 impl SelectedTab {
@@ -27,52 +45,88 @@ impl SelectedTab {
 
     fn render(self, app: &App, area: Rect, frame: &mut Frame) {
         match self {
-            Self::TabInvoices => self.render_tab0(app, area, frame),
-            Self::TabClients => self.render_tab1(app, area, frame),
+            Self::Invoices => self.render_tab0(app, area, frame),
+            Self::Clients => self.render_tab1(app, area, frame),
+            Self::Dashboard => self.render_tab2(app, area, frame),
+            Self::Reports => self.render_tab3(app, area, frame),
+            Self::Reminders => self.render_tab4(app, area, frame),
+            Self::Bank => self.render_tab5(app, area, frame),
+            Self::Time => self.render_tab6(app, area, frame),
+            Self::Expenses => self.render_tab7(app, area, frame),
         }
     }
 
     pub fn render_tab0(self, app: &App, area: Rect, frame: &mut Frame) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
             .split(area);
 
-        let items: Vec<ListItem> = app
-            .invoices
-            .iter()
-            .map(|inv| {
-                ListItem::new(format!(
-                    "{} - {} ({})",
-                    inv.code, inv.client.name, inv.status
-                ))
+        let header = SortColumn::iter()
+            .map(|column| {
+                if column == app.sort.column {
+                    Cell::from(format!("{} {}", column.title(), app.sort.direction.arrow()))
+                        .style(Style::default().fg(Color::Cyan))
+                } else {
+                    Cell::from(column.title())
+                }
             })
-            .collect();
-
-        let mut list_state = ListState::default();
-        list_state.select(Some(app.selected));
-
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Invoices"))
-            .highlight_style(
+            .collect::<Row>()
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .bottom_margin(1);
+
+        let rows = app.invoices.iter().map(|inv| {
+            Row::new(vec![
+                Cell::from(inv.code.clone()),
+                Cell::from(inv.date.clone()),
+                Cell::from(inv.due_date.clone()),
+                Cell::from(inv.client.name.clone()),
+                Cell::from(Text::from(format!("{:.2}", inv.calculate_net_total())).right_aligned()),
+                Cell::from(Text::from(format!("{:.2}", inv.balance())).right_aligned()),
+                Cell::from(status_badge(&inv.status)),
+            ])
+        });
+
+        let widths = [
+            Constraint::Fill(3),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(4),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+        ];
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.selected));
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Invoices ({})", app.invoice_count)),
+            )
+            .row_highlight_style(
                 Style::default()
                     .fg(Color::Cyan)
                     .add_modifier(Modifier::BOLD),
             )
             .highlight_symbol("▶ ");
 
-        frame.render_stateful_widget(list, chunks[0], &mut list_state);
+        frame.render_stateful_widget(table, chunks[0], &mut table_state);
 
         let inv = app.selected_invoice();
 
-        let detail_text = if inv.code.eq("empty_invoice") {
+        let mut detail_text = if inv.code.eq("empty_invoice") {
             "No invoice created yet.\nPress 'n' to create a new invoice.\nPress 'h' for help."
                 .to_string()
         } else {
             format!(
-                "Invoice: {}\nDate: {}\nStatus: {},\nClient:\n- Name: {}\n- Email: {}\n- Phone: {}\n- Address: {}\nTotal: ${:.2}\nDiscount: ${:.2}\nTax: ${:.2}\nNet Total: ${:.2}\nItems:\n{}",
+                "Invoice: {}\nDate: {}\nDue Date: {}\nStatus: {},\nClient:\n- Name: {}\n- Email: {}\n- Phone: {}\n- Address: {}\nTotal: ${:.2}\nDiscount: ${:.2}\nTax: ${:.2}\nNet Total: ${:.2}\nPaid: ${:.2}\nBalance: ${:.2}\nItems:\n{}",
                 inv.code,
                 inv.date,
+                inv.due_date,
                 inv.status,
                 inv.client.name,
                 inv.client.email,
@@ -82,6 +136,8 @@ impl SelectedTab {
                 inv.discount,
                 inv.tax,
                 inv.calculate_net_total(),
+                inv.paid,
+                inv.balance(),
                 inv.items
                     .iter()
                     .map(|item| format!("- {}", item))
@@ -90,6 +146,33 @@ impl SelectedTab {
             )
         };
 
+        if !inv.void_reason.is_empty() {
+            detail_text.push_str(&format!("\nVoid Reason: {}", inv.void_reason));
+        }
+
+        if !app.detail_reminders.is_empty() {
+            detail_text.push_str("\nReminders:");
+            for reminder in &app.detail_reminders {
+                detail_text.push_str(&format!(
+                    "\n- {} {}. {} {}",
+                    reminder.date, reminder.level, reminder.name, reminder.status
+                ));
+                if reminder.fees > 0.0 {
+                    detail_text.push_str(&format!(" (+{:.2})", reminder.fees));
+                }
+            }
+        }
+
+        if !app.detail_emails.is_empty() {
+            detail_text.push_str("\nEmails:");
+            for email in &app.detail_emails {
+                detail_text.push_str(&format!(
+                    "\n- {} {} to {}",
+                    email.sent_at, email.status, email.recipient
+                ));
+            }
+        }
+
         let paragraph = Paragraph::new(detail_text)
             .block(Block::default().borders(Borders::ALL).title("Details"))
             .alignment(Alignment::Left);
@@ -110,10 +193,10 @@ impl SelectedTab {
             .collect();
 
         let mut list_state = ListState::default();
-        list_state.select(Some(app.selected));
+        list_state.select(Some(app.client_selected));
 
         let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Invoices"))
+            .block(Block::default().borders(Borders::ALL).title("Clients"))
             .highlight_style(
                 Style::default()
                     .fg(Color::Cyan)
@@ -123,44 +206,514 @@ impl SelectedTab {
 
         frame.render_stateful_widget(list, chunks[0], &mut list_state);
 
-        let inv = app.selected_invoice();
-
-        let detail_text = if inv.code.eq("empty_invoice") {
-            "No client added yet.\nPress 'n' to add your very first new client.\nPress 'h' for help."
-                .to_string()
-        } else {
-            format!(
-                "Invoice: {}\nDate: {}\nStatus: {},\nClient:\n- Name: {}\n- Email: {}\n- Phone: {}\n- Address: {}\nTotal: ${:.2}\nDiscount: ${:.2}\nTax: ${:.2}\nNet Total: ${:.2}\nItems:\n{}",
-                inv.code,
-                inv.date,
-                inv.status,
-                inv.client.name,
-                inv.client.email,
-                inv.client.phone,
-                inv.client.address,
-                inv.total,
-                inv.discount,
-                inv.tax,
-                inv.calculate_net_total(),
-                inv.items
-                    .iter()
-                    .map(|item| format!("- {}", item))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            )
+        let client = app.clients.get(app.client_selected);
+        let detail_text = match client {
+            None => {
+                "No client added yet.\nPress 'n' to add your very first new client.\nPress 'h' for help."
+                    .to_string()
+            }
+            Some(client) => format!(
+                "Name: {}\nEmail: {}\nPhone: {}\nAddress: {}\nCountry: {}\nVAT ID: {}\nEndpoint: {}\nReference: {}",
+                client.name,
+                client.email,
+                client.phone,
+                client.address,
+                client.country,
+                client.vat_id,
+                client.endpoint,
+                client.reference,
+            ),
         };
 
         let paragraph = Paragraph::new(detail_text)
             .block(Block::default().borders(Borders::ALL).title("Details"))
             .alignment(Alignment::Left);
 
-        frame.render_widget(paragraph, chunks[1]);
+        let [details_area, projects_area] =
+            Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(chunks[1]);
+        frame.render_widget(paragraph, details_area);
+
+        let Some(client) = client else {
+            return;
+        };
+        let mut lines: Vec<Line> = Vec::new();
+        for budget in app
+            .projects
+            .iter()
+            .filter(|budget| budget.project.client_id == client.id)
+        {
+            lines.extend(project_lines(budget));
+        }
+        if lines.is_empty() {
+            lines.push(Line::raw("No projects yet. Press 'P' to add one."));
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Projects of {}", client.name)),
+            ),
+            projects_area,
+        );
+    }
+
+    pub fn render_tab2(self, app: &App, area: Rect, frame: &mut Frame) {
+        let stats = &app.dashboard;
+        let [top, chart_area] =
+            Layout::vertical([Constraint::Length(8), Constraint::Min(0)]).areas(area);
+        let [summary_area, clients_area, trend_area] = Layout::horizontal([
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+            Constraint::Percentage(30),
+        ])
+        .areas(top);
+
+        let days_to_pay = stats
+            .avg_days_to_pay
+            .map_or("-".to_string(), |days| format!("{days:.1} days"));
+        let summary = vec![
+            Line::from(vec![
+                "Receivables:  ".into(),
+                format!("${:.2}", stats.receivables).bold(),
+            ]),
+            Line::from(vec![
+                "Overdue:      ".into(),
+                format!("${:.2}", stats.overdue)
+                    .fg(tailwind::RED.c400)
+                    .bold(),
+                format!(" ({} invoices)", stats.overdue_count).into(),
+            ]),
+            Line::from(vec!["Days to pay:  ".into(), days_to_pay.bold()]),
+        ];
+        frame.render_widget(
+            Paragraph::new(summary).block(Block::default().borders(Borders::ALL).title("Summary")),
+            summary_area,
+        );
+
+        let clients: Vec<ListItem> = stats
+            .top_clients
+            .iter()
+            .map(|client| ListItem::new(format!("{:<20} ${:.2}", client.name, client.revenue)))
+            .collect();
+        frame.render_widget(
+            List::new(clients).block(Block::default().borders(Borders::ALL).title("Top Clients")),
+            clients_area,
+        );
+
+        let invoiced: Vec<u64> = stats
+            .months
+            .iter()
+            .map(|month| month.invoiced.max(0.0).round() as u64)
+            .collect();
+        frame.render_widget(
+            Sparkline::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Invoiced, 12 months"),
+                )
+                .data(&invoiced)
+                .style(Style::default().fg(tailwind::EMERALD.c400)),
+            trend_area,
+        );
+
+        let mut chart = BarChart::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(Line::from(vec![
+                        "Monthly ".into(),
+                        "invoiced".fg(tailwind::CYAN.c500),
+                        " / ".into(),
+                        "collected".fg(tailwind::EMERALD.c500),
+                    ])),
+            )
+            .bar_width(4)
+            .bar_gap(1)
+            .group_gap(2);
+        for month in &stats.months {
+            let label = month.month.get(2..).unwrap_or(&month.month).to_string();
+            let bars = [
+                Bar::default()
+                    .value(month.invoiced.max(0.0).round() as u64)
+                    .style(Style::default().fg(tailwind::CYAN.c500))
+                    .text_value(String::new()),
+                Bar::default()
+                    .value(month.collected.max(0.0).round() as u64)
+                    .style(Style::default().fg(tailwind::EMERALD.c500))
+                    .text_value(String::new()),
+            ];
+            chart = chart.data(BarGroup::default().label(Line::from(label)).bars(&bars));
+        }
+        frame.render_widget(chart, chart_area);
+    }
+
+    pub fn render_tab3(self, app: &App, area: Rect, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(26), Constraint::Min(0)])
+            .split(area);
+
+        let kinds: Vec<ListItem> = ReportKind::iter()
+            .map(|kind| ListItem::new(kind.title()))
+            .collect();
+        let mut list_state = ListState::default();
+        list_state.select(Some(app.report_kind as usize));
+        let list = List::new(kinds)
+            .block(Block::default().borders(Borders::ALL).title("Reports"))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(list, chunks[0], &mut list_state);
+
+        let title = format!(
+            "{} ({})  t: range  c: csv  p: pdf",
+            app.report_kind.title(),
+            app.report_range
+        );
+        let block = Block::default().borders(Borders::ALL).title(title);
+        let Some(report) = &app.report else {
+            frame.render_widget(Paragraph::new("No report loaded.").block(block), chunks[1]);
+            return;
+        };
+
+        let header = report
+            .columns
+            .iter()
+            .map(|column| Cell::from(*column))
+            .collect::<Row>()
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .bottom_margin(1);
+        let rows = report
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| Cell::from(cell.as_str()))
+                    .collect::<Row>()
+            })
+            .chain(std::iter::once(
+                report
+                    .totals
+                    .iter()
+                    .map(|cell| Cell::from(cell.as_str()))
+                    .collect::<Row>()
+                    .style(Style::default().add_modifier(Modifier::BOLD))
+                    .top_margin(1),
+            ));
+        let widths = vec![Constraint::Fill(1); report.columns.len()];
+        frame.render_widget(
+            Table::new(rows, widths).header(header).block(block),
+            chunks[1],
+        );
+    }
+
+    pub fn render_tab4(self, app: &App, area: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Pending reminders  a: send  d: skip");
+        if app.reminders.is_empty() {
+            frame.render_widget(Paragraph::new("No reminders due.").block(block), area);
+            return;
+        }
+
+        let header = [
+            "Code", "Client", "Due Date", "Days", "Balance", "Level", "Fees",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+        let rows = app.reminders.iter().map(|reminder| {
+            let inv = &reminder.invoice;
+            let level = app
+                .dunning
+                .levels
+                .get(reminder.level)
+                .map_or("", |level| level.name.as_str());
+            Row::new([
+                Cell::from(inv.code.as_str()),
+                Cell::from(inv.client.name.as_str()),
+                Cell::from(inv.due_date.as_str()),
+                Cell::from(reminder.days_overdue.to_string()),
+                Cell::from(format!("{:.2} {}", inv.balance(), inv.currency)),
+                Cell::from(format!("{}. {}", reminder.level + 1, level)),
+                Cell::from(format!("{:.2}", reminder.fees())),
+            ])
+        });
+        let widths = [
+            Constraint::Length(10),
+            Constraint::Fill(2),
+            Constraint::Length(12),
+            Constraint::Length(6),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Length(10),
+        ];
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.reminder_selected));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(table, area, &mut table_state);
+    }
+
+    pub fn render_tab5(self, app: &App, area: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Bank payments  a: accept match  d: reject  i: import statement");
+        if app.matches.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No open bank payments. Press 'i' to import a statement.")
+                    .block(block),
+                area,
+            );
+            return;
+        }
+
+        let header = [
+            "Date",
+            "Payer",
+            "Reference",
+            "Amount",
+            "Invoice",
+            "Balance",
+            "Matched on",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+        let rows = app.matches.iter().map(|suggestion| {
+            let transaction = &suggestion.transaction;
+            let (code, balance) = match &suggestion.invoice {
+                Some(inv) => (
+                    format!("{} {}", inv.code, inv.client.name),
+                    format!("{:.2}", inv.balance()),
+                ),
+                None => ("no match".to_string(), String::new()),
+            };
+            Row::new([
+                Cell::from(transaction.date.as_str()),
+                Cell::from(transaction.name.as_str()),
+                Cell::from(transaction.reference.as_str()),
+                Cell::from(
+                    Text::from(format!(
+                        "{:.2} {}",
+                        transaction.amount, transaction.currency
+                    ))
+                    .right_aligned(),
+                ),
+                Cell::from(code),
+                Cell::from(Text::from(balance).right_aligned()),
+                Cell::from(suggestion.reasons.join(", ")),
+            ])
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Fill(2),
+            Constraint::Fill(3),
+            Constraint::Length(14),
+            Constraint::Fill(2),
+            Constraint::Length(10),
+            Constraint::Length(20),
+        ];
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.match_selected));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(table, area, &mut table_state);
+    }
+
+    pub fn render_tab6(self, app: &App, area: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Time  n: new entry  space: start/stop timer  b: bill client  d: delete");
+        if app.time_entries.is_empty() {
+            frame.render_widget(
+                Paragraph::new(
+                    "No time recorded. Press 'n' to add an entry or space to start a timer.",
+                )
+                .block(block),
+                area,
+            );
+            return;
+        }
+
+        let header = [
+            "Date",
+            "Client",
+            "Project",
+            "Description",
+            "Hours",
+            "Rate",
+            "Amount",
+            "Billed",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+        let now = Local::now();
+        let rows = app.time_entries.iter().map(|entry| {
+            let (minutes, billed) = if entry.is_running() {
+                (timesheet::elapsed(entry, now), "running")
+            } else if entry.invoice_id.is_some() {
+                (entry.minutes, "billed")
+            } else {
+                (entry.minutes, "")
+            };
+            let row = Row::new([
+                Cell::from(entry.date.as_str()),
+                Cell::from(entry.client.name.as_str()),
+                Cell::from(entry.project.as_str()),
+                Cell::from(entry.description.as_str()),
+                Cell::from(Text::from(timesheet::format_minutes(minutes)).right_aligned()),
+                Cell::from(Text::from(format!("{:.2}", entry.rate)).right_aligned()),
+                Cell::from(
+                    Text::from(format!("{:.2}", f64::from(minutes) / 60.0 * entry.rate))
+                        .right_aligned(),
+                ),
+                Cell::from(billed),
+            ]);
+            if entry.invoice_id.is_some() {
+                row.style(Style::default().fg(Color::DarkGray))
+            } else {
+                row
+            }
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(3),
+            Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(11),
+            Constraint::Length(8),
+        ];
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.time_selected));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(table, area, &mut table_state);
+    }
+
+    pub fn render_tab7(self, app: &App, area: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title("Expenses  n: new expense  b: bill client  d: delete");
+        if app.expenses.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No expenses recorded. Press 'n' to add one.").block(block),
+                area,
+            );
+            return;
+        }
+
+        let header = [
+            "Date", "Vendor", "Category", "Client", "Amount", "Receipt", "Billing",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .bottom_margin(1);
+        let rows = app.expenses.iter().map(|expense| {
+            let billing = if expense.is_billed() {
+                "billed"
+            } else if expense.billable {
+                "billable"
+            } else {
+                ""
+            };
+            let row = Row::new([
+                Cell::from(expense.date.as_str()),
+                Cell::from(expense.vendor.as_str()),
+                Cell::from(expense.category.as_str()),
+                Cell::from(
+                    expense
+                        .client
+                        .as_ref()
+                        .map_or("", |client| client.name.as_str()),
+                ),
+                Cell::from(
+                    Text::from(format!("{:.2} {}", expense.amount, expense.currency))
+                        .right_aligned(),
+                ),
+                Cell::from(expense.receipt.as_str()),
+                Cell::from(billing),
+            ]);
+            if expense.is_billed() {
+                row.style(Style::default().fg(Color::DarkGray))
+            } else {
+                row
+            }
+        });
+        let widths = [
+            Constraint::Length(12),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Length(14),
+            Constraint::Fill(2),
+            Constraint::Length(9),
+        ];
+
+        let mut table_state = TableState::default();
+        table_state.select(Some(app.expense_selected));
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶ ");
+        frame.render_stateful_widget(table, area, &mut table_state);
     }
 
     pub const fn palette(self) -> tailwind::Palette {
         match self {
-            Self::TabInvoices => tailwind::CYAN,
-            Self::TabClients => tailwind::INDIGO,
+            Self::Invoices => tailwind::CYAN,
+            Self::Clients => tailwind::INDIGO,
+            Self::Dashboard => tailwind::EMERALD,
+            Self::Reports => tailwind::AMBER,
+            Self::Reminders => tailwind::ROSE,
+            Self::Bank => tailwind::TEAL,
+            Self::Time => tailwind::VIOLET,
+            Self::Expenses => tailwind::ORANGE,
         }
     }
 
@@ -178,6 +731,56 @@ impl SelectedTab {
     }
 }
 
+/// A project's budget bar followed by what was left after each month.
+fn project_lines(budget: &ProjectBudget) -> Vec<Line<'static>> {
+    let project = &budget.project;
+    let mut header = vec![project.name.clone().bold()];
+    if !project.po_number.is_empty() {
+        header.push(format!("  PO {}", project.po_number).into());
+    }
+    if !project.is_active() {
+        header.push(format!("  ({})", project.status).dim());
+    }
+    let mut lines = vec![Line::from(header)];
+    if project.budget <= 0.0 {
+        lines.push(Line::raw(format!("  {:.2} used, no budget", budget.used)));
+        return lines;
+    }
+
+    const WIDTH: usize = 20;
+    let burn = budget.burn();
+    let filled = ((burn.min(1.0) * WIDTH as f64).round() as usize).min(WIDTH);
+    let color = if burn > 1.0 {
+        tailwind::RED.c400
+    } else if burn > 0.8 {
+        tailwind::AMBER.c400
+    } else {
+        tailwind::EMERALD.c400
+    };
+    lines.push(Line::from(vec![
+        "  ".into(),
+        "█".repeat(filled).fg(color),
+        "░".repeat(WIDTH - filled).dim(),
+        format!(
+            " {:.0}%  {:.2} of {:.2} used, {:.2} left",
+            burn * 100.0,
+            budget.used,
+            project.budget,
+            budget.remaining()
+        )
+        .into(),
+    ]));
+    let mut left = project.budget;
+    for (month, amount) in &budget.by_month {
+        left -= amount;
+        lines.push(Line::raw(format!(
+            "  {}  {:>10.2} used  {:>10.2} left",
+            month, amount, left
+        )));
+    }
+    lines
+}
+
 pub fn render_tabs(app: &App, area: Rect, buf: &mut Buffer) {
     let titles = SelectedTab::iter().map(SelectedTab::title);
     let highlight_style = (Color::default(), app.current_tab.palette().c700);
@@ -190,12 +793,38 @@ pub fn render_tabs(app: &App, area: Rect, buf: &mut Buffer) {
         .render(area, buf)
 }
 
+/// Colored badge for an invoice status.
+fn status_badge(status: &str) -> Span<'static> {
+    let color = match status.to_lowercase().as_str() {
+        "paid" => tailwind::GREEN.c600,
+        "sent" => tailwind::BLUE.c600,
+        "overdue" => tailwind::RED.c600,
+        "void" | "cancelled" => tailwind::ZINC.c700,
+        _ => tailwind::SLATE.c500,
+    };
+    format!(" {status} ").fg(tailwind::SLATE.c50).bg(color)
+}
+
 fn render_title(area: Rect, buf: &mut Buffer) {
     "shayyz-code's invoice-rs".bold().render(area, buf);
 }
 
-fn render_footer(area: Rect, buf: &mut Buffer) {
-    Line::raw("◄ ► to change tab | Press q to quit")
+fn render_footer(app: &App, area: Rect, buf: &mut Buffer) {
+    match &app.status {
+        Some(status) => Line::raw(status.as_str())
+            .fg(tailwind::EMERALD.c300)
+            .centered()
+            .render(area, buf),
+        None => Line::raw("◄ ► to change tab | s/S to sort | h for help | Press q to quit")
+            .centered()
+            .render(area, buf),
+    }
+}
+
+fn render_banner(banner: &str, area: Rect, buf: &mut Buffer) {
+    Line::raw(banner)
+        .fg(tailwind::AMBER.c100)
+        .bg(tailwind::RED.c800)
         .centered()
         .render(area, buf);
 }
@@ -203,8 +832,9 @@ fn render_footer(area: Rect, buf: &mut Buffer) {
 pub fn draw(frame: &mut Frame, app: &App) {
     use Constraint::{Length, Min};
     let area = frame.area();
-    let vertical = Layout::vertical([Length(1), Min(0), Length(1)]);
-    let [header_area, inner_area, footer_area] = vertical.areas(area);
+    let banner_height = if app.banner.is_some() { 1 } else { 0 };
+    let vertical = Layout::vertical([Length(1), Length(banner_height), Min(0), Length(1)]);
+    let [header_area, banner_area, inner_area, footer_area] = vertical.areas(area);
 
     let horizontal = Layout::horizontal([Min(0), Length(20)]);
     let [tabs_area, title_area] = horizontal.areas(header_area);
@@ -212,13 +842,34 @@ pub fn draw(frame: &mut Frame, app: &App) {
     render_title(title_area, frame.buffer_mut());
     render_tabs(app, tabs_area, frame.buffer_mut());
     app.current_tab.render(app, inner_area, frame);
-    render_footer(footer_area, frame.buffer_mut());
+    if let Some(banner) = &app.banner {
+        render_banner(banner, banner_area, frame.buffer_mut());
+    }
+    render_footer(app, footer_area, frame.buffer_mut());
 
     if let (Mode::Editing, Some(form)) = (&app.mode, &app.form) {
         draw_form(frame, form);
     }
+    if let (Mode::Editing, Some(form)) = (&app.mode, &app.client_form) {
+        client_form::draw_form(frame, form);
+    }
+    if let (Mode::Editing, Some(form)) = (&app.mode, &app.entry_form) {
+        entry_form::draw_form(frame, form);
+    }
+
+    if let Some(wizard) = &app.wizard {
+        draw_wizard(frame, wizard);
+    }
 
     if let (Mode::Modal(_), Some(modal)) = (&app.mode, &app.modal) {
         draw_modal(frame, modal);
     }
+
+    if let Some((title, entries)) = &app.history {
+        draw_history(frame, title, entries);
+    }
+
+    if app.show_help {
+        draw_help(frame, &app.keymap, KeyContext::from(&app.mode));
+    }
 }
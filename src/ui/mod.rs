@@ -1,5 +1,10 @@
 pub mod button;
 pub mod client_form;
+pub mod entry_form;
+pub mod help;
+pub mod history;
+pub mod import_wizard;
+pub mod input;
 pub mod invoice_form;
 pub mod layout;
 pub mod modal;
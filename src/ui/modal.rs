@@ -7,12 +7,13 @@ use ratatui::{
 };
 use std::fmt;
 
-use crate::{app::App, ui::button::Button};
+use crate::ui::{button::Button, input::InputField};
 
 #[derive(Clone, Copy)]
 pub enum ModalType {
     Alert,
     Confirm,
+    Prompt,
 }
 
 impl fmt::Display for ModalType {
@@ -20,6 +21,59 @@ impl fmt::Display for ModalType {
         match self {
             ModalType::Alert => write!(f, "Alert"),
             ModalType::Confirm => write!(f, "Confirm"),
+            ModalType::Prompt => write!(f, "Prompt"),
+        }
+    }
+}
+
+/// What to do once a modal is confirmed. `App::dispatch` runs it after the
+/// modal has been closed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    SaveInvoice,
+    SaveClient,
+    DeleteInvoice(u32),
+    VoidInvoice(u32),
+    CreditNote(u32),
+    RecordPayment(u32),
+    EmailInvoice(u32),
+    SendReminder(u32),
+    SkipReminder(u32),
+    SetReportRange,
+    /// Bank transaction ids.
+    AcceptMatch(u32),
+    RejectMatch(u32),
+    ImportStatement,
+    /// Client id.
+    BillTime(u32),
+    /// Time entry id.
+    DeleteTimeEntry(u32),
+    /// Client id.
+    BillExpenses(u32),
+    /// Expense id.
+    DeleteExpense(u32),
+}
+
+impl Action {
+    pub fn invoice_id(&self) -> Option<u32> {
+        match self {
+            Action::SaveInvoice
+            | Action::SaveClient
+            | Action::SetReportRange
+            | Action::AcceptMatch(_)
+            | Action::RejectMatch(_)
+            | Action::ImportStatement
+            | Action::BillTime(_)
+            | Action::DeleteTimeEntry(_)
+            | Action::BillExpenses(_)
+            | Action::DeleteExpense(_) => None,
+            Action::DeleteInvoice(id)
+            | Action::VoidInvoice(id)
+            | Action::CreditNote(id)
+            | Action::RecordPayment(id)
+            | Action::EmailInvoice(id)
+            | Action::SendReminder(id)
+            | Action::SkipReminder(id) => Some(*id),
         }
     }
 }
@@ -31,29 +85,39 @@ pub enum ActiveFieldForConfirm {
 }
 
 pub struct Modal {
-    pub invoice_id: Option<u32>,
     pub kind: ModalType,
     pub content: String,
     pub active: ActiveFieldForConfirm,
-    pub action: Option<Box<dyn FnMut(&mut App)>>,
+    /// Text typed into a prompt modal.
+    pub input: InputField,
+    pub action: Option<Action>,
 }
 
 impl Modal {
-    pub fn new(
-        invoice_id: Option<u32>,
-        content: String,
-        kind: ModalType,
-        action: Option<Box<dyn FnMut(&mut App)>>,
-    ) -> Self {
+    pub fn new(content: String, kind: ModalType, action: Option<Action>) -> Self {
         Self {
-            invoice_id,
             kind,
             content,
             active: ActiveFieldForConfirm::Cancel,
+            input: InputField::new("", ""),
             action,
         }
     }
 
+    pub fn alert(content: String) -> Self {
+        Self::new(content, ModalType::Alert, None)
+    }
+
+    pub fn confirm(content: String, action: Action) -> Self {
+        Self::new(content, ModalType::Confirm, Some(action))
+    }
+
+    pub fn prompt(content: String, action: Action) -> Self {
+        let mut modal = Self::new(content, ModalType::Prompt, Some(action));
+        modal.input.focus();
+        modal
+    }
+
     pub fn next_field(&mut self) {
         self.active = match self.active {
             ActiveFieldForConfirm::Confirm => ActiveFieldForConfirm::Cancel,
@@ -61,25 +125,26 @@ impl Modal {
         };
     }
 
-    pub fn press_field<F>(&mut self, mut callback_on_close: F, app: &mut App)
-    where
-        F: FnMut(&mut App),
-    {
-        match self.active {
-            ActiveFieldForConfirm::Confirm => {
-                if let Some(action) = self.action.as_deref_mut() {
-                    action(app);
-                }
-            }
-            _ => {}
+    pub fn previous_field(&mut self) {
+        self.active = match self.active {
+            ActiveFieldForConfirm::Confirm => ActiveFieldForConfirm::Cancel,
+            ActiveFieldForConfirm::Cancel => ActiveFieldForConfirm::Confirm,
+        };
+    }
+
+    /// The action to run when Enter is pressed, if any.
+    pub fn submitted_action(&self) -> Option<Action> {
+        match self.kind {
+            ModalType::Alert => None,
+            ModalType::Confirm if self.active == ActiveFieldForConfirm::Cancel => None,
+            ModalType::Confirm | ModalType::Prompt => self.action,
         }
-        callback_on_close(app);
     }
 }
 
 impl fmt::Display for Modal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(invoice_id) = &self.invoice_id {
+        if let Some(invoice_id) = self.action.and_then(|action| action.invoice_id()) {
             write!(
                 f,
                 "Invoice ID: {}\nKind: {}\nContent: {}",
@@ -167,6 +232,16 @@ pub fn draw_modal(frame: &mut Frame, modal: &Modal) {
             cancel_btn.render(btn_layout[0], frame.buffer_mut(), &mut String::new());
             confirm_btn.render(btn_layout[1], frame.buffer_mut(), &mut String::new());
         }
+
+        ModalType::Prompt => {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title("Enter to confirm, Esc to cancel");
+            let input_area = block.inner(inner_layout[1]);
+
+            frame.render_widget(block, inner_layout[1]);
+            frame.render_widget(&modal.input, input_area);
+        }
     }
 }
 
@@ -13,6 +13,12 @@ pub struct SplashScreen {
     done: bool,
 }
 
+impl Default for SplashScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SplashScreen {
     pub fn new() -> Self {
         Self {
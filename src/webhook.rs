@@ -0,0 +1,204 @@
+use chrono::{Local, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::db::Database;
+use crate::error::AppError;
+use crate::models::WebhookDelivery;
+
+/// How often the dispatcher looks for new events and due retries.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Longest wait between two attempts, however many have failed.
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+
+/// A URL that receives events, from `[[webhooks.endpoints]]`.
+#[derive(Clone, Deserialize)]
+pub struct Endpoint {
+    pub url: String,
+    /// Key for the `X-Invoice-Rs-Signature` HMAC.
+    pub secret: String,
+    /// Event types to send, e.g. `["invoice.sent", "payment.recorded"]`;
+    /// empty sends all of them.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl Endpoint {
+    fn wants(&self, kind: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|event| event == kind)
+    }
+}
+
+/// Webhook settings, from the `[webhooks]` table of the config file.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub endpoints: Vec<Endpoint>,
+    /// Attempts per delivery before it is given up as failed.
+    pub max_attempts: u32,
+    /// Wait before the first retry; it doubles with each failure after that.
+    pub backoff_secs: i64,
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: 8,
+            backoff_secs: 30,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// Deliver events on a background thread for as long as the program runs.
+/// Events queued while no endpoint is configured are dropped.
+pub fn spawn(db: Arc<Mutex<Database>>, config: WebhookConfig) -> JoinHandle<()> {
+    thread::spawn(move || {
+        loop {
+            // Failed attempts are recorded on the delivery; anything else is
+            // retried on the next round.
+            let _ = run_once(&db, &config, Utc::now().timestamp());
+            thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
+
+/// Queue overdue events, fan new events out to the endpoints and attempt
+/// the deliveries due at `now`. Returns how many were delivered. The app,
+/// `serve` and `webhooks --deliver` may all run this against the same
+/// database; each delivery is claimed first so only one of them sends it.
+pub fn run_once(db: &Mutex<Database>, config: &WebhookConfig, now: i64) -> Result<usize, AppError> {
+    let owner = uuid::Uuid::new_v4().to_string();
+    {
+        let db = db.lock().unwrap();
+        db.emit_overdue(&Local::now().date_naive().to_string())?;
+        for event in db.undispatched_events()? {
+            let urls: Vec<&str> = config
+                .endpoints
+                .iter()
+                .filter(|endpoint| endpoint.wants(&event.kind))
+                .map(|endpoint| endpoint.url.as_str())
+                .collect();
+            db.queue_deliveries(event.id, &urls, now)?;
+        }
+    }
+
+    let mut delivered = 0;
+    loop {
+        // Leave the attempt time to finish before the claim lapses.
+        let lease_until = Utc::now().timestamp().max(now) + 2 * config.timeout_secs as i64;
+        let claimed = db
+            .lock()
+            .unwrap()
+            .claim_delivery(now, &owner, lease_until)?;
+        let Some(mut delivery) = claimed else {
+            break;
+        };
+        attempt(&mut delivery, config, now);
+        if delivery.status == "delivered" {
+            delivered += 1;
+        }
+        db.lock().unwrap().update_delivery(&delivery, &owner)?;
+    }
+    Ok(delivered)
+}
+
+/// POST the event once and update the delivery with the outcome.
+fn attempt(delivery: &mut WebhookDelivery, config: &WebhookConfig, now: i64) {
+    delivery.attempts += 1;
+    let result = match config.endpoints.iter().find(|e| e.url == delivery.url) {
+        Some(endpoint) => post(endpoint, delivery, config, now),
+        None => Err("endpoint no longer configured".to_string()),
+    };
+    match result {
+        Ok(code) if (200..300).contains(&code) => {
+            delivery.status = "delivered".to_string();
+            delivery.response_code = Some(code);
+            delivery.error.clear();
+        }
+        Ok(code) => {
+            delivery.response_code = Some(code);
+            retry(delivery, config, now, format!("HTTP {}", code));
+        }
+        Err(e) => {
+            delivery.response_code = None;
+            retry(delivery, config, now, e);
+        }
+    }
+}
+
+fn retry(delivery: &mut WebhookDelivery, config: &WebhookConfig, now: i64, error: String) {
+    delivery.error = error;
+    if delivery.attempts >= config.max_attempts {
+        delivery.status = "failed".to_string();
+        return;
+    }
+    let factor = 1_i64 << (delivery.attempts - 1).min(20);
+    delivery.next_attempt_at = now + (config.backoff_secs * factor).min(MAX_BACKOFF_SECS);
+}
+
+fn post(
+    endpoint: &Endpoint,
+    delivery: &WebhookDelivery,
+    config: &WebhookConfig,
+    now: i64,
+) -> Result<u16, String> {
+    let body = body(delivery);
+    let timestamp = now.to_string();
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(config.timeout_secs)))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let response = agent
+        .post(&endpoint.url)
+        .header("Content-Type", "application/json")
+        .header(
+            "User-Agent",
+            concat!("invoice-rs/", env!("CARGO_PKG_VERSION")),
+        )
+        .header("X-Invoice-Rs-Event", &delivery.event.kind)
+        .header("X-Invoice-Rs-Delivery", &delivery.id.to_string())
+        .header("X-Invoice-Rs-Timestamp", &timestamp)
+        .header(
+            "X-Invoice-Rs-Signature",
+            &format!("sha256={}", sign(&endpoint.secret, &timestamp, &body)),
+        )
+        .send(&body)
+        .map_err(|e| e.to_string())?;
+    Ok(response.status().as_u16())
+}
+
+/// The JSON posted for an event; `data` holds the invoice and, for
+/// `payment.recorded`, the payment.
+fn body(delivery: &WebhookDelivery) -> String {
+    let event = &delivery.event;
+    format!(
+        r#"{{"id":{},"type":{},"created_at":{},"data":{}}}"#,
+        event.id,
+        serde_json::Value::from(event.kind.as_str()),
+        serde_json::Value::from(event.created_at.as_str()),
+        event.payload
+    )
+}
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>`, so receivers can check both
+/// the sender and that the request isn't a replay.
+pub fn sign(secret: &str, timestamp: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
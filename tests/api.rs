@@ -0,0 +1,311 @@
+//! The API router on an in-memory database, driven with one request at a
+//! time and no listening socket.
+
+use axum::Router;
+use axum::body::{self, Body};
+use axum::http::{Method, Request, StatusCode, header};
+use invoice_rs::api::{self, ApiState};
+use invoice_rs::config::Config;
+use invoice_rs::db::Database;
+use invoice_rs::error::AppError;
+use invoice_rs::models::Client;
+use lopdf::{Document, Object, dictionary};
+use serde_json::{Value, json};
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+const TOKEN: &str = "test-token";
+
+/// Stands in for headless Chrome: an empty page, with the HTML it was given
+/// kept as the subject so tests can see what was printed.
+fn fake_printer(html: &str) -> Result<Vec<u8>, AppError> {
+    let mut doc = Document::with_version("1.7");
+    let pages = doc.new_object_id();
+    let page = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages,
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+    });
+    doc.objects.insert(
+        pages,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages,
+    });
+    let info = doc.add_object(dictionary! {
+        "Subject" => Object::string_literal(html),
+    });
+    doc.trailer.set("Root", catalog);
+    doc.trailer.set("Info", info);
+    let mut pdf = Vec::new();
+    doc.save_to(&mut pdf)?;
+    Ok(pdf)
+}
+
+struct TestApi {
+    router: Router,
+    runtime: Runtime,
+}
+
+impl TestApi {
+    /// A router over a fresh database holding one client, with id 1.
+    fn new() -> Self {
+        let db = Database::in_memory().unwrap();
+        db.add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+        let mut config = Config::default();
+        config.pdf.printer = fake_printer;
+        Self {
+            router: api::router(ApiState::new(db, TOKEN, &config)),
+            runtime: Runtime::new().unwrap(),
+        }
+    }
+
+    fn send(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Vec<u8>, Option<String>) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+        self.runtime.block_on(async {
+            let response = self.router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let disposition = response
+                .headers()
+                .get(header::CONTENT_DISPOSITION)
+                .map(|value| value.to_str().unwrap().to_string());
+            let bytes = body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, bytes.to_vec(), disposition)
+        })
+    }
+
+    /// Send with the right token and parse the JSON reply, if any.
+    fn json(&self, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let (status, bytes, _) = self.send(method, uri, Some(TOKEN), body);
+        let value = if bytes.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap()
+        };
+        (status, value)
+    }
+
+    fn create_invoice(&self, code: &str, date: &str) -> Value {
+        let (status, invoice) = self.json(
+            Method::POST,
+            "/invoices",
+            Some(json!({
+                "code": code,
+                "client_id": 1,
+                "currency": "EUR",
+                "date": date,
+                "items": [{ "title": "Consulting", "price": 90.0, "quantity": 8, "remark": null }],
+            })),
+        );
+        assert_eq!(status, StatusCode::CREATED, "{}", invoice);
+        invoice
+    }
+}
+
+#[test]
+fn refuses_requests_without_the_right_token() {
+    let api = TestApi::new();
+    for token in [None, Some("wrong-token"), Some("")] {
+        let (status, body, _) = api.send(Method::GET, "/invoices", token, None);
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "missing or wrong bearer token");
+    }
+    let (status, _, _) = api.send(Method::GET, "/openapi.json", None, None);
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn creates_reads_updates_and_deletes_an_invoice() {
+    let api = TestApi::new();
+    let invoice = api.create_invoice("INV-1001", "2026-01-15");
+    let id = invoice["id"].as_u64().unwrap();
+    assert_eq!(invoice["status"], "draft");
+    assert_eq!(invoice["total"], 720.0);
+    assert_eq!(invoice["client"]["name"], "Globex B.V.");
+
+    let uri = format!("/invoices/{}", id);
+    let (status, fetched) = api.json(Method::GET, &uri, None);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(fetched["code"], "INV-1001");
+    assert_eq!(fetched["items"].as_array().unwrap().len(), 1);
+
+    let (status, updated) = api.json(
+        Method::PUT,
+        &uri,
+        Some(json!({ "client_id": 1, "currency": "EUR", "total": 500.0, "discount": 20.0 })),
+    );
+    assert_eq!(status, StatusCode::OK, "{}", updated);
+    assert_eq!(updated["code"], "INV-1001");
+    assert_eq!(updated["total"], 500.0);
+    assert_eq!(updated["discount"], 20.0);
+
+    let (status, errors) = api.json(
+        Method::PUT,
+        &uri,
+        Some(json!({ "client_id": 1, "currency": "EURO" })),
+    );
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", errors);
+
+    let (status, _) = api.json(Method::DELETE, &uri, None);
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = api.json(Method::GET, &uri, None);
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn pages_invoices_with_a_cursor() {
+    let api = TestApi::new();
+    for (code, date) in [
+        ("INV-1001", "2026-01-10"),
+        ("INV-1002", "2026-01-20"),
+        ("INV-1003", "2026-01-30"),
+    ] {
+        api.create_invoice(code, date);
+    }
+    let codes = |page: &Value| -> Vec<String> {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|invoice| invoice["code"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (status, first) = api.json(Method::GET, "/invoices?limit=2", None);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(codes(&first), ["INV-1003", "INV-1002"]);
+    let next = first["next"].as_u64().unwrap();
+
+    let (_, second) = api.json(
+        Method::GET,
+        &format!("/invoices?limit=2&after={}", next),
+        None,
+    );
+    assert_eq!(codes(&second), ["INV-1001"]);
+    assert!(second["next"].is_null());
+
+    let (status, _) = api.json(Method::GET, "/invoices?after=999", None);
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn returns_the_invoice_as_a_pdf() {
+    let api = TestApi::new();
+    let invoice = api.create_invoice("INV-1001", "2026-01-15");
+    let uri = format!("/invoices/{}/pdf", invoice["id"]);
+
+    let (status, pdf, disposition) = api.send(Method::GET, &uri, Some(TOKEN), None);
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        disposition.as_deref(),
+        Some("attachment; filename=\"invoice_INV-1001.pdf\"")
+    );
+    let doc = Document::load_mem(&pdf).unwrap();
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .unwrap();
+    let printed = info.get(b"Subject").and_then(Object::as_str).unwrap();
+    assert!(String::from_utf8_lossy(printed).contains("INV-1001"));
+
+    let (status, _, _) = api.send(Method::GET, "/invoices/999/pdf", Some(TOKEN), None);
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn pages_clients_by_id() {
+    let api = TestApi::new();
+    for email in ["a@initech.example", "b@initech.example"] {
+        let (status, client) = api.json(
+            Method::POST,
+            "/clients",
+            Some(json!({ "name": "Initech", "email": email })),
+        );
+        assert_eq!(status, StatusCode::CREATED, "{}", client);
+    }
+    let ids = |page: &Value| -> Vec<u64> {
+        page["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|client| client["id"].as_u64().unwrap())
+            .collect()
+    };
+
+    let (_, first) = api.json(Method::GET, "/clients?limit=2", None);
+    assert_eq!(ids(&first), [1, 2]);
+    assert_eq!(first["next"], 2);
+    let (_, second) = api.json(Method::GET, "/clients?limit=2&after=2", None);
+    assert_eq!(ids(&second), [3]);
+    assert!(second["next"].is_null());
+}
+
+#[test]
+fn refuses_payments_on_drafts_and_credit_notes() {
+    let api = TestApi::new();
+    let invoice = api.create_invoice("INV-1001", "2026-01-15");
+    let payments = format!("/invoices/{}/payments", invoice["id"]);
+    let (status, error) = api.json(Method::POST, &payments, Some(json!({ "amount": 100.0 })));
+    assert_eq!(status, StatusCode::CONFLICT, "{}", error);
+
+    let (status, sent) = api.json(
+        Method::PUT,
+        &format!("/invoices/{}", invoice["id"]),
+        Some(json!({ "client_id": 1, "currency": "EUR", "total": 720.0, "status": "sent" })),
+    );
+    assert_eq!(status, StatusCode::OK, "{}", sent);
+    for amount in [0.0, -5.0] {
+        let (status, _) = api.json(Method::POST, &payments, Some(json!({ "amount": amount })));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let (status, paid) = api.json(Method::POST, &payments, Some(json!({ "amount": 720.0 })));
+    assert_eq!(status, StatusCode::CREATED, "{}", paid);
+    assert_eq!(paid["status"], "paid");
+
+    let (status, note) = api.json(
+        Method::POST,
+        &format!("/invoices/{}/credit-note", invoice["id"]),
+        None,
+    );
+    assert_eq!(status, StatusCode::CREATED, "{}", note);
+    let (status, error) = api.json(
+        Method::POST,
+        &format!("/invoices/{}/payments", note["id"]),
+        Some(json!({ "amount": 10.0 })),
+    );
+    assert_eq!(status, StatusCode::CONFLICT, "{}", error);
+}
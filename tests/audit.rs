@@ -0,0 +1,74 @@
+//! The audit log's entries and hash chain.
+
+use invoice_rs::audit;
+use invoice_rs::db::Database;
+use invoice_rs::models::{AuditEntry, Client, Invoice, Item};
+
+fn database() -> (Database, u32) {
+    let db = Database::in_memory().unwrap();
+    let client_id = db
+        .add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+    let id = db
+        .add_invoice(&Invoice {
+            code: "INV-1001".to_string(),
+            client: db.get_client(client_id).unwrap().unwrap(),
+            total: 720.0,
+            currency: "EUR".to_string(),
+            status: "draft".to_string(),
+            date: "2026-01-15".to_string(),
+            items: vec![Item::new("Consulting", Some(90.0), Some(8), None)],
+            ..Invoice::default()
+        })
+        .unwrap();
+    (db, id)
+}
+
+#[test]
+fn saving_an_unchanged_invoice_logs_nothing() {
+    let (db, id) = database();
+    let invoice = db.get_invoice(id).unwrap().unwrap();
+    let logged = db.get_audit_log(Some(id)).unwrap().len();
+
+    db.update_invoice(&invoice).unwrap();
+    assert_eq!(db.get_audit_log(Some(id)).unwrap().len(), logged);
+
+    let mut changed = invoice.clone();
+    changed.items[0].quantity = Some(10);
+    changed
+        .items
+        .push(Item::new("Travel", Some(40.0), Some(1), None));
+    db.update_invoice(&changed).unwrap();
+    let log = db.get_audit_log(Some(id)).unwrap();
+    assert_eq!(log.len(), logged + 1);
+    assert!(log.last().unwrap().changes.contains("\"items\""));
+    // The first item kept its row.
+    let items = db.get_items(id).unwrap();
+    assert_eq!(items[0].id, invoice.items[0].id);
+    assert_eq!(items.len(), 2);
+}
+
+#[test]
+fn the_hash_covers_what_the_entry_belongs_to() {
+    let (db, _) = database();
+    let log = db.get_audit_log(None).unwrap();
+    assert_eq!(audit::verify(&log), Ok(()));
+
+    let tampered: [fn(&mut AuditEntry); 3] = [
+        |entry| entry.entity = "payment".to_string(),
+        |entry| entry.entity_id += 1,
+        |entry| entry.invoice_id = None,
+    ];
+    for tamper in tampered {
+        let mut log = log.clone();
+        let last = log.len() - 1;
+        tamper(&mut log[last]);
+        assert_eq!(audit::verify(&log), Err(log[last].id));
+    }
+}
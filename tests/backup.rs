@@ -0,0 +1,98 @@
+//! Merging a backup into a database that already holds other data.
+
+use invoice_rs::backup::Backup;
+use invoice_rs::db::Database;
+use invoice_rs::models::{Client, Invoice, Project, TimeEntry};
+
+#[test]
+fn a_client_whose_id_is_taken_is_added_under_a_new_id_with_its_records() {
+    let source = Database::in_memory().unwrap();
+    let client_id = source
+        .add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+    let client = source.get_client(client_id).unwrap().unwrap();
+    let project_id = source
+        .add_project(&Project {
+            id: 0,
+            client_id,
+            name: "Rebranding".to_string(),
+            po_number: String::new(),
+            budget: 0.0,
+            rate: 90.0,
+            status: "active".to_string(),
+        })
+        .unwrap();
+    source
+        .add_invoice(&Invoice {
+            code: "INV-1001".to_string(),
+            client: client.clone(),
+            total: 720.0,
+            currency: "EUR".to_string(),
+            status: "draft".to_string(),
+            date: "2026-01-15".to_string(),
+            ..Invoice::default()
+        })
+        .unwrap();
+    source
+        .add_time_entry(&TimeEntry {
+            id: 0,
+            client: client.clone(),
+            project: "Rebranding".to_string(),
+            project_id: Some(project_id),
+            description: "Logo".to_string(),
+            date: "2026-01-14".to_string(),
+            minutes: 120,
+            rate: 90.0,
+            started_at: None,
+            invoice_id: None,
+        })
+        .unwrap();
+    let backup = Backup::export(&source).unwrap();
+
+    // Client 1 here is somebody else.
+    let target = Database::in_memory().unwrap();
+    target
+        .add_client(&Client::new(
+            0,
+            "Initech",
+            "",
+            "billing@initech.example",
+            "Main St 1",
+        ))
+        .unwrap();
+
+    let report = target.restore_merge(&backup).unwrap();
+    assert_eq!(report.clients, 1);
+    assert_eq!(report.invoices, 1);
+    // The target keeps its own audit log, which is reported as well.
+    assert_eq!(report.conflicts.len(), 2, "{:?}", report.conflicts);
+    assert!(report.conflicts[1].ends_with("added as client 2"));
+
+    assert_eq!(target.get_client(1).unwrap().unwrap().name, "Initech");
+    assert_eq!(target.get_client(2).unwrap().unwrap().name, "Globex B.V.");
+    let invoices = target.get_all_invoices().unwrap();
+    assert_eq!(invoices.len(), 1);
+    assert_eq!(invoices[0].client.id, 2);
+    assert_eq!(invoices[0].client.name, "Globex B.V.");
+    let projects = target.get_projects().unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!(projects[0].client_id, 2);
+    let entries = target.get_time_entries().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].client.id, 2);
+    assert_eq!(entries[0].project_id, Some(projects[0].id));
+
+    // Merging the same backup again adds nothing.
+    let report = target.restore_merge(&backup).unwrap();
+    assert_eq!(report.clients, 0);
+    assert_eq!(report.invoices, 0);
+    assert_eq!(report.rows, 0);
+    assert_eq!(report.conflicts.len(), 1, "{:?}", report.conflicts);
+    assert_eq!(target.get_all_clients().unwrap().len(), 2);
+}
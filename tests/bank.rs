@@ -0,0 +1,171 @@
+//! Reading CAMT.053, OFX and CSV bank statements.
+
+use invoice_rs::bank::{self, BankConfig, CsvProfile, DateOrder, Decimal, StatementFormat};
+
+const CAMT053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Id>STMT-1</Id>
+      <Acct><Id><IBAN>DE02120300000000202051</IBAN></Id></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">720.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2026-02-03</Dt></BookgDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls>
+          <TxDtls>
+            <RltdPties><Dbtr><Nm>Globex B.V.</Nm></Dbtr></RltdPties>
+            <RmtInf><Ustrd>Invoice INV-1001</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1250.50</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><DtTm>2026-02-04T09:30:00</DtTm></BookgDt>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-1</EndToEndId></Refs>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">1000.50</Amt></TxAmt></AmtDtls>
+          </TxDtls>
+          <TxDtls>
+            <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">250.00</Amt></TxAmt></AmtDtls>
+          </TxDtls>
+        </NtryDtls>
+        <AcctSvcrRef>BATCH-1</AcctSvcrRef>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">99.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><Dt>2026-02-05</Dt></BookgDt>
+        <AcctSvcrRef>REF-2</AcctSvcrRef>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+const OFX: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>USD
+<BANKACCTFROM><ACCTID>12345</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20260203120000[-5:EST]
+<TRNAMT>720,50
+<FITID>F-1
+<NAME>Initech &amp; Co
+<MEMO>INV-1002
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260204
+<TRNAMT>-15.00
+<PAYEE>Bank fees
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+#[test]
+fn reads_camt053_entries_and_batches() {
+    assert!(StatementFormat::detect(CAMT053) == StatementFormat::Camt053);
+    let transactions = bank::parse_camt053(CAMT053).unwrap();
+    assert_eq!(transactions.len(), 4);
+
+    let first = &transactions[0];
+    assert_eq!(first.bank_id, "DE02120300000000202051:REF-1");
+    assert_eq!(first.date, "2026-02-03");
+    assert_eq!(first.amount, 720.0);
+    assert_eq!(first.currency, "EUR");
+    assert_eq!(first.name, "Globex B.V.");
+    assert_eq!(first.reference, "Invoice INV-1001");
+
+    // A batch is split into its transactions.
+    let (one, two) = (&transactions[1], &transactions[2]);
+    assert_eq!(one.bank_id, "DE02120300000000202051:E2E-1");
+    assert_eq!(one.amount, 1000.5);
+    assert_eq!(one.date, "2026-02-04");
+    assert_eq!(two.bank_id, "DE02120300000000202051:BATCH-1/2");
+    assert_eq!(two.amount, 250.0);
+
+    assert_eq!(transactions[3].amount, -99.0);
+    assert!(bank::parse_camt053("<Document/>").is_err());
+}
+
+#[test]
+fn reads_ofx_transactions() {
+    assert!(StatementFormat::detect(OFX) == StatementFormat::Ofx);
+    let transactions = bank::parse_ofx(OFX).unwrap();
+    assert_eq!(transactions.len(), 2);
+
+    let credit = &transactions[0];
+    assert_eq!(credit.bank_id, "12345:F-1");
+    assert_eq!(credit.date, "2026-02-03");
+    assert_eq!(credit.amount, 720.5);
+    assert_eq!(credit.currency, "USD");
+    assert_eq!(credit.name, "Initech & Co");
+    assert_eq!(credit.reference, "INV-1002");
+
+    let debit = &transactions[1];
+    assert_eq!(debit.amount, -15.0);
+    assert_eq!(debit.name, "Bank fees");
+    assert_eq!(debit.bank_id, "12345:2026-02-04|-15.00|Bank fees||1");
+}
+
+#[test]
+fn reads_csv_by_column_name_with_guessed_separators() {
+    let text = "Buchungstag;Betrag;Auftraggeber;Verwendungszweck\n\
+                03.02.2026;1.234,50;Globex B.V.;INV-1001\n\
+                04.02.2026;1,234;Initech;INV-1002\n\
+                05.02.2026;-12,5;Bank;Fees\n\
+                05.02.2026;-12,5;Bank;Fees\n";
+    let transactions = bank::parse_csv(text, &BankConfig::default()).unwrap();
+    let amounts: Vec<f64> = transactions.iter().map(|t| t.amount).collect();
+    assert_eq!(amounts, [1234.5, 1234.0, -12.5, -12.5]);
+    assert_eq!(transactions[0].date, "2026-02-03");
+    assert_eq!(transactions[0].name, "Globex B.V.");
+    assert_eq!(transactions[0].reference, "INV-1001");
+    // Identical rows still get ids of their own.
+    assert_ne!(transactions[2].bank_id, transactions[3].bank_id);
+
+    let error = bank::parse_csv("date,amount\n31.02.2026,5\n", &BankConfig::default());
+    assert_eq!(error.err().unwrap(), "line 2: bad date '31.02.2026'");
+    assert!(bank::parse_csv("when,what\n", &BankConfig::default()).is_err());
+}
+
+#[test]
+fn uses_the_csv_profile_matching_the_headers() {
+    let config = BankConfig {
+        csv: vec![
+            CsvProfile {
+                headers: vec!["Check Number".to_string()],
+                decimal: Decimal::Dot,
+                date_order: DateOrder::Mdy,
+            },
+            CsvProfile {
+                headers: Vec::new(),
+                decimal: Decimal::Comma,
+                date_order: DateOrder::Dmy,
+            },
+        ],
+    };
+    let us = "Date,Amount,Description,Check Number\n02/03/2026,\"1,234\",INV-1001,\n";
+    let transactions = bank::parse_csv(us, &config).unwrap();
+    assert_eq!(transactions[0].date, "2026-02-03");
+    assert_eq!(transactions[0].amount, 1234.0);
+
+    // Other files fall through to the profile without headers.
+    let other = "date;amount\n02/03/2026;1,234\n";
+    let transactions = bank::parse_csv(other, &config).unwrap();
+    assert_eq!(transactions[0].date, "2026-03-02");
+    assert_eq!(transactions[0].amount, 1.234);
+}
@@ -0,0 +1,75 @@
+//! Which reminders come up, and what they charge.
+
+use chrono::NaiveDate;
+use invoice_rs::db::Database;
+use invoice_rs::dunning::{self, DunningConfig, DunningLevel};
+use invoice_rs::models::{Client, Invoice, Item, Reminder};
+
+fn level(name: &str, days: i64) -> DunningLevel {
+    DunningLevel {
+        name: name.to_string(),
+        days,
+        template: "reminder".to_string(),
+        fee: 10.0,
+        interest: 36.5,
+    }
+}
+
+fn date(text: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn charges_interest_once_and_leaves_fee_invoices_alone() {
+    let db = Database::in_memory().unwrap();
+    let client_id = db
+        .add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+    let id = db
+        .add_invoice(&Invoice {
+            code: "INV-1001".to_string(),
+            client: db.get_client(client_id).unwrap().unwrap(),
+            total: 1000.0,
+            currency: "EUR".to_string(),
+            status: "sent".to_string(),
+            date: "2026-01-01".to_string(),
+            due_date: "2026-01-31".to_string(),
+            ..Invoice::default()
+        })
+        .unwrap();
+    let config = DunningConfig {
+        levels: vec![level("First", 10), level("Second", 20)],
+    };
+
+    // 36.5% a year is 0.1% a day, so a euro per day on 1000.
+    let pending = dunning::pending(&db, &config, date("2026-02-10")).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].interest_days, 10);
+    assert_eq!(pending[0].interest, 10.0);
+    let first = Reminder {
+        id: 0,
+        invoice_id: id,
+        level: 1,
+        name: "First".to_string(),
+        date: "2026-02-10".to_string(),
+        fees: pending[0].fees(),
+        status: "sent".to_string(),
+    };
+    db.add_reminder(&first, &[Item::new("Late fee", Some(20.0), Some(1), None)])
+        .unwrap();
+
+    // The fee invoice is due at once but isn't reminded of.
+    let pending = dunning::pending(&db, &config, date("2026-02-25")).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].invoice.code, "INV-1001");
+    assert_eq!(pending[0].level, 1);
+    assert_eq!(pending[0].days_overdue, 25);
+    assert_eq!(pending[0].interest_days, 15);
+    assert_eq!(pending[0].interest, 15.0);
+}
@@ -0,0 +1,215 @@
+//! Golden files for the UBL and Factur-X (CII) XML. Run with
+//! `UPDATE_GOLDEN=1` to rewrite the files in `tests/fixtures` after an
+//! intended change to the templates, and review the diff.
+
+use std::fs;
+
+use invoice_rs::config::IssuerConfig;
+use invoice_rs::facturx::{self, Profile};
+use invoice_rs::models::{Client, Invoice, Item};
+use invoice_rs::ubl;
+use roxmltree::{Document, Node};
+
+const CBC: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2";
+const CAC: &str = "urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2";
+const RSM: &str = "urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100";
+const RAM: &str =
+    "urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100";
+const UDT: &str = "urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100";
+
+fn issuer() -> IssuerConfig {
+    IssuerConfig {
+        name: "Acme GmbH".to_string(),
+        street: "Hauptstr. 1".to_string(),
+        city: "Berlin".to_string(),
+        postal_code: "10115".to_string(),
+        country: "DE".to_string(),
+        vat_id: "DE123456789".to_string(),
+        endpoint: "9930:DE123456789".to_string(),
+        email: "billing@acme.example".to_string(),
+        iban: "DE89 3704 0044 0532 0130 00".to_string(),
+        bic: "COBADEFFXXX".to_string(),
+    }
+}
+
+fn client() -> Client {
+    Client {
+        country: "NL".to_string(),
+        vat_id: "NL123456789B01".to_string(),
+        endpoint: "0106:12345678".to_string(),
+        reference: "PO-77".to_string(),
+        ..Client::new(
+            7,
+            "Globex B.V.",
+            "+31 20 123 4567",
+            "ap@globex.example",
+            "Damrak 1",
+        )
+    }
+}
+
+/// 800.00 of lines at 19 %, less a 50.00 discount.
+fn standard() -> Invoice {
+    Invoice {
+        id: 1,
+        code: "INV-1001".to_string(),
+        client: client(),
+        total: 800.0,
+        currency: "EUR".to_string(),
+        discount: 50.0,
+        tax: 142.5,
+        status: "sent".to_string(),
+        date: "2026-01-15".to_string(),
+        due_date: "2026-02-14".to_string(),
+        items: vec![
+            Item::new(
+                "Consulting",
+                Some(90.0),
+                Some(8),
+                Some("January".to_string()),
+            ),
+            Item::new("Travel", Some(80.0), Some(1), None),
+        ],
+        ..Invoice::default()
+    }
+}
+
+/// Lines taxed at 0 %, e.g. exports.
+fn zero_rated() -> Invoice {
+    let mut item = Item::new("Licence", Some(250.0), Some(2), None);
+    item.tax_rate = Some(0.0);
+    Invoice {
+        id: 2,
+        code: "INV-1002".to_string(),
+        client: client(),
+        total: 500.0,
+        currency: "EUR".to_string(),
+        status: "sent".to_string(),
+        date: "2026-01-20".to_string(),
+        due_date: "2026-02-19".to_string(),
+        items: vec![item],
+        ..Invoice::default()
+    }
+}
+
+fn credit_note() -> Invoice {
+    standard().credit_note("CN-INV-1001", "2026-02-01")
+}
+
+/// Golden file name and rendered XML of every case.
+fn documents() -> Vec<(&'static str, String)> {
+    let issuer = issuer();
+    let ubl = |invoice: &Invoice| ubl::render(invoice, &issuer).unwrap();
+    let cii = |invoice: &Invoice| facturx::render(invoice, &issuer, Profile::En16931).unwrap();
+    vec![
+        ("ubl_standard.xml", ubl(&standard())),
+        ("ubl_zero_rated.xml", ubl(&zero_rated())),
+        ("ubl_credit_note.xml", ubl(&credit_note())),
+        ("cii_standard.xml", cii(&standard())),
+        ("cii_zero_rated.xml", cii(&zero_rated())),
+        ("cii_credit_note.xml", cii(&credit_note())),
+    ]
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Node<'a, 'input> {
+    node.children()
+        .find(|child| child.has_tag_name((ns, name)))
+        .unwrap_or_else(|| panic!("no {} in {}", name, node.tag_name().name()))
+}
+
+fn text<'a>(node: Node<'a, '_>, ns: &str, name: &str) -> &'a str {
+    child(node, ns, name).text().unwrap_or_default()
+}
+
+#[test]
+fn xml_matches_golden_files() {
+    for (name, xml) in documents() {
+        let path = format!("tests/fixtures/{}", name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::write(&path, &xml).unwrap();
+            continue;
+        }
+        let golden = fs::read_to_string(&path).unwrap();
+        assert!(xml == golden, "{} differs from its golden file", name);
+    }
+}
+
+#[test]
+fn ubl_states_id_date_and_totals() {
+    let issuer = issuer();
+    for (invoice, root, tax, payable) in [
+        (standard(), "Invoice", "142.50", "892.50"),
+        (zero_rated(), "Invoice", "0.00", "500.00"),
+        (credit_note(), "CreditNote", "142.50", "892.50"),
+    ] {
+        let xml = ubl::render(&invoice, &issuer).unwrap();
+        let doc = Document::parse(&xml).unwrap();
+        let document = doc.root_element();
+        assert_eq!(document.tag_name().name(), root);
+        assert_eq!(text(document, CBC, "ID"), invoice.code);
+        assert_eq!(text(document, CBC, "IssueDate"), invoice.date);
+
+        let tax_total = child(document, CAC, "TaxTotal");
+        assert_eq!(text(tax_total, CBC, "TaxAmount"), tax);
+        let totals = child(document, CAC, "LegalMonetaryTotal");
+        assert_eq!(text(totals, CBC, "TaxInclusiveAmount"), payable);
+        assert_eq!(text(totals, CBC, "PayableAmount"), payable);
+    }
+}
+
+#[test]
+fn ubl_credit_note_refers_to_the_invoice() {
+    let xml = ubl::render(&credit_note(), &issuer()).unwrap();
+    let doc = Document::parse(&xml).unwrap();
+    let document = doc.root_element();
+    assert_eq!(text(document, CBC, "CreditNoteTypeCode"), "381");
+    let reference = child(
+        child(document, CAC, "BillingReference"),
+        CAC,
+        "InvoiceDocumentReference",
+    );
+    assert_eq!(text(reference, CBC, "ID"), "INV-1001");
+    assert_eq!(
+        text(
+            child(document, CAC, "CreditNoteLine"),
+            CBC,
+            "CreditedQuantity"
+        ),
+        "8"
+    );
+}
+
+#[test]
+fn cii_states_id_date_and_totals() {
+    let issuer = issuer();
+    for (invoice, type_code, tax, payable) in [
+        (standard(), "380", "142.50", "892.50"),
+        (zero_rated(), "380", "0.00", "500.00"),
+        (credit_note(), "381", "142.50", "892.50"),
+    ] {
+        let xml = facturx::render(&invoice, &issuer, Profile::En16931).unwrap();
+        let doc = Document::parse(&xml).unwrap();
+        let document = child(doc.root_element(), RSM, "ExchangedDocument");
+        assert_eq!(text(document, RAM, "ID"), invoice.code);
+        assert_eq!(text(document, RAM, "TypeCode"), type_code);
+        let issued = child(document, RAM, "IssueDateTime");
+        assert_eq!(
+            text(issued, UDT, "DateTimeString"),
+            invoice.date.replace('-', "")
+        );
+
+        let settlement = child(
+            child(doc.root_element(), RSM, "SupplyChainTradeTransaction"),
+            RAM,
+            "ApplicableHeaderTradeSettlement",
+        );
+        let totals = child(
+            settlement,
+            RAM,
+            "SpecifiedTradeSettlementHeaderMonetarySummation",
+        );
+        assert_eq!(text(totals, RAM, "TaxTotalAmount"), tax);
+        assert_eq!(text(totals, RAM, "GrandTotalAmount"), payable);
+        assert_eq!(text(totals, RAM, "DuePayableAmount"), payable);
+    }
+}
@@ -0,0 +1,107 @@
+//! The document an issued invoice keeps.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use invoice_rs::config::IssuerConfig;
+use invoice_rs::db::Database;
+use invoice_rs::error::AppError;
+use invoice_rs::models::{Client, Invoice, Item, Project};
+use invoice_rs::pdf::{self, PdfConfig};
+use lopdf::{Document, Object, dictionary};
+
+static PRINTED: AtomicUsize = AtomicUsize::new(0);
+
+/// Stands in for headless Chrome: an empty page that comes out different
+/// every time it's printed.
+fn printer(_html: &str) -> Result<Vec<u8>, AppError> {
+    let count = PRINTED.fetch_add(1, Ordering::SeqCst);
+    let mut doc = Document::with_version("1.7");
+    let pages = doc.new_object_id();
+    let page = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages,
+        "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+    });
+    doc.objects.insert(
+        pages,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages,
+    });
+    let info = doc.add_object(dictionary! {
+        "Subject" => Object::string_literal(count.to_string()),
+    });
+    doc.trailer.set("Root", catalog);
+    doc.trailer.set("Info", info);
+    let mut pdf = Vec::new();
+    doc.save_to(&mut pdf)?;
+    Ok(pdf)
+}
+
+#[test]
+fn an_issued_invoice_keeps_its_first_pdf_and_project() {
+    let db = Database::in_memory().unwrap();
+    let client_id = db
+        .add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+    let project_id = db
+        .add_project(&Project {
+            id: 0,
+            client_id,
+            name: "Rebranding".to_string(),
+            po_number: "PO-7".to_string(),
+            budget: 0.0,
+            rate: 90.0,
+            status: "active".to_string(),
+        })
+        .unwrap();
+    let project = db
+        .get_projects()
+        .unwrap()
+        .into_iter()
+        .find(|project| project.id == project_id);
+    let id = db
+        .add_invoice(&Invoice {
+            code: "INV-1001".to_string(),
+            client: db.get_client(client_id).unwrap().unwrap(),
+            total: 720.0,
+            currency: "EUR".to_string(),
+            status: "sent".to_string(),
+            date: "2026-01-15".to_string(),
+            items: vec![Item::new("Consulting", Some(90.0), Some(8), None)],
+            project,
+            ..Invoice::default()
+        })
+        .unwrap();
+    let config = PdfConfig {
+        facturx: None,
+        printer,
+    };
+    let issuer = IssuerConfig::default();
+
+    let invoice = db.get_invoice(id).unwrap().unwrap();
+    let first = pdf::invoice_pdf(&db, &invoice, &issuer, &config).unwrap();
+    let printed = PRINTED.load(Ordering::SeqCst);
+    let invoice = db.get_invoice(id).unwrap().unwrap();
+    assert_eq!(invoice.pdf_hash, pdf::hash(&first));
+    let again = pdf::invoice_pdf(&db, &invoice, &issuer, &config).unwrap();
+    assert_eq!(again, first);
+    assert_eq!(PRINTED.load(Ordering::SeqCst), printed);
+
+    // The project as it was when the invoice was issued.
+    db.set_project_status(project_id, "done").unwrap();
+    let invoice = db.get_invoice(id).unwrap().unwrap();
+    assert_eq!(invoice.project.unwrap().status, "active");
+}
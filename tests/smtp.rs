@@ -0,0 +1,138 @@
+//! Mail delivery against an SMTP sink running on a local port.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use invoice_rs::mail::{Email, MailConfig};
+use invoice_rs::smtp;
+
+/// What the sink was sent: the commands, and the message after DATA.
+#[derive(Default)]
+struct Transcript {
+    commands: Vec<String>,
+    message: String,
+}
+
+/// Accept one connection on a free port and answer like a server that
+/// offers AUTH but not STARTTLS, until QUIT or the client goes away.
+fn sink() -> (u16, JoinHandle<Transcript>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut transcript = Transcript::default();
+        writer.write_all(b"220 sink ESMTP\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let command = line.trim_end().to_string();
+            let reply: &[u8] = match command.split(' ').next().unwrap_or_default() {
+                "EHLO" => b"250-sink\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n",
+                "AUTH" => b"235 ok\r\n",
+                "DATA" => {
+                    writer.write_all(b"354 go ahead\r\n").unwrap();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        // Undo the dot stuffing.
+                        let line = line.strip_prefix('.').unwrap_or(&line);
+                        transcript.message.push_str(line);
+                    }
+                    b"250 queued\r\n"
+                }
+                "QUIT" => b"221 bye\r\n",
+                _ => b"250 ok\r\n",
+            };
+            transcript.commands.push(command.clone());
+            writer.write_all(reply).unwrap();
+            if command == "QUIT" {
+                break;
+            }
+        }
+        transcript
+    });
+    (port, handle)
+}
+
+fn config(port: u16, username: &str) -> MailConfig {
+    toml::from_str(&format!(
+        "host = \"127.0.0.1\"\nport = {}\nsecurity = \"none\"\nusername = \"{}\"\npassword = \"secret\"",
+        port, username
+    ))
+    .unwrap()
+}
+
+fn email() -> Email {
+    Email {
+        from: "billing@acme.example".to_string(),
+        from_name: "Acme GmbH".to_string(),
+        to: "ap@globex.example".to_string(),
+        subject: "Invoice INV-1001".to_string(),
+        body: "Please find the invoice attached.".to_string(),
+        attachment_name: "invoice_INV-1001.pdf".to_string(),
+        attachment: b"%PDF-1.7 not really a pdf".repeat(20),
+    }
+}
+
+#[test]
+fn delivers_the_message_with_its_attachment() {
+    let (port, handle) = sink();
+    let email = email();
+    smtp::send(&config(port, ""), &email.from, &email.to, &email.to_mime()).unwrap();
+    let transcript = handle.join().unwrap();
+
+    assert_eq!(
+        transcript.commands,
+        [
+            "EHLO acme.example",
+            "MAIL FROM:<billing@acme.example>",
+            "RCPT TO:<ap@globex.example>",
+            "DATA",
+            "QUIT",
+        ]
+    );
+    let message = transcript.message;
+    assert!(message.contains("To: ap@globex.example\r\n"));
+    assert!(message.contains("Subject: Invoice INV-1001\r\n"));
+    let boundary = message
+        .split("boundary=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let parts: Vec<&str> = message.split(&format!("--{}", boundary)).collect();
+    // Headers, body, attachment and the closing "--".
+    assert_eq!(parts.len(), 4);
+    let (headers, content) = parts[2].split_once("\r\n\r\n").unwrap();
+    assert!(headers.contains("Content-Type: application/pdf; name=\"invoice_INV-1001.pdf\""));
+    assert!(headers.contains("filename=\"invoice_INV-1001.pdf\""));
+    assert!(content.lines().all(|line| line.len() <= 76));
+    let decoded = STANDARD.decode(content.replace("\r\n", "")).unwrap();
+    assert_eq!(decoded, email.attachment);
+}
+
+#[test]
+fn refuses_to_log_in_without_tls() {
+    let (port, handle) = sink();
+    let email = email();
+    let error = smtp::send(
+        &config(port, "billing"),
+        &email.from,
+        &email.to,
+        &email.to_mime(),
+    )
+    .unwrap_err();
+    let transcript = handle.join().unwrap();
+
+    assert!(error.to_string().contains("unencrypted"), "{}", error);
+    assert_eq!(transcript.commands, ["EHLO acme.example"]);
+}
@@ -0,0 +1,178 @@
+//! The CSV reader and writer, column mapping, and a full export read back
+//! into an empty database.
+
+use invoice_rs::credit_note;
+use invoice_rs::csv;
+use invoice_rs::db::Database;
+use invoice_rs::models::{Client, Invoice, InvoiceFilter, InvoiceSort, Item};
+use invoice_rs::transfer::{self, CsvFile, ImportKind, Mapping, Records};
+
+fn headers(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn file(text: &str) -> CsvFile {
+    let mut records = csv::parse(text).unwrap().into_iter();
+    CsvFile {
+        headers: records.next().unwrap(),
+        rows: records.collect(),
+    }
+}
+
+fn client() -> Client {
+    Client::new(0, "Globex B.V.", "", "ap@globex.example", "Damrak 1")
+}
+
+#[test]
+fn parses_quoted_fields_and_line_endings() {
+    let text =
+        "\u{feff}name,note\r\n\"Acme, Inc.\",\"said \"\"hi\"\"\nthen left\"\r\n\r\nGlobex,\n";
+    assert_eq!(
+        csv::parse(text).unwrap(),
+        [
+            vec!["name", "note"],
+            vec!["Acme, Inc.", "said \"hi\"\nthen left"],
+            vec!["Globex", ""],
+        ]
+    );
+    assert_eq!(
+        csv::parse_with("a;\"1,5\"\n", ';').unwrap(),
+        [vec!["a", "1,5"]]
+    );
+    let error = csv::parse("a,b\n\"open,c\n").unwrap_err();
+    assert_eq!(error, "unterminated quoted field on line 3");
+}
+
+#[test]
+fn escapes_formulas_and_reads_them_back() {
+    let values = [
+        "=HYPERLINK(\"http://evil.example\")",
+        "+1",
+        "-720",
+        "@SUM(A1)",
+        "'=kept",
+        "plain, with comma",
+        "it's fine",
+    ];
+    let mut out = String::new();
+    csv::write_record(&mut out, &values);
+    assert!(
+        out.starts_with(
+            "\"'=HYPERLINK(\"\"http://evil.example\"\")\",'+1,'-720,'@SUM(A1),''=kept,"
+        )
+    );
+    assert_eq!(csv::parse(&out).unwrap(), [values.to_vec()]);
+}
+
+#[test]
+fn guesses_columns_by_name_and_alias() {
+    let names = headers(&["Code", "Client", "E-Mail", "Qty", "Item", "Notes"]);
+    let mapping = Mapping::guess(ImportKind::Invoices, &names);
+    assert_eq!(
+        mapping.columns,
+        [
+            Some("code"),
+            Some("client_email"),
+            // Already mapped to the first matching column.
+            None,
+            Some("item_quantity"),
+            Some("item_title"),
+            None,
+        ]
+    );
+    assert_eq!(mapping.missing(), ["date"]);
+}
+
+#[test]
+fn applies_overrides_to_the_guessed_mapping() {
+    let names = headers(&["Code", "Client", "Issued", "Notes"]);
+    let mut mapping = Mapping::guess(ImportKind::Invoices, &names);
+    mapping
+        .apply(&names, &["issued=date".to_string(), "client=".to_string()])
+        .unwrap();
+    assert_eq!(mapping.columns, [Some("code"), None, Some("date"), None]);
+    assert_eq!(mapping.missing(), ["client_email"]);
+
+    for bad in ["issued", "Missing=date", "issued=nonsense"] {
+        assert!(
+            mapping.apply(&names, &[bad.to_string()]).is_err(),
+            "{}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn reports_row_errors_and_duplicates() {
+    let db = Database::in_memory().unwrap();
+    db.add_client(&client()).unwrap();
+    let file = file(
+        "name,email\n\
+         Initech,billing@initech.example\n\
+         Globex again,AP@globex.example\n\
+         ,nobody@example.com\n",
+    );
+    let mapping = Mapping::guess(ImportKind::Clients, &file.headers);
+    let preview = transfer::preview(&db, &file, &mapping).unwrap();
+    assert_eq!(preview.count(), 1);
+    assert_eq!(preview.duplicates.len(), 1);
+    assert_eq!(preview.duplicates[0].row, 3);
+    assert_eq!(preview.errors.len(), 1);
+    assert_eq!(preview.errors[0].row, 4);
+}
+
+#[test]
+fn a_full_export_imports_again_with_its_credit_notes() {
+    let source = Database::in_memory().unwrap();
+    let client_id = source.add_client(&client()).unwrap();
+    let invoice = Invoice {
+        code: "INV-1001".to_string(),
+        client: source.get_client(client_id).unwrap().unwrap(),
+        total: 720.0,
+        currency: "EUR".to_string(),
+        status: "sent".to_string(),
+        date: "2026-01-15".to_string(),
+        due_date: "2026-02-14".to_string(),
+        items: vec![Item::new("=cmd|' /C calc'!A0", Some(90.0), Some(8), None)],
+        ..Invoice::default()
+    };
+    let id = source.add_invoice(&invoice).unwrap();
+    let note = credit_note::create(&source, id, "2026-01-20").unwrap();
+    let exported =
+        transfer::export_invoices(&source, &InvoiceFilter::default(), InvoiceSort::default())
+            .unwrap();
+
+    let target = Database::in_memory().unwrap();
+    target.add_client(&client()).unwrap();
+    let file = file(&exported);
+    let mapping = Mapping::guess(ImportKind::Invoices, &file.headers);
+    assert!(mapping.columns.iter().all(Option::is_some));
+    let preview = transfer::preview(&target, &file, &mapping).unwrap();
+    assert!(preview.errors.is_empty(), "{}", preview.errors[0]);
+    let Records::Invoices(invoices) = &preview.records else {
+        panic!("expected invoices");
+    };
+    let imported = invoices.iter().find(|i| i.code == note.code).unwrap();
+    assert_eq!(imported.credit_note_for, "INV-1001");
+    assert_eq!(imported.total, -720.0);
+    let original = invoices.iter().find(|i| i.code == "INV-1001").unwrap();
+    assert_eq!(original.items[0].title, "=cmd|' /C calc'!A0");
+    assert_eq!(transfer::commit(&target, &preview).unwrap(), 2);
+}
+
+#[test]
+fn refuses_a_credit_note_for_an_unknown_invoice() {
+    let db = Database::in_memory().unwrap();
+    db.add_client(&client()).unwrap();
+    let file = file(
+        "code,client_email,currency,total,date,credit_note_for\n\
+         CN-1,ap@globex.example,EUR,-100,2026-01-20,INV-404\n",
+    );
+    let mapping = Mapping::guess(ImportKind::Invoices, &file.headers);
+    let preview = transfer::preview(&db, &file, &mapping).unwrap();
+    assert_eq!(preview.count(), 0);
+    assert_eq!(
+        preview.errors[0].to_string(),
+        "row 2: credits unknown invoice INV-404"
+    );
+}
@@ -0,0 +1,207 @@
+//! Webhook delivery to a receiver running on a local port.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use chrono::Utc;
+use invoice_rs::db::Database;
+use invoice_rs::models::{Client, Invoice};
+use invoice_rs::webhook::{self, Endpoint, WebhookConfig};
+
+const SECRET: &str = "whsec_test";
+
+/// A request as the receiver saw it, header names lowercased.
+struct Received {
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+/// Answer one request per status in `statuses`, in order, then stop.
+fn receiver(statuses: &'static [u16]) -> (u16, JoinHandle<Vec<Received>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let mut received = Vec::new();
+        for status in statuses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = HashMap::new();
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("POST /hook "), "{}", request_line);
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let Some((name, value)) = line.trim_end().split_once(": ") else {
+                    break;
+                };
+                headers.insert(name.to_ascii_lowercase(), value.to_string());
+            }
+            let length = headers["content-length"].parse().unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            writer
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            received.push(Received {
+                headers,
+                body: String::from_utf8(body).unwrap(),
+            });
+        }
+        received
+    });
+    (port, handle)
+}
+
+/// A database with one new invoice, so one `invoice.created` event.
+fn database() -> Mutex<Database> {
+    let db = Database::in_memory().unwrap();
+    let client_id = db
+        .add_client(&Client::new(
+            0,
+            "Globex B.V.",
+            "",
+            "ap@globex.example",
+            "Damrak 1",
+        ))
+        .unwrap();
+    let invoice = Invoice {
+        code: "INV-1001".to_string(),
+        client: db.get_client(client_id).unwrap().unwrap(),
+        total: 720.0,
+        currency: "EUR".to_string(),
+        status: "draft".to_string(),
+        date: "2026-01-15".to_string(),
+        ..Invoice::default()
+    };
+    db.add_invoice(&invoice).unwrap();
+    Mutex::new(db)
+}
+
+fn config(port: u16) -> WebhookConfig {
+    WebhookConfig {
+        endpoints: vec![Endpoint {
+            url: format!("http://127.0.0.1:{}/hook", port),
+            secret: SECRET.to_string(),
+            events: Vec::new(),
+        }],
+        max_attempts: 3,
+        backoff_secs: 30,
+        timeout_secs: 5,
+    }
+}
+
+#[test]
+fn signs_the_event_and_retries_after_a_server_error() {
+    let (port, handle) = receiver(&[500, 204]);
+    let db = database();
+    let config = config(port);
+    let now = Utc::now().timestamp();
+
+    assert_eq!(webhook::run_once(&db, &config, now).unwrap(), 0);
+    let deliveries = db.lock().unwrap().get_deliveries(10).unwrap();
+    assert_eq!(deliveries.len(), 1);
+    let delivery = &deliveries[0];
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.response_code, Some(500));
+    assert_eq!(delivery.next_attempt_at, now + 30);
+
+    // Not due yet, so nothing is sent.
+    assert_eq!(webhook::run_once(&db, &config, now + 29).unwrap(), 0);
+    assert_eq!(
+        db.lock().unwrap().get_deliveries(10).unwrap()[0].attempts,
+        1
+    );
+
+    assert_eq!(webhook::run_once(&db, &config, now + 30).unwrap(), 1);
+    let delivery = &db.lock().unwrap().get_deliveries(10).unwrap()[0];
+    assert_eq!(delivery.status, "delivered");
+    assert_eq!(delivery.attempts, 2);
+    assert_eq!(delivery.response_code, Some(204));
+
+    let received = handle.join().unwrap();
+    assert_eq!(received.len(), 2);
+    for (request, timestamp) in received.iter().zip([now, now + 30]) {
+        let headers = &request.headers;
+        assert_eq!(headers["x-invoice-rs-event"], "invoice.created");
+        assert_eq!(headers["x-invoice-rs-delivery"], delivery.id.to_string());
+        assert_eq!(headers["x-invoice-rs-timestamp"], timestamp.to_string());
+        let signature = webhook::sign(SECRET, &timestamp.to_string(), &request.body);
+        assert_eq!(
+            headers["x-invoice-rs-signature"],
+            format!("sha256={}", signature)
+        );
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["type"], "invoice.created");
+        assert_eq!(body["data"]["invoice"]["code"], "INV-1001");
+    }
+    assert_eq!(received[0].body, received[1].body);
+}
+
+#[test]
+fn a_claimed_delivery_is_left_to_its_owner_until_the_claim_lapses() {
+    // Nothing listens on the port of a dropped listener, so attempts fail
+    // without a receiver.
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let db = database();
+    let config = config(port);
+    let now = Utc::now().timestamp();
+    {
+        let db = db.lock().unwrap();
+        let event = db.undispatched_events().unwrap().remove(0);
+        db.queue_deliveries(event.id, &[&config.endpoints[0].url], now)
+            .unwrap();
+        // A second dispatcher finding the same event queues nothing.
+        db.queue_deliveries(event.id, &[&config.endpoints[0].url], now)
+            .unwrap();
+        let claimed = db.claim_delivery(now, "other", now + 60).unwrap();
+        assert!(claimed.is_some());
+        assert!(
+            db.claim_delivery(now, "another", now + 60)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    assert_eq!(webhook::run_once(&db, &config, now).unwrap(), 0);
+    let deliveries = db.lock().unwrap().get_deliveries(10).unwrap();
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].attempts, 0);
+
+    webhook::run_once(&db, &config, now + 60).unwrap();
+    let delivery = &db.lock().unwrap().get_deliveries(10).unwrap()[0];
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.status, "pending");
+    assert!(delivery.response_code.is_none());
+}
+
+#[test]
+fn looks_for_overdue_invoices_once_a_day() {
+    let db = database();
+    let db = db.lock().unwrap();
+    let mut invoice = db.get_invoice(1).unwrap().unwrap();
+    invoice.status = "sent".to_string();
+    invoice.due_date = "2026-01-30".to_string();
+    db.update_invoice(&invoice).unwrap();
+
+    assert_eq!(db.emit_overdue("2026-01-30").unwrap(), 0);
+    // Already checked today, though the invoice is overdue by now.
+    assert_eq!(db.emit_overdue("2026-01-30").unwrap(), 0);
+    assert_eq!(db.emit_overdue("2026-01-31").unwrap(), 1);
+    assert_eq!(db.emit_overdue("2026-02-01").unwrap(), 0);
+}